@@ -1,3 +1,14 @@
 fn main() {
+    // The OS keyring is used on macOS and Windows, and on Linux only with the
+    // `secret-service` feature, which links libdbus
+    println!("cargo::rustc-check-cfg=cfg(os_keyring)");
+    let os = std::env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    let keyring = std::env::var_os("CARGO_FEATURE_KEYRING").is_some();
+    let secret_service = std::env::var_os("CARGO_FEATURE_SECRET_SERVICE").is_some();
+    if keyring && (matches!(os.as_str(), "macos" | "windows") || (os == "linux" && secret_service))
+    {
+        println!("cargo::rustc-cfg=os_keyring");
+    }
+
     tauri_build::build()
 }
@@ -8,13 +8,14 @@ use std::{
     path::{Path, PathBuf},
 };
 use tauri::Manager;
+use tracing::{error, info, warn};
 use walkdir::WalkDir;
 
 pub async fn app_dir(app_handle: &tauri::AppHandle) -> AppResult<PathBuf> {
     let app_data_dir = app_handle
         .path()
         .app_data_dir()
-        .map_err(|e| AppError::Unknown(e.to_string()))?;
+        .map_err(|e| AppError::PathError(e.to_string()))?;
 
     if !tokio::fs::try_exists(&app_data_dir).await.unwrap_or(false) {
         tokio::fs::create_dir_all(&app_data_dir)
@@ -24,11 +25,28 @@ pub async fn app_dir(app_handle: &tauri::AppHandle) -> AppResult<PathBuf> {
     Ok(app_data_dir)
 }
 
+/// Scratch space that is not counted as app storage.
+pub fn cache_dir(app_handle: &tauri::AppHandle) -> AppResult<PathBuf> {
+    app_handle
+        .path()
+        .app_cache_dir()
+        .map_err(|e| AppError::PathError(e.to_string()))
+}
+
+/// Per-device state such as the credential key, left out of backups and
+/// sync.
+pub fn device_dir(app_handle: &tauri::AppHandle) -> AppResult<PathBuf> {
+    app_handle
+        .path()
+        .app_config_dir()
+        .map_err(|e| AppError::PathError(e.to_string()))
+}
+
 pub fn external_dir(app_handle: &tauri::AppHandle) -> AppResult<PathBuf> {
     Ok(app_handle
         .path()
         .home_dir()
-        .map_err(|e| AppError::Unknown(e.to_string()))?
+        .map_err(|e| AppError::PathError(e.to_string()))?
         .join(crate::core::APP_NAME))
 }
 
@@ -42,6 +60,18 @@ async fn write<P: AsRef<Path>, C: AsRef<[u8]>>(p: P, c: C) -> std::io::Result<()
     tokio::fs::write(p, c).await
 }
 
+/// Compute the SHA-256 of a file as a lowercase hex string.
+/// The file is streamed through the hasher, so large audio files are never
+/// loaded into memory.
+pub fn hash_file<P: AsRef<Path>>(path: P) -> std::io::Result<String> {
+    use sha2::{Digest, Sha256};
+
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
 pub fn get_audio_filename(audio: &Audio) -> String {
     let id = format!("{:x}", md5::compute(&audio.download_url));
     format!(
@@ -62,7 +92,16 @@ pub fn get_cover_filename(cover_url: &str) -> String {
     format!("{id}_{filename}")
 }
 
-pub async fn download_audio(audio: &Audio, app_dir: PathBuf) -> anyhow::Result<LocalAudio> {
+#[tracing::instrument(skip_all, fields(id = %audio.id, platform = ?audio.platform))]
+pub async fn download_audio(audio: &Audio, app_dir: PathBuf) -> AppResult<LocalAudio> {
+    // Search hits are only extracted once downloaded, their stream URLs expire
+    let resolved;
+    let audio = if crate::discover::is_page_url(&audio.download_url) {
+        resolved = crate::discover::resolve(audio).await?;
+        &resolved
+    } else {
+        audio
+    };
     let filename = get_audio_filename(audio);
     let audio_path = format!(
         "{}/{:?}/{}/{}",
@@ -70,45 +109,145 @@ pub async fn download_audio(audio: &Audio, app_dir: PathBuf) -> anyhow::Result<L
     );
     let file_path = app_dir.join(&audio_path);
 
+    if let Some(demuxed) = find_demuxed(&audio_path, &app_dir).await {
+        info!("Audio already extracted, skipping download: {}", demuxed);
+        let mut local = local_audio(audio, demuxed, &app_dir).await;
+        local.audio.format = crate::demux::demuxed_format(&local.path).or(local.audio.format);
+        return Ok(local);
+    }
     if !tokio::fs::try_exists(&file_path).await.unwrap_or(false) {
-        println!("Downloading audio: {}", audio.title);
+        info!("Downloading audio: {}", audio.title);
         let download_future = audio.platform.extractor().download(&audio.download_url);
         let bin =
             match tokio::time::timeout(std::time::Duration::from_secs(60), download_future).await {
                 Ok(Ok(data)) => data,
                 Ok(Err(e)) => {
-                    eprintln!("Download failed for '{}': {e}", audio.title);
-                    return Err(e.into());
+                    error!("Download failed for '{}': {e}", audio.title);
+                    return Err(AppError::extractor(
+                        format!("Download failed for '{}'", audio.title),
+                        e,
+                    ));
                 }
                 Err(_) => {
-                    eprintln!("Download timed out for '{}'", audio.title);
-                    return Err(anyhow::anyhow!("Download timed out for '{}'", audio.title));
+                    error!("Download timed out for '{}'", audio.title);
+                    return Err(AppError::Timeout(format!(
+                        "Download timed out for '{}'",
+                        audio.title
+                    )));
                 }
             };
 
         write(&file_path, bin).await.map_err(|e| {
-            eprintln!("Failed to write audio file for '{}': {e}", audio.title);
+            error!("Failed to write audio file for '{}': {e}", audio.title);
             AppError::Io(e)
         })?;
-        println!("Successfully downloaded audio: {}", audio_path);
+        info!("Successfully downloaded audio: {}", audio_path);
     } else {
-        println!(
+        info!(
             "Audio file already exists, skipping download: {}",
             audio_path
         );
     }
 
+    Ok(local_audio(audio, audio_path, &app_dir).await)
+}
+
+/// Describe a downloaded file, fetching the cover and captions and probing
+/// the file.
+async fn local_audio(audio: &Audio, audio_path: String, app_dir: &Path) -> LocalAudio {
     let cover_path = if let Some(url) = &audio.cover {
-        download_cover(url, audio.platform, app_dir).await
+        download_cover(url, audio.platform, app_dir.to_path_buf()).await
     } else {
         None
     };
 
-    Ok(LocalAudio {
+    // Extractors often leave the duration out, the file knows better
+    let info = probe_file(app_dir.join(&audio_path)).await;
+    let mut audio = audio.clone();
+    if let Some(duration) = info.as_ref().and_then(|i| i.duration) {
+        audio.duration = Some(duration.round() as _);
+    }
+
+    let local = LocalAudio {
         path: audio_path,
-        audio: audio.clone(),
+        external_path: None,
+        audio,
         cover_path,
-    })
+        info,
+        loudness: None,
+        start_ms: None,
+        end_ms: None,
+        lyrics_path: None,
+        fingerprint: None,
+    };
+    attach_lyrics(local, app_dir).await
+}
+
+/// Point a downloaded audio at its lyrics, converting the captions of the
+/// video when there are none stored yet. Captions are best effort: a
+/// failure or timeout only leaves the audio without lyrics.
+async fn attach_lyrics(local: LocalAudio, app_dir: &Path) -> LocalAudio {
+    let stored = crate::lyrics::lyrics_path(&local);
+    if tokio::fs::try_exists(app_dir.join(&stored))
+        .await
+        .unwrap_or(false)
+    {
+        return LocalAudio {
+            lyrics_path: Some(stored),
+            ..local
+        };
+    }
+    if !matches!(local.audio.platform, Platform::Bilibili | Platform::Youtube) {
+        return local;
+    }
+
+    let captions = crate::subtitles::Captions::default();
+    let attach = crate::lyrics::attach(&captions, &local, app_dir);
+    match tokio::time::timeout(std::time::Duration::from_secs(15), attach).await {
+        Ok(Ok(Some(attached))) => attached,
+        Ok(Ok(None)) => local,
+        Ok(Err(e)) => {
+            warn!("Captions failed for '{}': {e}", local.audio.title);
+            local
+        }
+        Err(_) => {
+            warn!("Captions timed out for '{}'", local.audio.title);
+            local
+        }
+    }
+}
+
+/// A download whose video was dropped by [`crate::demux::demux`] keeps its
+/// name with an audio extension instead.
+async fn find_demuxed(audio_path: &str, app_dir: &Path) -> Option<String> {
+    for ext in crate::demux::DEMUXED_EXTENSIONS {
+        let path = crate::demux::demuxed_path(Path::new(audio_path), ext);
+        if path.as_path() != Path::new(audio_path)
+            && tokio::fs::try_exists(app_dir.join(&path))
+                .await
+                .unwrap_or(false)
+        {
+            return Some(path.to_string_lossy().replace("\\", "/"));
+        }
+    }
+    None
+}
+
+/// Probe a downloaded file off the async runtime. Failures are logged and
+/// leave the extractor's metadata as it is.
+pub async fn probe_file(path: PathBuf) -> Option<crate::probe::AudioInfo> {
+    let result = tokio::task::spawn_blocking(move || crate::probe::probe(&path)).await;
+    match result {
+        Ok(Ok(info)) => Some(info),
+        Ok(Err(e)) => {
+            warn!("Failed to probe audio: {e}");
+            None
+        }
+        Err(e) => {
+            warn!("Probe task failed: {e}");
+            None
+        }
+    }
 }
 
 pub async fn exists_audio(audio: &Audio, app_dir: PathBuf) -> AppResult<Option<String>> {
@@ -120,7 +259,7 @@ pub async fn exists_audio(audio: &Audio, app_dir: PathBuf) -> AppResult<Option<S
     let file_path = app_dir.join(&audio_path);
 
     if !tokio::fs::try_exists(&file_path).await.unwrap_or(false) {
-        return Ok(None);
+        return Ok(find_demuxed(&audio_path, &app_dir).await);
     }
     Ok(Some(audio_path))
 }
@@ -142,6 +281,7 @@ pub async fn exists_cover(
     Ok(None)
 }
 
+#[tracing::instrument(skip(app_dir))]
 pub async fn download_cover(
     cover_url: &str,
     platform: Platform,
@@ -160,13 +300,13 @@ pub async fn download_cover(
     match tokio::time::timeout(std::time::Duration::from_secs(30), download_future).await {
         Ok(Ok(cover_data)) => {
             if let Err(e) = write(&full_cover_path, &cover_data).await {
-                eprintln!("Failed to write cover file: {e}");
+                warn!("Failed to write cover file: {e}");
             } else {
                 return Some(cover_path);
             }
         }
-        Ok(Err(e)) => eprintln!("Cover download failed: {e}"),
-        Err(_) => eprintln!("Cover download timed out"),
+        Ok(Err(e)) => warn!("Cover download failed: {e}"),
+        Err(_) => warn!("Cover download timed out"),
     }
     None
 }
@@ -178,10 +318,15 @@ pub fn get_used_paths(config: &Config) -> HashSet<String> {
             used_paths.insert(cover_path.replace("\\", "/"));
         }
         for audio in &playlist.audios {
-            used_paths.insert(audio.path.replace("\\", "/"));
+            if !audio.is_external() {
+                used_paths.insert(audio.path.replace("\\", "/"));
+            }
             if let Some(ref cover_path) = audio.cover_path {
                 used_paths.insert(cover_path.replace("\\", "/"));
             }
+            if let Some(ref lyrics_path) = audio.lyrics_path {
+                used_paths.insert(lyrics_path.replace("\\", "/"));
+            }
         }
     }
     used_paths
@@ -204,27 +349,32 @@ pub fn get_save_filename(audio: &musicfree::Audio) -> String {
     )
 }
 
-pub async fn save_audio(
+/// Where [`save_audio`] puts an audio, relative to the target directory:
+/// `Platform/playlist_title/filename`.
+pub fn get_save_path(
     playlist: &crate::core::LocalPlaylist,
     audio: &crate::core::LocalAudio,
-    app_dir: PathBuf,
-    target_dir: PathBuf,
-) -> AppResult<String> {
+) -> PathBuf {
     let filename = get_save_filename(&audio.audio);
     let playlist_title =
         sanitize_filename::sanitize(playlist.title.as_deref().unwrap_or("Unknown"));
+    PathBuf::from(format!("{:?}", audio.audio.platform))
+        .join(playlist_title)
+        .join(filename)
+}
 
-    // Build target path: target_dir / Platform / playlist_title / filename
-    let dest_path = target_dir
-        .join(format!("{:?}", audio.audio.platform))
-        .join(&playlist_title)
-        .join(&filename);
+pub async fn save_audio(
+    playlist: &crate::core::LocalPlaylist,
+    audio: &crate::core::LocalAudio,
+    app_dir: PathBuf,
+    target_dir: PathBuf,
+) -> AppResult<String> {
+    let dest_path = target_dir.join(get_save_path(playlist, audio));
 
-    // Source path: app_dir / audio.path
-    let src_path = app_dir.join(&audio.path);
+    let src_path = audio.file(&app_dir);
 
     if !tokio::fs::try_exists(&src_path).await.unwrap_or(false) {
-        return Err(AppError::Unknown(format!(
+        return Err(AppError::NotFound(format!(
             "Source audio file not found: {}",
             src_path.display()
         )));
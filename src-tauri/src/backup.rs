@@ -0,0 +1,784 @@
+use crate::{
+    api,
+    core::{ASSETS_DIR, CONFIG_FILE, Config, HISTORY_FILE, get_config_path},
+    error::{AppError, AppResult},
+    progress::Task,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+use zip::write::FileOptions;
+
+/// Every backup archive is named `musicfree-{timestamp}[-incremental].zip`.
+pub const BACKUP_PREFIX: &str = "musicfree-";
+
+/// Manifest stored at the root of every archive written by this version.
+pub const BACKUP_MANIFEST_FILE: &str = "manifest.json";
+
+const BACKUP_MANIFEST_VERSION: u32 = 1;
+
+/// Imports are staged in `{cache_dir}/import-staging/{uuid}`, out of the
+/// app data counted as storage.
+pub const STAGING_DIR: &str = "import-staging";
+
+/// Where older versions staged imports, inside the app data directory.
+const LEGACY_STAGING_DIR: &str = ".import-staging";
+
+/// Held locked by the import using a staging directory, so a second instance
+/// cleaning up never removes an import still in progress.
+const STAGING_LOCK_FILE: &str = ".lock";
+
+/// Largest asset accepted from a backup.
+const MAX_ENTRY_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Largest config or manifest accepted from a backup.
+const MAX_JSON_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Largest total uncompressed size of one archive.
+const MAX_ARCHIVE_SIZE: u64 = 256 * 1024 * 1024 * 1024;
+
+/// Entries above `MAX_RATIO_MIN_SIZE` may not inflate more than this.
+const MAX_COMPRESSION_RATIO: u64 = 100;
+const MAX_RATIO_MIN_SIZE: u64 = 1024 * 1024;
+
+/// What an export contains.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupScope {
+    /// Config plus every asset it references
+    #[default]
+    Full,
+    /// Only `musicfree.json`
+    Config,
+    /// The playlists in `ExportOptions::playlist_ids` and their assets
+    Playlists,
+}
+
+/// Compression used for JSON entries. Audio and images are always stored
+/// as-is since they are already compressed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupCompression {
+    Stored,
+    #[default]
+    Deflate,
+    Zstd,
+}
+
+impl BackupCompression {
+    fn method(self) -> zip::CompressionMethod {
+        match self {
+            BackupCompression::Stored => zip::CompressionMethod::Stored,
+            BackupCompression::Deflate => zip::CompressionMethod::Deflated,
+            BackupCompression::Zstd => zip::CompressionMethod::Zstd,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupKind {
+    Full,
+    /// Only assets missing from `BackupManifest::base` are stored
+    Incremental,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportOptions {
+    #[serde(default)]
+    pub scope: BackupScope,
+    #[serde(default)]
+    pub playlist_ids: Vec<String>,
+    #[serde(default)]
+    pub incremental: bool,
+    #[serde(default)]
+    pub compression: BackupCompression,
+    /// Store the play history next to the config
+    #[serde(default)]
+    pub include_history: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupFile {
+    /// SHA-256 of the file content
+    pub hash: String,
+    pub size: u64,
+    /// Whether the file is stored in this archive rather than in a base
+    #[serde(default)]
+    pub included: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub version: u32,
+    pub created_at: String,
+    pub kind: BackupKind,
+    pub scope: BackupScope,
+    /// File name of the backup this one builds on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    /// Every asset the archived config references, keyed by relative path
+    #[serde(default)]
+    pub files: BTreeMap<String, BackupFile>,
+}
+
+/// How an imported backup is combined with the local library.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Merge playlists with the same id, add the others
+    #[default]
+    Merge,
+    /// Replace all local playlists with the backup
+    Replace,
+    /// Only add playlists that do not exist locally
+    AddMissing,
+}
+
+/// Summary of a backup archive, read without extracting it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    pub path: String,
+    pub filename: String,
+    /// RFC 3339 time from the manifest, or the file modification time
+    pub created_at: String,
+    pub size: u64,
+    pub playlist_count: usize,
+    pub track_count: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<BackupKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<BackupScope>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupPreview {
+    pub info: BackupInfo,
+    pub config: Config,
+}
+
+/// Backup archives in `dir`, newest first by modification time.
+pub fn list_backup_files(dir: &Path) -> AppResult<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if let Some(name) = path.file_name().and_then(|n| n.to_str())
+            && name.starts_with(BACKUP_PREFIX)
+            && name.ends_with(".zip")
+        {
+            let modified = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .unwrap_or(std::time::SystemTime::UNIX_EPOCH);
+            files.push((path, modified));
+        }
+    }
+    files.sort_by(|a, b| b.1.cmp(&a.1));
+    Ok(files.into_iter().map(|(p, _)| p).collect())
+}
+
+fn read_entry(archive: &mut zip::ZipArchive<File>, name: &str) -> AppResult<Option<Vec<u8>>> {
+    let entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    if entry.size() > MAX_JSON_SIZE {
+        return Err(AppError::InvalidBackup(format!("{} is too large", name)));
+    }
+    let mut content = Vec::new();
+    entry.take(MAX_JSON_SIZE).read_to_end(&mut content)?;
+    Ok(Some(content))
+}
+
+/// Read `manifest.json` from an archive. Archives written before manifests
+/// existed yield `None`.
+pub fn read_manifest(zip_path: &Path) -> AppResult<Option<BackupManifest>> {
+    let mut archive = zip::ZipArchive::new(File::open(zip_path)?)?;
+    read_entry(&mut archive, BACKUP_MANIFEST_FILE)?
+        .map(|content| serde_json::from_slice(&content))
+        .transpose()
+        .map_err(AppError::from)
+}
+
+/// Read the play history of an archive and of the bases of an incremental
+/// one, if any of them was exported with one. Records repeated across the
+/// chain are left for the merge to drop.
+pub fn read_history(zip_path: &Path) -> AppResult<Option<Vec<u8>>> {
+    let mut history: Option<Vec<u8>> = None;
+    for path in backup_chain(zip_path)? {
+        let mut archive = zip::ZipArchive::new(File::open(&path)?)?;
+        if let Some(content) = read_entry(&mut archive, HISTORY_FILE)? {
+            let history = history.get_or_insert_with(Vec::new);
+            history.extend_from_slice(&content);
+            history.push(b'\n');
+        }
+    }
+    Ok(history)
+}
+
+/// Read the config and summary of an archive from any path, in memory.
+pub fn inspect(zip_path: &Path) -> AppResult<BackupPreview> {
+    let file = File::open(zip_path)?;
+    let metadata = file.metadata()?;
+    let mut archive = zip::ZipArchive::new(file)?;
+    validate_archive(&mut archive)?;
+
+    let config: Config = match read_entry(&mut archive, CONFIG_FILE)? {
+        Some(content) => serde_json::from_slice(&content)?,
+        None => {
+            return Err(AppError::NotFound(
+                "Invalid backup: no config file".to_string(),
+            ));
+        }
+    };
+    let manifest: Option<BackupManifest> = read_entry(&mut archive, BACKUP_MANIFEST_FILE)?
+        .map(|content| serde_json::from_slice(&content))
+        .transpose()?;
+
+    let created_at = match &manifest {
+        Some(manifest) => manifest.created_at.clone(),
+        None => metadata
+            .modified()
+            .map(|t| chrono::DateTime::<chrono::Local>::from(t).to_rfc3339())
+            .unwrap_or_default(),
+    };
+    let info = BackupInfo {
+        path: zip_path.to_string_lossy().to_string(),
+        filename: zip_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        created_at,
+        size: metadata.len(),
+        playlist_count: config.playlists.len(),
+        track_count: config.playlists.iter().map(|p| p.audios.len()).sum(),
+        kind: manifest.as_ref().map(|m| m.kind),
+        scope: manifest.as_ref().map(|m| m.scope),
+    };
+
+    Ok(BackupPreview { info, config })
+}
+
+/// Summaries of every readable backup in `dir`, newest first.
+pub fn list_backups(dir: &Path) -> AppResult<Vec<BackupInfo>> {
+    let mut backups = Vec::new();
+    for path in list_backup_files(dir)? {
+        match inspect(&path) {
+            Ok(preview) => backups.push(preview.info),
+            Err(e) => tracing::warn!("Skipping unreadable backup {}: {}", path.display(), e),
+        }
+    }
+    Ok(backups)
+}
+
+/// Narrow an imported config to what `mode` will apply.
+pub fn scope_import(imported: Config, current: &Config, mode: ImportMode) -> Config {
+    if mode != ImportMode::AddMissing {
+        return imported;
+    }
+    let existing: HashSet<&str> = current
+        .playlists
+        .iter()
+        .filter_map(|p| p.id.as_deref())
+        .collect();
+    Config {
+        playlists: imported
+            .playlists
+            .into_iter()
+            .filter(|p| p.id.as_deref().is_none_or(|id| !existing.contains(id)))
+            .collect(),
+    }
+}
+
+/// The archive followed by the bases of an incremental backup, which are
+/// looked up next to it by file name.
+fn backup_chain(zip_path: &Path) -> AppResult<Vec<PathBuf>> {
+    let mut chain = vec![zip_path.to_path_buf()];
+    let mut manifest = read_manifest(zip_path)?;
+    while let Some(base) = manifest.and_then(|m| m.base) {
+        let base_path = zip_path.with_file_name(&base);
+        if !base_path.exists() {
+            tracing::warn!(
+                "Base backup {} is missing, some assets cannot be restored",
+                base
+            );
+            break;
+        }
+        if chain.contains(&base_path) {
+            break;
+        }
+        manifest = read_manifest(&base_path)?;
+        chain.push(base_path);
+    }
+    Ok(chain)
+}
+
+/// `name.part` next to `path`, renamed over `path` once fully written.
+fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+/// Asset entry selected for extraction
+struct PlannedEntry {
+    archive: usize,
+    index: usize,
+    name: String,
+    size: u64,
+}
+
+/// Reject entries a backup written by this app can never contain: paths
+/// outside the archive root or `assets/`, oversized files and zip bombs.
+fn validate_entry(
+    raw_name: &str,
+    enclosed_name: Option<PathBuf>,
+    size: u64,
+    compressed_size: u64,
+) -> AppResult<()> {
+    let Some(name) = enclosed_name else {
+        return Err(AppError::InvalidBackup(format!(
+            "Unsafe entry path {}",
+            raw_name
+        )));
+    };
+    let name = name.to_string_lossy().replace("\\", "/");
+    if !(name == CONFIG_FILE
+        || name == BACKUP_MANIFEST_FILE
+        || name == HISTORY_FILE
+        || name.starts_with(&format!("{}/", ASSETS_DIR)))
+    {
+        return Err(AppError::InvalidBackup(format!(
+            "Unexpected entry {}",
+            name
+        )));
+    }
+    let max_size = if name.starts_with(ASSETS_DIR) {
+        MAX_ENTRY_SIZE
+    } else {
+        MAX_JSON_SIZE
+    };
+    if size > max_size {
+        return Err(AppError::InvalidBackup(format!(
+            "{} is too large ({} bytes)",
+            name, size
+        )));
+    }
+    if size > MAX_RATIO_MIN_SIZE && size / compressed_size.max(1) > MAX_COMPRESSION_RATIO {
+        return Err(AppError::InvalidBackup(format!(
+            "{} has a suspicious compression ratio",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Check every entry of an archive with [`validate_entry`].
+pub fn validate_archive(archive: &mut zip::ZipArchive<File>) -> AppResult<()> {
+    let mut total: u64 = 0;
+    for index in 0..archive.len() {
+        let file = archive.by_index(index)?;
+        if file.is_dir() {
+            continue;
+        }
+        validate_entry(
+            file.name(),
+            file.enclosed_name(),
+            file.size(),
+            file.compressed_size(),
+        )?;
+        total = total.saturating_add(file.size());
+    }
+    if total > MAX_ARCHIVE_SIZE {
+        return Err(AppError::InvalidBackup(format!(
+            "Archive expands to {} bytes",
+            total
+        )));
+    }
+    Ok(())
+}
+
+/// Per-import directory under [`STAGING_DIR`], locked while in use and
+/// removed when dropped.
+struct Staging {
+    dir: PathBuf,
+    lock: Option<File>,
+}
+
+impl Staging {
+    fn create(cache_dir: &Path) -> AppResult<Self> {
+        let dir = cache_dir
+            .join(STAGING_DIR)
+            .join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir)?;
+        let lock = File::create(dir.join(STAGING_LOCK_FILE))?;
+        lock.lock()?;
+        Ok(Staging {
+            dir,
+            lock: Some(lock),
+        })
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        // Released first, Windows cannot remove a file that is still open
+        drop(self.lock.take());
+        if let Err(e) = std::fs::remove_dir_all(&self.dir) {
+            tracing::warn!("Failed to remove {}: {}", self.dir.display(), e);
+        }
+    }
+}
+
+/// Rename, or copy when the cache and app data directories are on different
+/// filesystems.
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    match std::fs::rename(from, to) {
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            std::fs::copy(from, to)?;
+            std::fs::remove_file(from)
+        }
+        result => result,
+    }
+}
+
+/// Move staged files into `app_dir`. If any move fails, the files already
+/// moved are removed again so `assets/` is left as it was.
+fn apply_staged(staging: &Staging, app_dir: &Path, names: &[String]) -> AppResult<()> {
+    let mut applied: Vec<PathBuf> = Vec::with_capacity(names.len());
+    for name in names {
+        let dest = app_dir.join(name);
+        let result = (|| -> std::io::Result<()> {
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            // Another import may have added the same file meanwhile
+            if dest.exists() {
+                return Ok(());
+            }
+            move_file(&staging.dir.join(name), &dest)?;
+            applied.push(dest.clone());
+            Ok(())
+        })();
+        if let Err(e) = result {
+            tracing::error!("Import failed at {}, rolling back: {}", name, e);
+            for path in applied.iter().rev() {
+                if let Err(e) = std::fs::remove_file(path) {
+                    tracing::warn!("Rollback could not remove {}: {}", path.display(), e);
+                }
+            }
+            return Err(e.into());
+        }
+    }
+    Ok(())
+}
+
+/// Import the assets of a backup into `app_dir`, following the base chain of
+/// incremental archives. With `wanted`, only those asset paths are imported.
+///
+/// Every archive is validated first. New files are then streamed into a
+/// unique staging directory and moved into place together; files that
+/// already exist are kept. Returns every asset path in the backup.
+/// Blocking; run it on a blocking thread.
+pub fn extract_assets(
+    zip_path: &Path,
+    app_dir: &Path,
+    cache_dir: &Path,
+    wanted: Option<&HashSet<String>>,
+    task: &mut Task,
+) -> AppResult<Vec<String>> {
+    let chain = backup_chain(zip_path)?;
+    let mut archives = Vec::with_capacity(chain.len());
+    for path in &chain {
+        let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+        validate_archive(&mut archive)?;
+        archives.push(archive);
+    }
+
+    // Plan first so progress has a total
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut paths = Vec::new();
+    let mut planned = Vec::new();
+    for (archive_index, archive) in archives.iter_mut().enumerate() {
+        for index in 0..archive.len() {
+            let file = archive.by_index(index)?;
+            if file.is_dir() {
+                continue;
+            }
+            let Some(relative) = file.enclosed_name() else {
+                continue;
+            };
+            let name = relative.to_string_lossy().replace("\\", "/");
+            if !name.starts_with(&format!("{}/", ASSETS_DIR))
+                || seen.contains(&name)
+                || wanted.is_some_and(|w| !w.contains(&name))
+            {
+                continue;
+            }
+            seen.insert(name.clone());
+            paths.push(name.clone());
+            if !app_dir.join(&name).exists() {
+                planned.push(PlannedEntry {
+                    archive: archive_index,
+                    index,
+                    name,
+                    size: file.size(),
+                });
+            }
+        }
+    }
+    task.set_total(planned.iter().map(|e| e.size).sum());
+
+    let staging = Staging::create(cache_dir)?;
+    for entry in &planned {
+        let file = archives[entry.archive].by_index(entry.index)?;
+        let dest = staging.dir.join(&entry.name);
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut out = File::create(&dest)?;
+        // Never trust the declared size while inflating
+        let mut limited = file.take(entry.size + 1);
+        if task.copy(&mut limited, &mut out, &entry.name)? > entry.size {
+            return Err(AppError::InvalidBackup(format!(
+                "{} is larger than declared",
+                entry.name
+            )));
+        }
+    }
+
+    let names: Vec<String> = planned.into_iter().map(|e| e.name).collect();
+    apply_staged(&staging, app_dir, &names)?;
+
+    task.finish();
+    Ok(paths)
+}
+
+/// Remove staging directories left behind by imports that crashed, and the
+/// staging locations used by older versions.
+pub fn cleanup_staging(app_dir: &Path, cache_dir: &Path) {
+    for legacy in [
+        std::env::temp_dir().join("musicfree_import_temp"),
+        app_dir.join(LEGACY_STAGING_DIR),
+    ] {
+        if legacy.exists() {
+            let _ = std::fs::remove_dir_all(&legacy);
+        }
+    }
+
+    let Ok(entries) = std::fs::read_dir(cache_dir.join(STAGING_DIR)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        // Another running instance holds the lock of an import in progress
+        let stale = match File::open(entry.path().join(STAGING_LOCK_FILE)) {
+            Ok(lock) => lock.try_lock().is_ok(),
+            Err(e) => e.kind() == std::io::ErrorKind::NotFound,
+        };
+        if stale {
+            tracing::info!("Removing stale import staging {}", entry.path().display());
+            let _ = std::fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+/// The newest archive in `dir` that carries a manifest, used as the base of
+/// an incremental backup.
+pub fn latest_manifest(dir: &Path) -> AppResult<Option<(String, BackupManifest)>> {
+    for path in list_backup_files(dir)? {
+        if let Ok(Some(manifest)) = read_manifest(&path)
+            && let Some(name) = path.file_name().and_then(|n| n.to_str())
+        {
+            return Ok(Some((name.to_string(), manifest)));
+        }
+    }
+    Ok(None)
+}
+
+/// Restrict the raw config JSON to the requested scope. Working on the raw
+/// value keeps fields the Rust `Config` does not model (e.g. `_updatedAt`).
+fn scoped_config(raw: &mut serde_json::Value, config: &Config, options: &ExportOptions) -> Config {
+    if options.scope != BackupScope::Playlists {
+        return config.clone();
+    }
+    let ids: HashSet<&str> = options.playlist_ids.iter().map(|s| s.as_str()).collect();
+    if let Some(playlists) = raw.get_mut("playlists").and_then(|p| p.as_array_mut()) {
+        playlists.retain(|p| {
+            p.get("id")
+                .and_then(|id| id.as_str())
+                .is_some_and(|id| ids.contains(id))
+        });
+    }
+    Config {
+        playlists: config
+            .playlists
+            .iter()
+            .filter(|p| p.id.as_deref().is_some_and(|id| ids.contains(id)))
+            .cloned()
+            .collect(),
+    }
+}
+
+/// Write a backup archive, streaming assets into it. Blocking; run it on a
+/// blocking thread.
+///
+/// With `options.incremental` and a `base`, assets whose hash already
+/// appears in the base manifest are listed but not stored again.
+pub fn write_backup(
+    app_dir: &Path,
+    zip_path: &Path,
+    config: &Config,
+    options: &ExportOptions,
+    base: Option<(String, BackupManifest)>,
+    task: &mut Task,
+) -> AppResult<BackupManifest> {
+    let config_path = get_config_path(app_dir.to_path_buf());
+    let mut raw_config: serde_json::Value = if config_path.exists() {
+        serde_json::from_slice(&std::fs::read(&config_path)?)?
+    } else {
+        serde_json::to_value(config)?
+    };
+    let config = scoped_config(&mut raw_config, config, options);
+
+    let (kind, base) = match base {
+        Some(base) if options.incremental && options.scope != BackupScope::Config => {
+            (BackupKind::Incremental, Some(base))
+        }
+        _ => (BackupKind::Full, None),
+    };
+    let known_hashes: HashSet<String> = base
+        .as_ref()
+        .map(|(_, m)| m.files.values().map(|f| f.hash.clone()).collect())
+        .unwrap_or_default();
+
+    // Hash first so progress has a total
+    let mut files = BTreeMap::new();
+    let mut sources = Vec::new();
+    let assets_path = app_dir.join(ASSETS_DIR);
+    if options.scope != BackupScope::Config && assets_path.exists() {
+        // Only files referenced by the (scoped) config are exported
+        let used_paths = api::get_used_paths(&config);
+        for entry in WalkDir::new(&assets_path) {
+            let entry = entry.map_err(|e| AppError::Io(e.into()))?;
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+            let name = path
+                .strip_prefix(app_dir)
+                .map_err(|e| AppError::PathError(e.to_string()))?;
+            let name_str = name
+                .to_str()
+                .ok_or(AppError::InvalidUtf8)?
+                .replace("\\", "/");
+            if !used_paths.contains(&name_str) {
+                continue;
+            }
+            task.check()?;
+
+            let hash = api::hash_file(path)?;
+            let size = entry.metadata().map_err(|e| AppError::Io(e.into()))?.len();
+            let included = !known_hashes.contains(&hash);
+            if included {
+                sources.push((name_str.clone(), path.to_path_buf(), size));
+            }
+            files.insert(
+                name_str,
+                BackupFile {
+                    hash,
+                    size,
+                    included,
+                },
+            );
+        }
+        // Files referenced in place stay with the user, only their entries go
+        let external = config
+            .playlists
+            .iter()
+            .flat_map(|p| &p.audios)
+            .filter(|a| a.is_external())
+            .count();
+        if external > 0 {
+            tracing::info!(
+                "Leaving {} files referenced in place out of the backup",
+                external
+            );
+        }
+    }
+    task.set_total(sources.iter().map(|(_, _, size)| size).sum());
+
+    let manifest = BackupManifest {
+        version: BACKUP_MANIFEST_VERSION,
+        created_at: chrono::Local::now().to_rfc3339(),
+        kind,
+        scope: options.scope,
+        base: base.map(|(name, _)| name),
+        files,
+    };
+
+    let history = if options.include_history {
+        Some(crate::history::read_raw(app_dir)?).filter(|h| !h.is_empty())
+    } else {
+        None
+    };
+
+    // Written next to the target and renamed when complete, so a cancelled or
+    // failed export never looks like a valid backup
+    let part = part_path(zip_path);
+    let result = (|| -> AppResult<()> {
+        let mut zip = zip::ZipWriter::new(File::create(&part)?);
+        let json_options = FileOptions::<()>::default()
+            .compression_method(options.compression.method())
+            .unix_permissions(0o644);
+        let asset_options = FileOptions::<()>::default()
+            .compression_method(zip::CompressionMethod::Stored)
+            .unix_permissions(0o755);
+
+        zip.start_file(CONFIG_FILE, json_options)?;
+        zip.write_all(&serde_json::to_vec_pretty(&raw_config)?)?;
+
+        if let Some(history) = &history {
+            zip.start_file(HISTORY_FILE, json_options)?;
+            zip.write_all(history)?;
+        }
+
+        for (name, path, _) in &sources {
+            zip.start_file(name.as_str(), asset_options)?;
+            let mut f = File::open(path)?;
+            task.copy(&mut f, &mut zip, name)?;
+        }
+
+        zip.start_file(BACKUP_MANIFEST_FILE, json_options)?;
+        zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+        zip.finish()?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&part);
+        return Err(e);
+    }
+    std::fs::rename(&part, zip_path)?;
+
+    task.finish();
+    Ok(manifest)
+}
+
+/// `musicfree-2024-05-01-153012.zip`, with an `-incremental` suffix when needed.
+pub fn backup_filename(incremental: bool) -> String {
+    let timestamp = chrono::Local::now().format("%Y-%m-%d-%H%M%S");
+    if incremental {
+        format!("{}{}-incremental.zip", BACKUP_PREFIX, timestamp)
+    } else {
+        format!("{}{}.zip", BACKUP_PREFIX, timestamp)
+    }
+}
@@ -1,14 +1,11 @@
 use crate::api::{self};
-use crate::core::{ASSETS_DIR, CONFIG_FILE, Config, LocalAudio, get_config_path};
-use crate::error::{AppError, AppResult};
+use crate::core::{ASSETS_DIR, AUDIOS_DIR, Config, LocalAudio, LocalPlaylist, get_config_path};
+use crate::error::{AppError, AppResult, SyncError};
 use chrono::Local;
 use musicfree::{Audio, Platform, Playlist};
 use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::{Read, Write};
-use std::path::PathBuf;
-use walkdir::WalkDir;
-use zip::write::FileOptions;
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
 
 #[tauri::command]
 pub async fn app_dir(app_handle: tauri::AppHandle) -> AppResult<PathBuf> {
@@ -28,7 +25,7 @@ pub async fn app_version() -> &'static str {
 pub async fn extract_audios(url: &str) -> AppResult<(Playlist, Option<usize>)> {
     musicfree::extract(url)
         .await
-        .map_err(|e| AppError::MusicFree(e.to_string()))
+        .map_err(|e| AppError::extractor(format!("Failed to extract {}", url), e))
 }
 
 #[tauri::command]
@@ -50,18 +47,48 @@ pub async fn save_config(config: Config, app_handle: tauri::AppHandle) -> AppRes
     let dir = app_dir(app_handle).await?;
     let s = serde_json::to_string_pretty(&config).map_err(AppError::Serde)?;
 
-    let p = get_config_path(dir);
+    let p = get_config_path(dir.clone());
     tokio::fs::write(p, s).await.map_err(AppError::Io)?;
+
+    // Keep library search current without holding up the save
+    tokio::task::spawn_blocking(move || crate::search::update(&config, &dir));
     Ok(())
 }
 
+/// Reindex library search after lyrics were written. Lyrics edited in place
+/// change no config, only the audios whose lyrics file changed are read.
+async fn reindex_lyrics(app_handle: tauri::AppHandle, app_dir: PathBuf) {
+    match get_config(app_handle).await {
+        Ok(config) => {
+            tokio::task::spawn_blocking(move || crate::search::update(&config, &app_dir));
+        }
+        Err(e) => warn!("Failed to reindex lyrics for library search: {e}"),
+    }
+}
+
 #[tauri::command]
-pub async fn download_audio(audio: Audio, app_handle: tauri::AppHandle) -> AppResult<LocalAudio> {
+pub async fn download_audio(
+    audio: Audio,
+    extract_audio: Option<bool>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<LocalAudio> {
     let dir = app_dir(app_handle).await?;
 
-    api::download_audio(&audio, dir)
-        .await
-        .map_err(|e| AppError::Unknown(e.to_string()))
+    let local = api::download_audio(&audio, dir.clone()).await?;
+    if !extract_audio.unwrap_or(false) {
+        return Ok(local);
+    }
+
+    // Keep the video when extraction fails, the download itself succeeded
+    tokio::task::spawn_blocking(move || {
+        let mut local = local;
+        if let Err(e) = crate::demux::demux(&mut local, &dir) {
+            warn!("Keeping '{}' as downloaded: {}", audio.title, e);
+        }
+        local
+    })
+    .await
+    .map_err(|e| AppError::Unknown(e.to_string()))
 }
 
 #[tauri::command]
@@ -134,13 +161,18 @@ pub async fn clear_all_data(app_handle: tauri::AppHandle) -> AppResult<()> {
     }
 
     // Delete musicfree.json
-    let config_path = get_config_path(dir);
+    let config_path = get_config_path(dir.clone());
     if tokio::fs::try_exists(&config_path).await.unwrap_or(false) {
         tokio::fs::remove_file(&config_path)
             .await
             .map_err(AppError::Io)?;
     }
 
+    // Delete the play history
+    tokio::task::spawn_blocking(move || crate::history::clear(&dir))
+        .await
+        .map_err(|e| AppError::Unknown(e.to_string()))??;
+
     Ok(())
 }
 
@@ -202,11 +234,18 @@ pub async fn clear_cache(app_handle: tauri::AppHandle) -> AppResult<()> {
     Ok(())
 }
 
+/// Export a backup archive to `external_dir()`. Without options this is a
+/// full backup of the config and every asset it references.
 #[tauri::command]
-pub async fn export_data(app_handle: tauri::AppHandle) -> AppResult<String> {
+pub async fn export_data(
+    options: Option<crate::backup::ExportOptions>,
+    task_id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<String> {
     let app_dir = api::app_dir(&app_handle).await?;
     let config = get_config(app_handle.clone()).await?;
     let download_dir = api::external_dir(&app_handle)?;
+    let options = options.unwrap_or_default();
 
     // Ensure download dir exists
     if !tokio::fs::try_exists(&download_dir).await.unwrap_or(false) {
@@ -215,265 +254,419 @@ pub async fn export_data(app_handle: tauri::AppHandle) -> AppResult<String> {
             .map_err(AppError::Io)?;
     }
 
-    let date_str = Local::now().format("%Y-%m-%d").to_string();
-    let zip_filename = format!("musicfree-{}.zip", date_str);
-    let zip_path = download_dir.join(&zip_filename);
+    let mut task = crate::progress::Task::start(&app_handle, "export", task_id);
+    let zip_path = tokio::task::spawn_blocking(move || -> AppResult<PathBuf> {
+        let base = if options.incremental && options.scope != crate::backup::BackupScope::Config {
+            crate::backup::latest_manifest(&download_dir)?
+        } else {
+            None
+        };
+        let zip_path = download_dir.join(crate::backup::backup_filename(base.is_some()));
+        let manifest =
+            crate::backup::write_backup(&app_dir, &zip_path, &config, &options, base, &mut task)?;
+        info!(
+            "Exported {:?} backup with {} assets to {}",
+            manifest.kind,
+            manifest.files.values().filter(|f| f.included).count(),
+            zip_path.display()
+        );
+        Ok(zip_path)
+    })
+    .await
+    .map_err(|e| AppError::Unknown(e.to_string()))??;
+
+    Ok(zip_path.to_string_lossy().to_string())
+}
 
-    // Get used paths from config to exclude unused cache files
-    let used_paths = api::get_used_paths(&config);
+/// Write a zip with logs, system info, storage stats, a redacted config and
+/// an asset integrity report to `external_dir()`, for attaching to bug reports.
+#[tauri::command]
+pub async fn export_diagnostics(app_handle: tauri::AppHandle) -> AppResult<String> {
+    let app_dir = api::app_dir(&app_handle).await?;
+    let download_dir = api::external_dir(&app_handle)?;
+    tokio::fs::create_dir_all(&download_dir).await?;
 
-    let app_dir_clone = app_dir.clone();
-    let zip_path_clone = zip_path.clone();
+    let config = get_config(app_handle.clone()).await?;
+    let report = crate::diagnostics::DiagnosticsReport {
+        generated_at: Local::now().to_rfc3339(),
+        system: crate::diagnostics::system_info(CARGO_PKG_VERSION, GIT_HASH),
+        storage: crate::diagnostics::StorageStats {
+            total: get_storage_size(app_handle.clone()).await?,
+            cache: get_cache_size(app_handle.clone()).await?,
+        },
+        integrity: crate::diagnostics::integrity_report(&app_handle, &config).await?,
+    };
 
-    // Spawn blocking task for compression
-    tokio::task::spawn_blocking(move || -> AppResult<()> {
-        let file = File::create(zip_path_clone).map_err(AppError::Io)?;
-        let mut zip = zip::ZipWriter::new(file);
-        let options = FileOptions::<()>::default()
-            .compression_method(zip::CompressionMethod::Stored)
-            .unix_permissions(0o755);
-
-        // Add musicfree.json
-        let config_path = get_config_path(app_dir_clone.clone());
-        if config_path.exists() {
-            zip.start_file(CONFIG_FILE, options)
-                .map_err(|e| AppError::Unknown(e.to_string()))?;
-            let content = std::fs::read(&config_path).map_err(AppError::Io)?;
-            zip.write_all(&content).map_err(AppError::Io)?;
-        }
+    // Redact the raw file so fields the Rust `Config` does not model are kept
+    let config_path = get_config_path(app_dir.clone());
+    let raw_config = match tokio::fs::read(&config_path).await {
+        Ok(bin) => serde_json::from_slice(&bin).ok(),
+        Err(_) => None,
+    };
+    let log_files = crate::logging::log_files(&crate::core::get_log_path(app_dir));
 
-        // Add assets directory (only used files)
-        let assets_path = app_dir_clone.join(ASSETS_DIR);
-        if assets_path.exists() {
-            for entry in WalkDir::new(&assets_path) {
-                let entry = entry.map_err(|e| AppError::Io(e.into()))?;
-                let path = entry.path();
-                if path.is_file() {
-                    let name = path
-                        .strip_prefix(&app_dir_clone)
-                        .map_err(|e| AppError::PathError(e.to_string()))?;
-                    let name_str = name
-                        .to_str()
-                        .ok_or(AppError::InvalidUtf8)?
-                        .replace("\\", "/");
-
-                    // Skip unused cache files
-                    if !used_paths.contains(&name_str) {
-                        continue;
-                    }
-
-                    zip.start_file(&name_str, options)
-                        .map_err(|e| AppError::Unknown(e.to_string()))?;
-                    let mut f = File::open(path).map_err(AppError::Io)?;
-                    let mut buffer = Vec::new();
-                    f.read_to_end(&mut buffer).map_err(AppError::Io)?;
-                    zip.write_all(&buffer).map_err(AppError::Io)?;
-                }
-            }
-        }
+    let timestamp = Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let zip_path = download_dir.join(format!("diagnostics-{}.zip", timestamp));
 
-        zip.finish().map_err(|e| AppError::Unknown(e.to_string()))?;
-        Ok(())
+    let zip_path_clone = zip_path.clone();
+    tokio::task::spawn_blocking(move || {
+        crate::diagnostics::write_bundle(&zip_path_clone, &report, raw_config, &log_files)
     })
     .await
     .map_err(|e| AppError::Unknown(e.to_string()))??;
 
+    info!("Exported diagnostics to {}", zip_path.display());
     Ok(zip_path.to_string_lossy().to_string())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImportResult {
+    /// Playlists to apply, already narrowed to what `mode` adds
     pub config: Config,
     pub filename: String,
     pub paths: Vec<String>,
+    pub mode: crate::backup::ImportMode,
 }
 
+/// Backups in the download directory, newest first.
 #[tauri::command]
-pub async fn import_data(app_handle: tauri::AppHandle) -> AppResult<ImportResult> {
-    let app_dir = api::app_dir(&app_handle).await?;
+pub async fn list_backups(
+    app_handle: tauri::AppHandle,
+) -> AppResult<Vec<crate::backup::BackupInfo>> {
     let download_dir = api::external_dir(&app_handle)?;
+    tokio::task::spawn_blocking(move || crate::backup::list_backups(&download_dir))
+        .await
+        .map_err(|e| AppError::Unknown(e.to_string()))?
+}
 
-    // 1. Find latest musicfree-*.zip
-    let mut latest_zip: Option<(PathBuf, std::time::SystemTime)> = None;
-
-    let mut entries = tokio::fs::read_dir(&download_dir)
+/// Read the config of a backup without extracting anything.
+#[tauri::command]
+pub async fn inspect_backup(path: String) -> AppResult<crate::backup::BackupPreview> {
+    tokio::task::spawn_blocking(move || crate::backup::inspect(Path::new(&path)))
         .await
-        .map_err(AppError::Io)?;
-    while let Ok(Some(entry)) = entries.next_entry().await {
-        let path = entry.path();
-        if let Some(name) = path.file_name().and_then(|n| n.to_str())
-            && name.starts_with("musicfree-")
-            && name.ends_with(".zip")
-            && let Ok(metadata) = entry.metadata().await
-            && let Ok(modified) = metadata.modified()
-        {
-            match latest_zip {
-                Some((_, time)) => {
-                    if modified > time {
-                        latest_zip = Some((path, modified));
-                    }
-                }
-                None => {
-                    latest_zip = Some((path, modified));
-                }
-            }
-        }
-    }
+        .map_err(|e| AppError::Unknown(e.to_string()))?
+}
 
-    let (zip_path, _) = latest_zip.ok_or(AppError::Unknown("No backup file found".to_string()))?;
+/// Import a backup. Without `path` the newest backup in the download
+/// directory is used.
+#[tauri::command]
+pub async fn import_data(
+    app_handle: tauri::AppHandle,
+    path: Option<String>,
+    mode: Option<crate::backup::ImportMode>,
+    task_id: Option<String>,
+) -> AppResult<ImportResult> {
+    let app_dir = api::app_dir(&app_handle).await?;
+    let cache_dir = api::cache_dir(&app_handle)?;
+    let mode = mode.unwrap_or_default();
+
+    // 1. Resolve the archive
+    let zip_path = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let download_dir = api::external_dir(&app_handle)?;
+            tokio::task::spawn_blocking(move || crate::backup::list_backup_files(&download_dir))
+                .await
+                .map_err(|e| AppError::Unknown(e.to_string()))??
+                .into_iter()
+                .next()
+                .ok_or(AppError::NotFound("No backup file found".to_string()))?
+        }
+    };
     let zip_filename = zip_path
         .file_name()
         .ok_or(AppError::PathError("Invalid zip file path".to_string()))?
         .to_string_lossy()
         .to_string();
 
-    // 2. Unzip to temp
-    let temp_dir = std::env::temp_dir().join("musicfree_import_temp");
-    if tokio::fs::try_exists(&temp_dir).await.unwrap_or(false) {
-        tokio::fs::remove_dir_all(&temp_dir)
-            .await
-            .map_err(AppError::Io)?;
-    }
-    tokio::fs::create_dir_all(&temp_dir)
-        .await
-        .map_err(AppError::Io)?;
-
-    let temp_dir_clone = temp_dir.clone();
+    // 2. Read the config and decide what to apply
     let zip_path_clone = zip_path.clone();
-
-    // Spawn blocking task for extraction
-    tokio::task::spawn_blocking(move || -> AppResult<()> {
-        let file = File::open(&zip_path_clone).map_err(AppError::Io)?;
-        let mut archive =
-            zip::ZipArchive::new(file).map_err(|e| AppError::Unknown(e.to_string()))?;
-
-        for i in 0..archive.len() {
-            let mut file = archive
-                .by_index(i)
-                .map_err(|e| AppError::Unknown(e.to_string()))?;
-            let outpath = match file.enclosed_name() {
-                Some(path) => temp_dir_clone.join(path),
-                None => continue,
-            };
-
-            if file.name().ends_with('/') {
-                std::fs::create_dir_all(&outpath).map_err(AppError::Io)?;
-            } else {
-                if let Some(p) = outpath.parent()
-                    && !p.exists()
-                {
-                    std::fs::create_dir_all(p).map_err(AppError::Io)?;
-                }
-                let mut outfile = File::create(&outpath).map_err(AppError::Io)?;
-                std::io::copy(&mut file, &mut outfile).map_err(AppError::Io)?;
-            }
+    let preview = tokio::task::spawn_blocking(move || crate::backup::inspect(&zip_path_clone))
+        .await
+        .map_err(|e| AppError::Unknown(e.to_string()))??;
+    let current = get_config(app_handle.clone()).await?;
+    let import_config = crate::backup::scope_import(preview.config, &current, mode);
+    let wanted = (mode == crate::backup::ImportMode::AddMissing)
+        .then(|| api::get_used_paths(&import_config));
+
+    // 3. Stream the assets into place
+    let mut task = crate::progress::Task::start(&app_handle, "import", task_id);
+    let paths = tokio::task::spawn_blocking(move || {
+        let paths = crate::backup::extract_assets(
+            &zip_path,
+            &app_dir,
+            &cache_dir,
+            wanted.as_ref(),
+            &mut task,
+        )?;
+        if let Some(history) = crate::backup::read_history(&zip_path)? {
+            crate::history::merge(&app_dir, &history)?;
         }
-        Ok(())
+        Ok::<_, AppError>(paths)
     })
     .await
     .map_err(|e| AppError::Unknown(e.to_string()))??;
 
-    // 3. Load configs
-    let import_config_path = temp_dir.join(CONFIG_FILE);
-    if !tokio::fs::try_exists(&import_config_path)
-        .await
-        .unwrap_or(false)
-    {
-        // Cleanup
-        tokio::fs::remove_dir_all(&temp_dir).await.ok();
-        return Err(AppError::Unknown(
-            "Invalid backup: no config file".to_string(),
-        ));
-    }
-
-    let import_json = tokio::fs::read_to_string(&import_config_path)
-        .await
-        .map_err(AppError::Io)?;
-    let import_config: Config = serde_json::from_str(&import_json).map_err(AppError::Serde)?;
-
-    // 4. Move all assets from temp_dir/assets to app_dir/assets
-    let temp_assets_dir = temp_dir.join(ASSETS_DIR);
-    let mut paths = Vec::new();
-    if tokio::fs::try_exists(&temp_assets_dir)
-        .await
-        .unwrap_or(false)
-    {
-        for entry in WalkDir::new(&temp_assets_dir) {
-            let entry = entry.map_err(|e| AppError::Io(e.into()))?;
-            let path = entry.path();
-            if path.is_file() {
-                let relative_path = path
-                    .strip_prefix(&temp_dir)
-                    .map_err(|e| AppError::PathError(e.to_string()))?;
-
-                let relative_path_str = relative_path
-                    .to_str()
-                    .ok_or(AppError::InvalidUtf8)?
-                    .replace("\\", "/");
-
-                paths.push(relative_path_str);
-
-                let dest_path = app_dir.join(relative_path);
-
-                if let Some(parent) = dest_path.parent() {
-                    tokio::fs::create_dir_all(parent)
-                        .await
-                        .map_err(AppError::Io)?;
-                }
-
-                // Copy file if it doesn't exist
-                if !tokio::fs::try_exists(&dest_path).await.unwrap_or(false) {
-                    tokio::fs::copy(path, dest_path)
-                        .await
-                        .map_err(AppError::Io)?;
-                }
-            }
-        }
-    }
-
-    // 5. Cleanup
-    tokio::fs::remove_dir_all(&temp_dir)
-        .await
-        .map_err(AppError::Io)?;
-
+    info!("Imported {} ({:?})", zip_filename, mode);
     Ok(ImportResult {
         config: import_config,
         filename: zip_filename,
         paths,
+        mode,
     })
 }
 
+/// Import an M3U/M3U8, XSPF or PLS playlist, or a MusicFree mobile backup.
+/// The returned playlists are merged into the config by the frontend.
 #[tauri::command]
-pub async fn sync_download(token: &str, repo: &str, path: Option<String>) -> AppResult<Vec<u8>> {
-    crate::sync::download(token, repo, path.as_deref())
+pub async fn import_playlist_file(
+    path: String,
+    task_id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<crate::playlists::PlaylistImport> {
+    let app_dir = api::app_dir(&app_handle).await?;
+    let mut task = crate::progress::Task::start(&app_handle, "import_playlist", task_id);
+    crate::playlists::import_file(Path::new(&path), &app_dir, &mut task).await
+}
+
+/// Imported files are referenced in place on desktop. Android only serves
+/// files below the app directory, so they are always copied there.
+fn copy_local_files(copy: Option<bool>) -> bool {
+    copy.unwrap_or(false) || cfg!(target_os = "android")
+}
+
+/// Scan folders for audio files, one `File` playlist per folder.
+/// The returned playlists are merged into the config by the frontend.
+#[tauri::command]
+pub async fn scan_local_folders(
+    paths: Vec<String>,
+    copy: Option<bool>,
+    task_id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<crate::library::ScanResult> {
+    let app_dir = api::app_dir(&app_handle).await?;
+    let mut task = crate::progress::Task::start(&app_handle, "scan_library", task_id);
+    let roots: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    let copy = copy_local_files(copy);
+    tokio::task::spawn_blocking(move || {
+        crate::library::scan_folders(&roots, &app_dir, copy, &mut task)
+    })
+    .await
+    .map_err(|e| AppError::Unknown(e.to_string()))?
+}
+
+/// Watch folders and emit `library-changed` when audio files are added or
+/// removed. Returns every folder being watched.
+#[tauri::command]
+pub async fn watch_local_folders(
+    paths: Vec<String>,
+    copy: Option<bool>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<Vec<String>> {
+    let app_dir = api::app_dir(&app_handle).await?;
+    let roots: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    crate::library::watch(&app_handle, &roots, &app_dir, copy_local_files(copy))?;
+    Ok(crate::library::watched()
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect())
+}
+
+/// Stop watching `paths`, or every folder when omitted.
+#[tauri::command]
+pub fn unwatch_local_folders(paths: Option<Vec<String>>) {
+    let roots = paths.map(|paths| paths.into_iter().map(PathBuf::from).collect::<Vec<_>>());
+    crate::library::unwatch(roots.as_deref());
+}
+
+/// Cancel a running export or import. Returns `false` if it already finished.
+#[tauri::command]
+pub fn cancel_task(id: String) -> bool {
+    crate::progress::cancel(&id)
+}
+
+/// Read the stored sync credentials, failing if none have been set.
+async fn sync_credentials(
+    app_handle: &tauri::AppHandle,
+) -> AppResult<crate::credentials::SyncCredentials> {
+    let dir = app_dir(app_handle.clone()).await?;
+    crate::credentials::load(&dir, &api::device_dir(app_handle)?)
+        .await?
+        .map(|(credentials, _)| credentials)
+        .ok_or(AppError::Sync(SyncError::MissingCredentials))
+}
+
+#[tauri::command]
+pub async fn set_sync_credentials(
+    token: String,
+    repo: String,
+    app_handle: tauri::AppHandle,
+) -> AppResult<crate::credentials::SyncStatus> {
+    let dir = app_dir(app_handle.clone()).await?;
+    let backend = crate::credentials::save(
+        &dir,
+        &api::device_dir(&app_handle)?,
+        crate::credentials::SyncCredentials {
+            token,
+            repo: repo.clone(),
+        },
+    )
+    .await?;
+
+    Ok(crate::credentials::SyncStatus {
+        configured: true,
+        repo: Some(repo),
+        backend: Some(backend),
+    })
+}
+
+#[tauri::command]
+pub async fn clear_sync_credentials(app_handle: tauri::AppHandle) -> AppResult<()> {
+    let dir = app_dir(app_handle).await?;
+    crate::credentials::clear(&dir).await
+}
+
+#[tauri::command]
+pub async fn sync_status(
+    app_handle: tauri::AppHandle,
+) -> AppResult<crate::credentials::SyncStatus> {
+    let dir = app_dir(app_handle.clone()).await?;
+    let status = match crate::credentials::load(&dir, &api::device_dir(&app_handle)?).await? {
+        Some((credentials, backend)) => crate::credentials::SyncStatus {
+            configured: true,
+            repo: Some(credentials.repo),
+            backend: Some(backend),
+        },
+        None => crate::credentials::SyncStatus {
+            configured: false,
+            repo: None,
+            backend: None,
+        },
+    };
+    Ok(status)
+}
+
+#[tauri::command]
+pub async fn sync_download(
+    path: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<Vec<u8>> {
+    let credentials = sync_credentials(&app_handle).await?;
+    crate::sync::download(&credentials.token, &credentials.repo, path.as_deref())
         .await
         .map_err(AppError::from)
 }
 
 #[tauri::command]
 pub async fn sync_update(
-    token: &str,
-    repo: &str,
     content: Vec<u8>,
     path: Option<String>,
     message: Option<String>,
+    device_id: Option<String>,
+    app_handle: tauri::AppHandle,
 ) -> AppResult<()> {
-    crate::sync::update(token, repo, content, path.as_deref(), message.as_deref())
-        .await
-        .map_err(AppError::from)
+    let credentials = sync_credentials(&app_handle).await?;
+    crate::sync::update(
+        &credentials.token,
+        &credentials.repo,
+        content,
+        path.as_deref(),
+        message.as_deref(),
+        device_id.as_deref(),
+    )
+    .await
+    .map_err(AppError::from)
 }
 
 #[tauri::command]
 pub async fn sync_file_info(
-    token: &str,
-    repo: &str,
     path: Option<String>,
+    app_handle: tauri::AppHandle,
 ) -> AppResult<Option<crate::sync::FileInfo>> {
-    crate::sync::get_file_info(token, repo, path.as_deref())
+    let credentials = sync_credentials(&app_handle).await?;
+    crate::sync::get_file_info(&credentials.token, &credentials.repo, path.as_deref())
         .await
         .map_err(AppError::from)
 }
 
+#[tauri::command]
+pub async fn sync_history(
+    path: Option<String>,
+    limit: Option<usize>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<Vec<crate::sync::SyncCommit>> {
+    let credentials = sync_credentials(&app_handle).await?;
+    crate::sync::history(
+        &credentials.token,
+        &credentials.repo,
+        path.as_deref(),
+        limit.unwrap_or(30),
+    )
+    .await
+    .map_err(AppError::from)
+}
+
+#[tauri::command]
+pub async fn sync_restore(
+    sha: String,
+    path: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<crate::sync::SyncRestore> {
+    let credentials = sync_credentials(&app_handle).await?;
+    let config = get_config(app_handle).await?;
+    crate::sync::restore(
+        &credentials.token,
+        &credentials.repo,
+        path.as_deref(),
+        &sha,
+        &config,
+    )
+    .await
+    .map_err(AppError::from)
+}
+
+#[tauri::command]
+pub async fn sync_upload_assets(
+    size_cap: Option<u64>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<crate::sync::AssetSyncResult> {
+    let credentials = sync_credentials(&app_handle).await?;
+    let dir = app_dir(app_handle.clone()).await?;
+    let config = get_config(app_handle).await?;
+    let paths = api::get_used_paths(&config);
+
+    crate::sync::upload_assets(
+        &credentials.token,
+        &credentials.repo,
+        &dir,
+        &paths,
+        size_cap.unwrap_or(crate::sync::DEFAULT_ASSET_SIZE_CAP),
+    )
+    .await
+    .map_err(AppError::from)
+}
+
+#[tauri::command]
+pub async fn sync_download_assets(
+    size_cap: Option<u64>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<crate::sync::AssetSyncResult> {
+    let credentials = sync_credentials(&app_handle).await?;
+    let dir = app_dir(app_handle.clone()).await?;
+    let config = get_config(app_handle).await?;
+    let paths = api::get_used_paths(&config);
+
+    crate::sync::download_assets(
+        &credentials.token,
+        &credentials.repo,
+        &dir,
+        &paths,
+        size_cap.unwrap_or(crate::sync::DEFAULT_ASSET_SIZE_CAP),
+    )
+    .await
+    .map_err(AppError::from)
+}
+
 async fn get_dir_size(path: PathBuf) -> AppResult<u64> {
     let mut total_size: u64 = 0;
     let mut entries = tokio::fs::read_dir(path).await.map_err(AppError::Io)?;
@@ -491,32 +684,19 @@ async fn get_dir_size(path: PathBuf) -> AppResult<u64> {
     Ok(total_size)
 }
 
-#[tauri::command]
-pub async fn write_log(
-    level: &str,
-    module: &str,
-    message: &str,
-    app_handle: tauri::AppHandle,
-) -> AppResult<()> {
-    let dir = app_dir(app_handle).await?;
-    let log_path = crate::core::get_log_path(dir);
-
-    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
-    let log_line = format!("[{}] [{}] [{}] {}\n", timestamp, level, module, message);
-
-    // Append to log file
-    let mut file = tokio::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(log_path)
-        .await
-        .map_err(AppError::Io)?;
-
-    use tokio::io::AsyncWriteExt;
-    file.write_all(log_line.as_bytes())
-        .await
-        .map_err(AppError::Io)?;
+/// Largest slice of the active log returned by `read_log`.
+const READ_LOG_MAX_BYTES: u64 = 1024 * 1024;
 
+/// Forward a frontend log line into the Rust log file.
+#[tauri::command]
+pub async fn write_log(level: &str, module: &str, message: &str) -> AppResult<()> {
+    match level.to_ascii_lowercase().as_str() {
+        "error" => tracing::error!(target: "frontend", module, "{}", message),
+        "warn" => tracing::warn!(target: "frontend", module, "{}", message),
+        "debug" => tracing::debug!(target: "frontend", module, "{}", message),
+        "trace" => tracing::trace!(target: "frontend", module, "{}", message),
+        _ => tracing::info!(target: "frontend", module, "{}", message),
+    }
     Ok(())
 }
 
@@ -531,48 +711,79 @@ pub async fn clear_log(app_handle: tauri::AppHandle) -> AppResult<()> {
     let dir = app_dir(app_handle).await?;
     let log_path = crate::core::get_log_path(dir);
 
-    if tokio::fs::try_exists(&log_path).await.unwrap_or(false) {
-        tokio::fs::remove_file(log_path)
-            .await
-            .map_err(AppError::Io)?;
-    }
+    tokio::task::spawn_blocking(move || crate::logging::clear(&log_path))
+        .await
+        .map_err(|e| AppError::Unknown(e.to_string()))??;
 
     Ok(())
 }
 
+/// Total size of the active and rotated log files.
 #[tauri::command]
 pub async fn get_log_size(app_handle: tauri::AppHandle) -> AppResult<u64> {
     let dir = app_dir(app_handle).await?;
     let log_path = crate::core::get_log_path(dir);
 
-    if tokio::fs::try_exists(&log_path).await.unwrap_or(false)
-        && let Ok(metadata) = tokio::fs::metadata(&log_path).await
-    {
-        return Ok(metadata.len());
+    let mut total_size = 0;
+    for file in crate::logging::log_files(&log_path) {
+        if let Ok(metadata) = tokio::fs::metadata(&file).await {
+            total_size += metadata.len();
+        }
     }
 
-    Ok(0)
+    Ok(total_size)
 }
 
+/// Tail of the active log file, capped at [`READ_LOG_MAX_BYTES`].
 #[tauri::command]
 pub async fn read_log(app_handle: tauri::AppHandle) -> AppResult<String> {
     let dir = app_dir(app_handle).await?;
     let log_path = crate::core::get_log_path(dir);
 
-    if tokio::fs::try_exists(&log_path).await.unwrap_or(false) {
-        let content = tokio::fs::read_to_string(&log_path)
-            .await
-            .map_err(AppError::Io)?;
-        return Ok(content);
+    if !tokio::fs::try_exists(&log_path).await.unwrap_or(false) {
+        return Ok(String::new());
     }
 
-    Ok(String::new())
+    let content =
+        tokio::task::spawn_blocking(move || crate::logging::tail(&log_path, READ_LOG_MAX_BYTES))
+            .await
+            .map_err(|e| AppError::Unknown(e.to_string()))??;
+    Ok(content)
+}
+
+#[tauri::command]
+pub async fn query_logs(
+    level: Option<String>,
+    module: Option<String>,
+    since: Option<i64>,
+    limit: Option<usize>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<Vec<crate::logging::LogEntry>> {
+    let dir = app_dir(app_handle).await?;
+    let log_path = crate::core::get_log_path(dir);
+    let query = crate::logging::LogQuery {
+        level,
+        module,
+        since,
+        limit: limit.unwrap_or(500),
+    };
+
+    let entries = tokio::task::spawn_blocking(move || crate::logging::query(&log_path, &query))
+        .await
+        .map_err(|e| AppError::Unknown(e.to_string()))??;
+    Ok(entries)
+}
+
+#[tauri::command]
+pub async fn set_log_level(level: &str) -> AppResult<()> {
+    crate::logging::set_level(level)
 }
 
 #[tauri::command]
 pub async fn save_audio(
     playlist_id: String,
     audio_id: Option<String>,
+    replay_gain: Option<bool>,
     app_handle: tauri::AppHandle,
 ) -> AppResult<Vec<String>> {
     let app_dir = app_dir(app_handle.clone()).await?;
@@ -582,7 +793,7 @@ pub async fn save_audio(
         .playlists
         .iter()
         .find(|p| p.id.as_deref() == Some(&playlist_id))
-        .ok_or_else(|| AppError::Unknown(format!("Playlist not found: {}", playlist_id)))?;
+        .ok_or_else(|| AppError::NotFound(format!("Playlist not found: {}", playlist_id)))?;
 
     let download_dir = api::external_dir(&app_handle)?;
 
@@ -597,7 +808,7 @@ pub async fn save_audio(
     };
 
     if audios_to_save.is_empty() {
-        return Err(AppError::Unknown(format!(
+        return Err(AppError::NotFound(format!(
             "No audio found to save in playlist: {}",
             playlist_id
         )));
@@ -607,12 +818,238 @@ pub async fn save_audio(
     for audio in audios_to_save {
         let saved_path =
             api::save_audio(playlist, audio, app_dir.clone(), download_dir.clone()).await?;
+        if replay_gain.unwrap_or(false)
+            && let Some(loudness) = audio.loudness
+        {
+            // The copy is saved either way, a missing tag is not worth failing for
+            let path = PathBuf::from(&saved_path);
+            let result =
+                tokio::task::spawn_blocking(move || crate::loudness::write_tags(&path, &loudness))
+                    .await
+                    .map_err(|e| AppError::Unknown(e.to_string()))?;
+            if let Err(e) = result {
+                warn!("Failed to write ReplayGain tags to {}: {}", saved_path, e);
+            }
+        }
         saved_paths.push(saved_path);
     }
 
     Ok(saved_paths)
 }
 
+/// Save every audio of a playlist like `save_audio`, then write a playlist
+/// file with relative paths next to them. Returns the playlist file path.
+#[tauri::command]
+pub async fn export_playlist(
+    playlist_id: String,
+    format: crate::playlists::PlaylistFormat,
+    app_handle: tauri::AppHandle,
+) -> AppResult<String> {
+    let app_dir = app_dir(app_handle.clone()).await?;
+    let config = get_config(app_handle.clone()).await?;
+    let playlist = config
+        .playlists
+        .iter()
+        .find(|p| p.id.as_deref() == Some(&playlist_id))
+        .ok_or_else(|| AppError::NotFound(format!("Playlist not found: {}", playlist_id)))?;
+    let download_dir = api::external_dir(&app_handle)?;
+
+    let mut entries = Vec::with_capacity(playlist.audios.len());
+    for audio in &playlist.audios {
+        let relative = api::get_save_path(playlist, audio);
+        if !tokio::fs::try_exists(download_dir.join(&relative))
+            .await
+            .unwrap_or(false)
+            && let Err(e) =
+                api::save_audio(playlist, audio, app_dir.clone(), download_dir.clone()).await
+        {
+            // Keep the remaining tracks rather than failing the whole export
+            error!("Skipping '{}' in playlist export: {}", audio.audio.title, e);
+            continue;
+        }
+        entries.push(crate::playlists::PlaylistEntry {
+            location: relative.to_string_lossy().replace("\\", "/"),
+            title: Some(audio.audio.title.clone()),
+            duration: audio.audio.duration.map(|d| d as f64),
+        });
+    }
+
+    let title = playlist.title.clone();
+    let content = crate::playlists::write_playlist(
+        &crate::playlists::ParsedPlaylist {
+            title: title.clone(),
+            entries,
+        },
+        format,
+    )?;
+    // The id keeps playlists sharing a title from overwriting each other
+    let filename = format!(
+        "{}.{}",
+        sanitize_filename::sanitize(format!(
+            "{} [{}]",
+            title.as_deref().unwrap_or("Unknown"),
+            playlist_id
+        )),
+        format.extension()
+    );
+    let path = download_dir.join(filename);
+    tokio::fs::write(&path, content).await?;
+
+    info!("Exported playlist {} to {}", playlist_id, path.display());
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Measure the loudness of every audio not analyzed yet and the album gain
+/// of every playlist. The report is applied to the config by the frontend.
+#[tauri::command]
+pub async fn analyze_loudness(
+    task_id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<crate::loudness::LoudnessReport> {
+    let app_dir = app_dir(app_handle.clone()).await?;
+    let config = get_config(app_handle.clone()).await?;
+    let mut task = crate::progress::Task::start(&app_handle, "analyze_loudness", task_id);
+    tokio::task::spawn_blocking(move || {
+        crate::loudness::analyze_config(&config, &app_dir, &mut task)
+    })
+    .await
+    .map_err(|e| AppError::Unknown(e.to_string()))?
+}
+
+/// Playback gain in dB of an audio, from its playlist's album gain when
+/// `album` is set. `None` until the audio was analyzed.
+#[tauri::command]
+pub async fn replay_gain(
+    playlist_id: String,
+    audio_id: String,
+    album: Option<bool>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<Option<f64>> {
+    let config = get_config(app_handle).await?;
+    let gain = config
+        .playlists
+        .iter()
+        .find(|p| p.id.as_deref() == Some(&playlist_id))
+        .and_then(|p| p.audios.iter().find(|a| a.audio.id == audio_id))
+        .and_then(|a| a.loudness)
+        .map(|l| l.gain(album.unwrap_or(false)));
+    Ok(gain)
+}
+
+/// Detect the leading and trailing silence of every audio not analyzed yet.
+/// The bounds, by audio path, are applied to the config by the frontend.
+#[tauri::command]
+pub async fn detect_silence(
+    task_id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<std::collections::HashMap<String, crate::silence::Bounds>> {
+    let app_dir = app_dir(app_handle.clone()).await?;
+    let config = get_config(app_handle.clone()).await?;
+    let mut task = crate::progress::Task::start(&app_handle, "detect_silence", task_id);
+    tokio::task::spawn_blocking(move || {
+        crate::silence::analyze_config(&config, &app_dir, &mut task)
+    })
+    .await
+    .map_err(|e| AppError::Unknown(e.to_string()))?
+}
+
+/// Write the part of an audio between `start_ms` and `end_ms` to a new file
+/// in `format` (mp3 by default), under `assets/{Platform}/audios` whatever
+/// the original's location. Returns its path relative to the app directory.
+#[tauri::command]
+pub async fn trim_audio(
+    input_path: String,
+    start_ms: u64,
+    end_ms: Option<u64>,
+    format: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<String> {
+    let app_dir = app_dir(app_handle.clone()).await?;
+    let config = get_config(app_handle).await?;
+    let audio = config
+        .playlists
+        .iter()
+        .flat_map(|p| &p.audios)
+        .find(|a| a.path == input_path)
+        .ok_or_else(|| AppError::NotFound(format!("Audio not found: {}", input_path)))?;
+    let input = audio.file(&app_dir);
+    let format = format.unwrap_or_else(|| "mp3".to_string());
+    crate::transcode::output_format(&format)?;
+
+    let stem = input
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let end = end_ms.map_or("end".to_string(), |ms| ms.to_string());
+    // Files referenced in place live outside the app directory, so the copy
+    // never goes next to its original
+    let path = format!(
+        "{}/{:?}/{}/{stem}_{start_ms}-{end}.{format}",
+        ASSETS_DIR, audio.audio.platform, AUDIOS_DIR
+    );
+    let output = app_dir.join(&path);
+
+    if !tokio::fs::try_exists(&output).await.unwrap_or(false) {
+        if let Some(parent) = output.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let (source, target) = (input.clone(), output.clone());
+        tokio::task::spawn_blocking(move || {
+            let wav = crate::transcode::cut(&source, start_ms, end_ms)?;
+            let data = crate::transcode::encode(wav, &format)?;
+            std::fs::write(&target, data)
+                .map_err(|e| AppError::transcode("Failed to write output", e))
+        })
+        .await
+        .map_err(|e| AppError::Unknown(e.to_string()))??;
+        info!("Trimmed '{}' to {}", input.display(), output.display());
+    }
+
+    Ok(path)
+}
+
+/// Read a pasted `mm:ss Title` list into segments for [`split_audio`].
+#[tauri::command]
+pub fn parse_timestamps(text: String) -> Vec<crate::split::Segment> {
+    crate::split::parse_timestamps(&text)
+}
+
+/// Cut a downloaded audio into one track per segment. The returned playlist
+/// is added to the config by the frontend.
+#[tauri::command]
+pub async fn split_audio(
+    audio_id: String,
+    segments: Vec<crate::split::Segment>,
+    task_id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<LocalPlaylist> {
+    let app_dir = app_dir(app_handle.clone()).await?;
+    let config = get_config(app_handle.clone()).await?;
+    let audio = config
+        .playlists
+        .into_iter()
+        .flat_map(|p| p.audios)
+        .find(|a| a.audio.id == audio_id)
+        .ok_or_else(|| AppError::NotFound(format!("Audio not found: {}", audio_id)))?;
+    let mut task = crate::progress::Task::start(&app_handle, "split_audio", task_id);
+    tokio::task::spawn_blocking(move || crate::split::split(&audio, &segments, &app_dir, &mut task))
+        .await
+        .map_err(|e| AppError::Unknown(e.to_string()))?
+}
+
+/// Read the real duration, codec, bitrate, sample rate, channels and
+/// container of an audio file, relative to the app directory.
+#[tauri::command]
+pub async fn probe_audio(
+    path: String,
+    app_handle: tauri::AppHandle,
+) -> AppResult<crate::probe::AudioInfo> {
+    let path = app_dir(app_handle).await?.join(path);
+    tokio::task::spawn_blocking(move || crate::probe::probe(&path))
+        .await
+        .map_err(|e| AppError::Unknown(e.to_string()))?
+}
+
 #[tauri::command]
 pub async fn transcode_audio(
     input_path: &str,
@@ -623,12 +1060,18 @@ pub async fn transcode_audio(
     let input = app_dir.join(input_path);
 
     // Validate format
-    let output_format = match format {
-        "mp3" => trackex::format::OutputFormat::Mp3,
-        "ogg" => trackex::format::OutputFormat::Ogg,
-        "wav" => trackex::format::OutputFormat::Wav,
-        _ => return Err(AppError::Unknown(format!("Unsupported format: {format}"))),
-    };
+    crate::transcode::output_format(format)?;
+
+    // Nothing to do when the file already has the target codec and container
+    if let Some(info) = api::probe_file(input.clone()).await
+        && info.matches_format(format)
+    {
+        info!(
+            "'{}' is already {format}, skipping transcode",
+            input.display()
+        );
+        return Ok(input_path.to_string());
+    }
 
     // Generate output path: replace extension
     let output = {
@@ -641,7 +1084,7 @@ pub async fn transcode_audio(
     if tokio::fs::try_exists(&output).await.unwrap_or(false) {
         return Ok(output
             .strip_prefix(&app_dir)
-            .map_err(|e| AppError::Unknown(e.to_string()))?
+            .map_err(|e| AppError::PathError(e.to_string()))?
             .to_string_lossy()
             .to_string());
     }
@@ -649,27 +1092,19 @@ pub async fn transcode_audio(
     // Read input file (I/O is caller responsibility in new trackex API)
     let input_data = tokio::fs::read(&input)
         .await
-        .map_err(|e| AppError::Unknown(format!("Failed to read input: {e}")))?;
-
-    let config = trackex::config::AudioConfig {
-        input_data,
-        format: output_format,
-        sample_rate: None,
-        channels: None,
-    };
+        .map_err(|e| AppError::transcode("Failed to read input", e))?;
 
     // Transcode — trackex returns output bytes directly
-    let output_data = trackex::extract_audio(&config).map_err(|e| {
-        eprintln!("Transcode failed: '{}' → {format}: {e}", input.display());
-        AppError::Unknown(e.to_string())
+    let output_data = crate::transcode::encode(input_data, format).inspect_err(|e| {
+        error!("Transcode failed: '{}' → {format}: {e}", input.display());
     })?;
 
     // Write output file
     tokio::fs::write(&output, &output_data)
         .await
-        .map_err(|e| AppError::Unknown(format!("Failed to write output: {e}")))?;
+        .map_err(|e| AppError::transcode("Failed to write output", e))?;
 
-    println!(
+    info!(
         "Successfully transcoded: '{}' → {format} ({})",
         input.display(),
         output.display()
@@ -677,7 +1112,269 @@ pub async fn transcode_audio(
 
     Ok(output
         .strip_prefix(&app_dir)
-        .map_err(|e| AppError::Unknown(e.to_string()))?
+        .map_err(|e| AppError::PathError(e.to_string()))?
         .to_string_lossy()
         .to_string())
 }
+
+/// Copy an .lrc file into the lyrics store and attach it to `audio`. SRT,
+/// WebVTT and Bilibili subtitles are converted to LRC first. The returned
+/// audio is saved into the config by the frontend.
+#[tauri::command]
+pub async fn import_lyrics(
+    audio: LocalAudio,
+    path: PathBuf,
+    app_handle: tauri::AppHandle,
+) -> AppResult<LocalAudio> {
+    let app_dir = app_dir(app_handle.clone()).await?;
+    let bytes = tokio::fs::read(&path).await?;
+    let mut text = String::from_utf8_lossy(&bytes).into_owned();
+    if let Some(format) = crate::subtitles::SubtitleFormat::detect(&path, &text) {
+        text = crate::subtitles::to_lrc(&text, format)?;
+    }
+    if crate::lyrics::parse_lrc(&text).lines.is_empty() {
+        return Err(AppError::NotFound(format!(
+            "No lyrics in {}",
+            path.display()
+        )));
+    }
+    let audio = crate::lyrics::store(&audio, &text, &app_dir).await?;
+    reindex_lyrics(app_handle, app_dir).await;
+    Ok(audio)
+}
+
+/// Look lyrics up for `audio`, first in its tags, then online, and attach
+/// the first found. `None` when no provider has any.
+#[tauri::command]
+pub async fn attach_lyrics(
+    audio: LocalAudio,
+    app_handle: tauri::AppHandle,
+) -> AppResult<Option<LocalAudio>> {
+    let app_dir = app_dir(app_handle.clone()).await?;
+    let attached =
+        crate::lyrics::attach_first(&crate::lyrics::default_providers(), &audio, &app_dir).await?;
+    if attached.is_some() {
+        reindex_lyrics(app_handle, app_dir).await;
+    }
+    Ok(attached)
+}
+
+/// Time-synced lines of `audio`, with the offset already applied.
+#[tauri::command]
+pub async fn get_lyrics(
+    audio: LocalAudio,
+    app_handle: tauri::AppHandle,
+) -> AppResult<Option<crate::lyrics::Lyrics>> {
+    let app_dir = app_dir(app_handle).await?;
+    let text = crate::lyrics::read_text(&audio, &app_dir).await?;
+    Ok(text.map(|t| crate::lyrics::parse_lrc(&t).shifted()))
+}
+
+/// Store `offset_ms` as the `[offset:]` of the lyrics of `audio` and return
+/// them shifted. Embedded lyrics are copied into the store first.
+#[tauri::command]
+pub async fn set_lyrics_offset(
+    audio: LocalAudio,
+    offset_ms: i64,
+    app_handle: tauri::AppHandle,
+) -> AppResult<(LocalAudio, crate::lyrics::Lyrics)> {
+    let app_dir = app_dir(app_handle.clone()).await?;
+    let text = crate::lyrics::read_text(&audio, &app_dir)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("No lyrics for {}", audio.audio.title)))?;
+    let text = crate::lyrics::with_offset(&text, offset_ms);
+    let audio = crate::lyrics::store(&audio, &text, &app_dir).await?;
+    reindex_lyrics(app_handle, app_dir).await;
+    Ok((audio, crate::lyrics::parse_lrc(&text).shifted()))
+}
+
+/// Ranked audios of the library matching `query` in their title, playlist
+/// names, platform or lyrics. Chinese and Japanese titles also match by
+/// pinyin or romaji.
+#[tauri::command]
+pub async fn search_library(
+    query: String,
+    limit: Option<usize>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<Vec<crate::search::LibraryHit>> {
+    // The index follows config saves and lyrics edits, only the first
+    // search after startup builds it
+    if !crate::search::is_loaded() {
+        let app_dir = app_dir(app_handle.clone()).await?;
+        let config = get_config(app_handle).await?;
+        tokio::task::spawn_blocking(move || crate::search::update(&config, &app_dir))
+            .await
+            .map_err(|e| AppError::Unknown(e.to_string()))?;
+    }
+    tokio::task::spawn_blocking(move || crate::search::search(&query, limit.unwrap_or(50)))
+        .await
+        .map_err(|e| AppError::Unknown(e.to_string()))
+}
+
+/// Search the platforms for `query`, or only `platform` when given. Results
+/// are lightweight hits pointing at the video page, they are extracted by
+/// [`api::download_audio`] once downloaded.
+#[tauri::command]
+pub async fn search_platform(
+    platform: Option<Platform>,
+    query: String,
+    page: Option<u32>,
+) -> AppResult<Vec<Audio>> {
+    let platforms = match platform {
+        Some(platform) => vec![platform],
+        None => crate::discover::ENABLED_PLATFORMS.to_vec(),
+    };
+    let audios = crate::discover::search(&platforms, query.trim(), page.unwrap_or(1)).await?;
+    info!("Found {} audios for '{}'", audios.len(), query);
+    Ok(audios)
+}
+
+/// Group library audios that hold the same song, by id, by content or by
+/// similar title and duration.
+#[tauri::command]
+pub async fn find_duplicates(
+    app_handle: tauri::AppHandle,
+) -> AppResult<Vec<crate::duplicates::DuplicateCluster>> {
+    let app_dir = app_dir(app_handle.clone()).await?;
+    let config = get_config(app_handle).await?;
+    let clusters = tokio::task::spawn_blocking(move || crate::duplicates::find(&config, &app_dir))
+        .await
+        .map_err(|e| AppError::Unknown(e.to_string()))?;
+    info!("Found {} duplicate clusters", clusters.len());
+    Ok(clusters)
+}
+
+/// Replace `duplicates` with `keep` in every playlist. The frontend saves
+/// the returned playlists and only then deletes the removed files, so a
+/// failed save leaves no playlist pointing at a deleted file.
+#[tauri::command]
+pub async fn merge_duplicates(
+    keep: LocalAudio,
+    duplicates: Vec<LocalAudio>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<crate::duplicates::MergeResult> {
+    let config = get_config(app_handle).await?;
+    let result = crate::duplicates::merge(&config, &keep, &duplicates);
+    info!(
+        "Merged {} duplicates into '{}'",
+        duplicates.len(),
+        keep.audio.title
+    );
+    Ok(result)
+}
+
+/// Fingerprint every audio without a fingerprint yet. The fingerprints, by
+/// audio path, are applied to the config by the frontend.
+#[tauri::command]
+pub async fn fingerprint_audios(
+    task_id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<std::collections::HashMap<String, String>> {
+    let app_dir = app_dir(app_handle.clone()).await?;
+    let config = get_config(app_handle.clone()).await?;
+    let mut task = crate::progress::Task::start(&app_handle, "fingerprint", task_id);
+    tokio::task::spawn_blocking(move || {
+        crate::fingerprint::analyze_config(&config, &app_dir, &mut task)
+    })
+    .await
+    .map_err(|e| AppError::Unknown(e.to_string()))?
+}
+
+/// Find fingerprinted audios holding the same recording, the highest
+/// bitrate first. Only matches spanning platforms unless `cross_platform`
+/// is `false`.
+#[tauri::command]
+pub async fn match_tracks(
+    cross_platform: Option<bool>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<Vec<crate::fingerprint::TrackMatch>> {
+    let config = get_config(app_handle).await?;
+    tokio::task::spawn_blocking(move || {
+        crate::fingerprint::match_tracks(&config, cross_platform.unwrap_or(true))
+    })
+    .await
+    .map_err(|e| AppError::Unknown(e.to_string()))
+}
+
+/// Append a playback to the play history. `position` is where playback
+/// stopped, in seconds.
+#[tauri::command]
+pub async fn record_play(
+    audio_id: String,
+    platform: Platform,
+    playlist_id: String,
+    position: f64,
+    duration: f64,
+    app_handle: tauri::AppHandle,
+) -> AppResult<()> {
+    let app_dir = app_dir(app_handle).await?;
+    let record = crate::history::PlayRecord {
+        audio_id,
+        playlist_id,
+        platform: Some(platform),
+        position,
+        duration,
+        played_at: Local::now().timestamp_millis(),
+    };
+    tokio::task::spawn_blocking(move || crate::history::append(&app_dir, &record))
+        .await
+        .map_err(|e| AppError::Unknown(e.to_string()))?
+}
+
+/// The last play of each recently played audio, most recent first.
+#[tauri::command]
+pub async fn recent_plays(
+    limit: Option<usize>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<Vec<crate::history::PlayRecord>> {
+    let app_dir = app_dir(app_handle).await?;
+    let records = tokio::task::spawn_blocking(move || crate::history::read(&app_dir))
+        .await
+        .map_err(|e| AppError::Unknown(e.to_string()))??;
+    Ok(crate::history::recent(&records, limit.unwrap_or(50)))
+}
+
+/// Plays and listening time over the last `days`, or all time.
+#[tauri::command]
+pub async fn play_stats(
+    days: Option<u32>,
+    limit: Option<usize>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<crate::history::HistoryStats> {
+    let app_dir = app_dir(app_handle).await?;
+    let since = days.map(|d| Local::now().timestamp_millis() - d as i64 * 24 * 60 * 60 * 1000);
+    tokio::task::spawn_blocking(move || {
+        let records = crate::history::read(&app_dir)?;
+        Ok(crate::history::stats(&records, since, limit.unwrap_or(50)))
+    })
+    .await
+    .map_err(|e| AppError::Unknown(e.to_string()))?
+}
+
+/// Merge the play history with the copy in the sync repository and upload
+/// the result. Returns the number of records.
+#[tauri::command]
+pub async fn sync_play_history(
+    device_id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> AppResult<usize> {
+    let credentials = sync_credentials(&app_handle).await?;
+    let dir = app_dir(app_handle).await?;
+    let path = crate::core::HISTORY_FILE;
+    let remote = crate::sync::download(&credentials.token, &credentials.repo, Some(path)).await?;
+    let merged = tokio::task::spawn_blocking(move || crate::history::merge(&dir, &remote))
+        .await
+        .map_err(|e| AppError::Unknown(e.to_string()))??;
+    let count = merged.iter().filter(|&&b| b == b'\n').count();
+    crate::sync::update(
+        &credentials.token,
+        &credentials.repo,
+        merged,
+        Some(path),
+        Some("Update play history"),
+        device_id.as_deref(),
+    )
+    .await?;
+    info!("Synced {} play history records", count);
+    Ok(count)
+}
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use musicfree::{Audio, Platform};
 use serde::{Deserialize, Serialize};
@@ -7,15 +7,58 @@ pub const APP_NAME: &str = "musicfree";
 pub const ASSETS_DIR: &str = "assets";
 pub const AUDIOS_DIR: &str = "audios";
 pub const COVERS_DIR: &str = "covers";
+pub const LYRICS_DIR: &str = "lyrics";
+/// Prefix of the [`LocalAudio::path`] of files referenced in place. Nothing
+/// is stored under it, the file is at [`LocalAudio::external_path`]
+pub const EXTERNAL_DIR: &str = "external";
 pub const CONFIG_FILE: &str = "musicfree.json";
 pub const LOG_FILE: &str = "musicfree.log";
+pub const HISTORY_FILE: &str = "history.jsonl";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocalAudio {
+    /// Relative to the app directory, and the key of the audio in reports
     pub path: String,
+    /// Absolute path of a file referenced in place instead of copied, see
+    /// [`crate::library::local_audio`]. Such files are left out of backups
+    /// and asset sync and are never deleted by the app
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_path: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub cover_path: Option<String>,
     pub audio: Audio,
+    /// Probed from the file itself, see [`crate::probe::probe`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub info: Option<crate::probe::AudioInfo>,
+    /// Measured by [`crate::loudness::analyze`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loudness: Option<crate::loudness::Loudness>,
+    /// Where the audible part starts, see [`crate::silence::detect`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_ms: Option<u64>,
+    /// Where the audible part ends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_ms: Option<u64>,
+    /// Stored LRC file, see [`crate::lyrics::store`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lyrics_path: Option<String>,
+    /// Acoustic fingerprint, see [`crate::fingerprint::compute`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+}
+
+impl LocalAudio {
+    /// Where the audio file is on disk.
+    pub fn file(&self, app_dir: &Path) -> PathBuf {
+        match &self.external_path {
+            Some(path) => PathBuf::from(path),
+            None => app_dir.join(&self.path),
+        }
+    }
+
+    pub fn is_external(&self) -> bool {
+        self.external_path.is_some()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
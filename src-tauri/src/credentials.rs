@@ -0,0 +1,283 @@
+use crate::error::{AppError, AppResult, SyncError};
+use base64::{Engine as _, engine::general_purpose};
+use chacha20poly1305::{
+    ChaCha20Poly1305, Key, Nonce,
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// Credential file used where no OS keyring is available, stored next to
+/// `musicfree.json`.
+pub const CREDENTIALS_FILE: &str = "credentials.bin";
+
+/// Random key of this device the credential file is encrypted with, kept
+/// in the device directory rather than next to the file.
+const DEVICE_KEY_FILE: &str = "device.key";
+
+#[cfg(os_keyring)]
+const KEYRING_SERVICE: &str = "musicfree";
+#[cfg(os_keyring)]
+const KEYRING_USER: &str = "sync";
+
+/// GitHub credentials used by the sync commands.
+/// Never returned to the frontend; use [`SyncStatus`] instead.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SyncCredentials {
+    pub token: String,
+    pub repo: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialBackend {
+    /// OS keyring (Keychain, Credential Manager, Secret Service)
+    Keyring,
+    /// File in the app data directory, encrypted with the device key
+    File,
+}
+
+/// What the frontend is allowed to know about the stored credentials.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncStatus {
+    pub configured: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<CredentialBackend>,
+}
+
+/// On-disk layout of [`CREDENTIALS_FILE`]
+#[derive(Serialize, Deserialize)]
+struct EncryptedFile {
+    version: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+fn credentials_path(app_dir: &Path) -> PathBuf {
+    app_dir.join(CREDENTIALS_FILE)
+}
+
+/// Current [`EncryptedFile::version`]. Versions 1 and 2 derived the key from
+/// the salt and the app data path alone, so the file gave the key away;
+/// version 1 also mixed in the hostname, so renaming the host lost the token.
+const FILE_VERSION: u32 = 3;
+
+/// Write `data` readable by the current user only.
+async fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path).await?;
+    // `mode` only applies to new files
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .await?;
+    }
+    file.write_all(data).await?;
+    file.flush().await
+}
+
+/// The key of this device in `device_dir`, created on first use. The
+/// directory is neither backed up nor synced, so a copied credential file
+/// cannot be read without it.
+async fn device_key(device_dir: &Path) -> AppResult<Vec<u8>> {
+    let path = device_dir.join(DEVICE_KEY_FILE);
+    match tokio::fs::read(&path).await {
+        Ok(key) if key.len() == 32 => return Ok(key),
+        Ok(_) => return Err(SyncError::Credentials("Invalid device key".to_string()).into()),
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        Err(_) => {}
+    }
+    let key = ChaCha20Poly1305::generate_key(&mut OsRng).to_vec();
+    tokio::fs::create_dir_all(device_dir).await?;
+    write_private(&path, &key).await?;
+    Ok(key)
+}
+
+/// Derive the file key from the salt stored in the file and the device key.
+/// Files older than version 3 have no device key and mix in the app data path.
+fn derive_key(app_dir: &Path, device_key: &[u8], salt: &[u8], version: u32) -> Key {
+    let mut hasher = Sha256::new();
+    if version >= 3 {
+        hasher.update(b"musicfree-credentials-v3");
+        hasher.update(salt);
+        hasher.update(device_key);
+    } else {
+        hasher.update(b"musicfree-credentials-v1");
+        hasher.update(salt);
+        if version == 1 {
+            hasher.update(tauri_plugin_os::hostname().as_bytes());
+        }
+        hasher.update(app_dir.to_string_lossy().as_bytes());
+    }
+    *Key::from_slice(&hasher.finalize())
+}
+
+fn encrypt(app_dir: &Path, device_key: &[u8], credentials: &SyncCredentials) -> AppResult<Vec<u8>> {
+    let salt = ChaCha20Poly1305::generate_key(&mut OsRng);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let key = derive_key(app_dir, device_key, &salt, FILE_VERSION);
+    let cipher = ChaCha20Poly1305::new(&key);
+
+    let plaintext = serde_json::to_vec(credentials)?;
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_ref())
+        .map_err(|e| SyncError::Credentials(format!("Failed to store credentials: {}", e)))?;
+
+    let file = EncryptedFile {
+        version: FILE_VERSION,
+        salt: general_purpose::STANDARD.encode(salt),
+        nonce: general_purpose::STANDARD.encode(nonce),
+        ciphertext: general_purpose::STANDARD.encode(ciphertext),
+    };
+    Ok(serde_json::to_vec(&file)?)
+}
+
+/// Read the file, returning whether it uses an older [`FILE_VERSION`].
+fn decrypt(app_dir: &Path, device_key: &[u8], data: &[u8]) -> AppResult<(SyncCredentials, bool)> {
+    let file: EncryptedFile = serde_json::from_slice(data)?;
+    let decode = |s: &str| {
+        general_purpose::STANDARD
+            .decode(s)
+            .map_err(|e| AppError::Sync(SyncError::Base64Decode(e)))
+    };
+    let salt = decode(&file.salt)?;
+    let nonce = decode(&file.nonce)?;
+    let ciphertext = decode(&file.ciphertext)?;
+    if nonce.len() != 12 {
+        return Err(SyncError::Credentials("Invalid credential nonce".to_string()).into());
+    }
+
+    let key = derive_key(app_dir, device_key, &salt, file.version);
+    let cipher = ChaCha20Poly1305::new(&key);
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+        .map_err(|_| {
+            SyncError::Credentials(
+                "Stored credentials belong to another install, please sign in again".to_string(),
+            )
+        })?;
+    Ok((
+        serde_json::from_slice(&plaintext)?,
+        file.version < FILE_VERSION,
+    ))
+}
+
+#[cfg(os_keyring)]
+fn keyring_entry() -> Option<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).ok()
+}
+
+#[cfg(os_keyring)]
+fn keyring_load() -> Option<SyncCredentials> {
+    let secret = keyring_entry()?.get_password().ok()?;
+    serde_json::from_str(&secret).ok()
+}
+
+#[cfg(os_keyring)]
+fn keyring_save(credentials: &SyncCredentials) -> bool {
+    let Some(entry) = keyring_entry() else {
+        return false;
+    };
+    let Ok(secret) = serde_json::to_string(credentials) else {
+        return false;
+    };
+    match entry.set_password(&secret) {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::warn!(
+                "OS keyring unavailable, falling back to the credential file: {}",
+                e
+            );
+            false
+        }
+    }
+}
+
+#[cfg(os_keyring)]
+fn keyring_clear() {
+    if let Some(entry) = keyring_entry() {
+        let _ = entry.delete_credential();
+    }
+}
+
+/// Load the stored credentials together with the backend they came from.
+/// `device_dir` holds the key of the credential file.
+pub async fn load(
+    app_dir: &Path,
+    device_dir: &Path,
+) -> AppResult<Option<(SyncCredentials, CredentialBackend)>> {
+    #[cfg(os_keyring)]
+    if let Some(credentials) = tokio::task::spawn_blocking(keyring_load)
+        .await
+        .map_err(|e| AppError::Unknown(e.to_string()))?
+    {
+        return Ok(Some((credentials, CredentialBackend::Keyring)));
+    }
+
+    let path = credentials_path(app_dir);
+    if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+        return Ok(None);
+    }
+    let data = tokio::fs::read(&path).await?;
+    let device_key = device_key(device_dir).await?;
+    let (credentials, outdated) = decrypt(app_dir, &device_key, &data)?;
+    if outdated {
+        write_private(&path, &encrypt(app_dir, &device_key, &credentials)?).await?;
+    }
+    Ok(Some((credentials, CredentialBackend::File)))
+}
+
+/// Store credentials, preferring the OS keyring when it is available.
+pub async fn save(
+    app_dir: &Path,
+    device_dir: &Path,
+    credentials: SyncCredentials,
+) -> AppResult<CredentialBackend> {
+    let path = credentials_path(app_dir);
+
+    #[cfg(os_keyring)]
+    {
+        let credentials = credentials.clone();
+        if tokio::task::spawn_blocking(move || keyring_save(&credentials))
+            .await
+            .map_err(|e| AppError::Unknown(e.to_string()))?
+        {
+            // Do not leave an older copy behind in the file backend
+            if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+                tokio::fs::remove_file(&path).await?;
+            }
+            return Ok(CredentialBackend::Keyring);
+        }
+        // An older keyring entry would otherwise win over the file in `load`
+        tokio::task::spawn_blocking(keyring_clear)
+            .await
+            .map_err(|e| AppError::Unknown(e.to_string()))?;
+    }
+
+    let device_key = device_key(device_dir).await?;
+    write_private(&path, &encrypt(app_dir, &device_key, &credentials)?).await?;
+    Ok(CredentialBackend::File)
+}
+
+/// Remove credentials from every backend.
+pub async fn clear(app_dir: &Path) -> AppResult<()> {
+    #[cfg(os_keyring)]
+    tokio::task::spawn_blocking(keyring_clear)
+        .await
+        .map_err(|e| AppError::Unknown(e.to_string()))?;
+
+    let path = credentials_path(app_dir);
+    if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+        tokio::fs::remove_file(&path).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,183 @@
+use crate::error::{AppError, AppResult};
+use ogg::reading::PacketReader;
+use std::{fs::File, io::BufReader, path::Path};
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{CODEC_TYPE_NULL, DecoderOptions},
+    errors::Error as SymphoniaError,
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+/// Opus always decodes at this rate, whatever the input rate was.
+const OPUS_SAMPLE_RATE: u32 = 48_000;
+
+/// Longest Opus packet, 120 ms, in samples per channel.
+const OPUS_MAX_FRAME: usize = 5_760;
+
+/// Layout of the samples passed to the [`decode`] callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Spec {
+    pub sample_rate: u32,
+    pub channels: usize,
+}
+
+/// Decode the first audio track of a file, handing interleaved `f32`
+/// samples to `on_samples` packet by packet.
+///
+/// Corrupt packets are skipped the way players do. Ogg Opus, which
+/// symphonia cannot decode, goes through libopus. Blocking; run it on a
+/// blocking thread.
+pub fn decode(
+    path: &Path,
+    mut on_samples: impl FnMut(Spec, &[f32]) -> AppResult<()>,
+) -> AppResult<()> {
+    if is_ogg_opus(path) {
+        return decode_opus(path, on_samples);
+    }
+    let file = File::open(path)?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| AppError::transcode(format!("Unsupported file {}", path.display()), e))?;
+    let mut format = probed.format;
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| AppError::Transcode {
+            message: format!("No audio track in {}", path.display()),
+            source: None,
+        })?;
+    let track_id = track.id;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| AppError::transcode(format!("Unsupported codec in {}", path.display()), e))?;
+
+    let mut buffer: Option<SampleBuffer<f32>> = None;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break;
+            }
+            Err(e) => return Err(AppError::transcode("Failed to read packet", e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(AppError::transcode("Failed to decode packet", e)),
+        };
+
+        let spec = *decoded.spec();
+        let capacity = decoded.capacity() as u64;
+        let buffer = match &mut buffer {
+            Some(b) if b.capacity() >= capacity as usize * spec.channels.count() => b,
+            _ => buffer.insert(SampleBuffer::new(capacity, spec)),
+        };
+        buffer.copy_interleaved_ref(decoded);
+        on_samples(
+            Spec {
+                sample_rate: spec.rate,
+                channels: spec.channels.count(),
+            },
+            buffer.samples(),
+        )?;
+    }
+    Ok(())
+}
+
+fn ogg_packets(path: &Path) -> AppResult<PacketReader<BufReader<File>>> {
+    Ok(PacketReader::new(BufReader::new(File::open(path)?)))
+}
+
+/// Whether the first packet of the file is an Opus identification header.
+fn is_ogg_opus(path: &Path) -> bool {
+    ogg_packets(path)
+        .ok()
+        .and_then(|mut packets| packets.read_packet().ok().flatten())
+        .is_some_and(|packet| packet.data.starts_with(b"OpusHead"))
+}
+
+/// Decode Ogg Opus, dropping the pre-skip of the encoder and applying the
+/// output gain of the header.
+fn decode_opus(
+    path: &Path,
+    mut on_samples: impl FnMut(Spec, &[f32]) -> AppResult<()>,
+) -> AppResult<()> {
+    let mut packets = ogg_packets(path)?;
+    let mut next = move || {
+        packets
+            .read_packet()
+            .map_err(|e| AppError::transcode(format!("Failed to read {}", path.display()), e))
+    };
+    let invalid = || AppError::Transcode {
+        message: format!("Invalid Opus header in {}", path.display()),
+        source: None,
+    };
+
+    // OpusHead: magic, version, channels, pre-skip, input rate, gain, mapping
+    let head = next()?.ok_or_else(invalid)?.data;
+    if head.len() < 19 || !head.starts_with(b"OpusHead") {
+        return Err(invalid());
+    }
+    let channels = head[9] as usize;
+    let opus_channels = match channels {
+        1 => opus::Channels::Mono,
+        2 => opus::Channels::Stereo,
+        _ => {
+            return Err(AppError::Transcode {
+                message: format!("{} Opus channels in {}", channels, path.display()),
+                source: None,
+            });
+        }
+    };
+    let mut skip = u16::from_le_bytes([head[10], head[11]]) as usize * channels;
+    // Q7.8 dB
+    let gain_db = i16::from_le_bytes([head[16], head[17]]) as f32 / 256.0;
+    let gain = 10f32.powf(gain_db / 20.0);
+
+    let mut decoder = opus::Decoder::new(OPUS_SAMPLE_RATE, opus_channels)
+        .map_err(|e| AppError::transcode("Failed to create Opus decoder", e))?;
+    let spec = Spec {
+        sample_rate: OPUS_SAMPLE_RATE,
+        channels,
+    };
+    let mut buffer = vec![0f32; OPUS_MAX_FRAME * channels];
+    while let Some(packet) = next()? {
+        if packet.data.starts_with(b"OpusTags") {
+            continue;
+        }
+        let frames = match decoder.decode_float(&packet.data, &mut buffer, false) {
+            Ok(frames) => frames,
+            Err(_) => continue,
+        };
+        let samples = &mut buffer[..frames * channels];
+        let skipped = skip.min(samples.len());
+        skip -= skipped;
+        let samples = &mut samples[skipped..];
+        if samples.is_empty() {
+            continue;
+        }
+        if gain != 1.0 {
+            samples.iter_mut().for_each(|s| *s *= gain);
+        }
+        on_samples(spec, samples)?;
+    }
+    Ok(())
+}
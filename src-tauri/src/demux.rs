@@ -0,0 +1,323 @@
+use crate::{
+    core::LocalAudio,
+    error::{AppError, AppResult},
+};
+use musicfree::core::AudioFormat;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+use tracing::info;
+
+/// Extensions the demuxed audio is written with, see [`demuxed_path`].
+pub const DEMUXED_EXTENSIONS: &[&str] = &["m4a", "opus"];
+
+fn demux_error(path: &Path, e: impl std::fmt::Display) -> AppError {
+    AppError::Transcode {
+        message: format!("Failed to demux {}: {}", path.display(), e),
+        source: None,
+    }
+}
+
+/// Where a container's audio ends up: the same name with an audio extension.
+pub fn demuxed_path(path: &Path, ext: &str) -> PathBuf {
+    path.with_extension(ext)
+}
+
+/// Format of a demuxed file, named by the extractor crate. `None` when it
+/// has no name for it, so the caller keeps the one it had.
+pub fn demuxed_format(path: &str) -> Option<AudioFormat> {
+    let name = match Path::new(path).extension()?.to_str()? {
+        "m4a" => "M4A",
+        "opus" => "Ogg",
+        _ => return None,
+    };
+    serde_json::from_value(serde_json::Value::String(name.to_string())).ok()
+}
+
+type Mp4File = mp4::Mp4Reader<BufReader<File>>;
+
+fn open_mp4(input: &Path) -> AppResult<Mp4File> {
+    let file = File::open(input)?;
+    let size = file.metadata()?.len();
+    mp4::Mp4Reader::read_header(BufReader::new(file), size).map_err(|e| demux_error(input, e))
+}
+
+/// Id of the first AAC audio track.
+fn aac_track(mp4: &Mp4File) -> Option<u32> {
+    use mp4::{MediaType, TrackType};
+
+    mp4.tracks()
+        .iter()
+        .find(|(_, t)| {
+            matches!(t.track_type(), Ok(TrackType::Audio))
+                && matches!(t.media_type(), Ok(MediaType::AAC))
+        })
+        .map(|(&id, _)| id)
+}
+
+/// Copy the AAC track of an MP4 into an M4A, dropping the video. Returns the
+/// number of samples written, `None` when there is nothing to drop.
+fn mp4_to_m4a(input: &Path, output: &Path) -> AppResult<Option<u64>> {
+    let mut mp4 = open_mp4(input)?;
+    let has_video = mp4
+        .tracks()
+        .values()
+        .any(|t| matches!(t.track_type(), Ok(mp4::TrackType::Video)));
+    let Some(track_id) = aac_track(&mp4) else {
+        return Ok(None);
+    };
+    if !has_video {
+        return Ok(None);
+    }
+    write_aac(&mut mp4, track_id, input, output, 0, None).map(Some)
+}
+
+/// Cut the AAC track of an MP4 or M4A between `start_ms` and `end_ms` (the
+/// end when `None`) into an M4A, without re-encoding. Returns the number of
+/// samples written, `None` when the file has no AAC track.
+/// Blocking; run it on a blocking thread.
+pub fn cut_aac(
+    input: &Path,
+    output: &Path,
+    start_ms: u64,
+    end_ms: Option<u64>,
+) -> AppResult<Option<u64>> {
+    let mut mp4 = open_mp4(input)?;
+    let Some(track_id) = aac_track(&mp4) else {
+        return Ok(None);
+    };
+    write_aac(&mut mp4, track_id, input, output, start_ms, end_ms).map(Some)
+}
+
+/// Write the samples of an AAC track starting between `start_ms` and
+/// `end_ms` into a new M4A, returning how many were written.
+fn write_aac(
+    mp4: &mut Mp4File,
+    track_id: u32,
+    input: &Path,
+    output: &Path,
+    start_ms: u64,
+    end_ms: Option<u64>,
+) -> AppResult<u64> {
+    use mp4::{AacConfig, MediaConfig, Mp4Config, TrackConfig, TrackType};
+
+    let track = &mp4.tracks()[&track_id];
+    let timescale = track.timescale() as u64;
+    let track_config = TrackConfig {
+        track_type: TrackType::Audio,
+        timescale: track.timescale(),
+        language: track.language().to_string(),
+        media_conf: MediaConfig::AacConfig(AacConfig {
+            bitrate: track.bitrate(),
+            profile: track.audio_profile().map_err(|e| demux_error(input, e))?,
+            freq_index: track
+                .sample_freq_index()
+                .map_err(|e| demux_error(input, e))?,
+            chan_conf: track.channel_config().map_err(|e| demux_error(input, e))?,
+        }),
+    };
+    let config = Mp4Config {
+        major_brand: *mp4.major_brand(),
+        minor_version: mp4.minor_version(),
+        compatible_brands: mp4.compatible_brands().to_vec(),
+        timescale: mp4.timescale(),
+    };
+    let sample_count = mp4
+        .sample_count(track_id)
+        .map_err(|e| demux_error(input, e))?;
+
+    let mut writer = mp4::Mp4Writer::write_start(BufWriter::new(File::create(output)?), &config)
+        .map_err(|e| demux_error(input, e))?;
+    writer
+        .add_track(&track_config)
+        .map_err(|e| demux_error(input, e))?;
+    let mut written = 0;
+    for sample_id in 1..=sample_count {
+        let Some(sample) = mp4
+            .read_sample(track_id, sample_id)
+            .map_err(|e| demux_error(input, e))?
+        else {
+            continue;
+        };
+        let at_ms = sample.start_time * 1000 / timescale.max(1);
+        if end_ms.is_some_and(|end| at_ms >= end) {
+            break;
+        }
+        if at_ms < start_ms {
+            continue;
+        }
+        writer
+            .write_sample(1, &sample)
+            .map_err(|e| demux_error(input, e))?;
+        written += 1;
+    }
+    writer.write_end().map_err(|e| demux_error(input, e))?;
+    Ok(written)
+}
+
+/// Samples at 48 kHz in an Opus packet, from its TOC byte (RFC 6716 3.1).
+fn opus_packet_samples(packet: &[u8]) -> u64 {
+    let Some(&toc) = packet.first() else {
+        return 0;
+    };
+    let config = toc >> 3;
+    let frame = match config {
+        0..=11 => [480, 960, 1920, 2880][config as usize % 4],
+        12..=15 => [480, 960][config as usize % 2],
+        _ => [120, 240, 480, 960][config as usize % 4],
+    };
+    let frames = match toc & 0b11 {
+        0 => 1,
+        1 | 2 => 2,
+        _ => packet.get(1).map_or(0, |c| c & 0x3f) as u64,
+    };
+    frame * frames
+}
+
+/// Move the Opus track of a WebM into an Ogg Opus file, dropping the video.
+/// Returns the number of packets written, `None` when there is nothing to
+/// drop.
+fn webm_to_opus(input: &Path, output: &Path) -> AppResult<Option<u64>> {
+    use matroska_demuxer::{Frame, MatroskaFile, TrackType};
+    use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+
+    let mut mkv = MatroskaFile::open(BufReader::new(File::open(input)?))
+        .map_err(|e| demux_error(input, e))?;
+    let has_video = mkv
+        .tracks()
+        .iter()
+        .any(|t| t.track_type() == TrackType::Video);
+    let Some(track) = mkv
+        .tracks()
+        .iter()
+        .find(|t| t.track_type() == TrackType::Audio && t.codec_id() == "A_OPUS")
+    else {
+        return Ok(None);
+    };
+    // The codec private data of a Matroska Opus track is its OpusHead
+    let (Some(head), true) = (track.codec_private().map(<[u8]>::to_vec), has_video) else {
+        return Ok(None);
+    };
+    let track_number = track.track_number().get();
+
+    let serial = stream_serial(input);
+    let mut writer = PacketWriter::new(BufWriter::new(File::create(output)?));
+    let mut tags = b"OpusTags".to_vec();
+    let vendor = crate::core::APP_NAME.as_bytes();
+    tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    tags.extend_from_slice(vendor);
+    tags.extend_from_slice(&0u32.to_le_bytes());
+    writer.write_packet(head, serial, PacketWriteEndInfo::EndPage, 0)?;
+    writer.write_packet(tags, serial, PacketWriteEndInfo::EndPage, 0)?;
+
+    // One packet is held back so the last one can end the stream
+    let mut granule: u64 = 0;
+    let mut written = 0;
+    let mut pending: Option<Vec<u8>> = None;
+    let mut page_start = 0;
+    let mut frame = Frame::default();
+    while mkv
+        .next_frame(&mut frame)
+        .map_err(|e| demux_error(input, e))?
+    {
+        if frame.track != track_number {
+            continue;
+        }
+        if let Some(packet) = pending.take() {
+            // Pages of about a second keep seeking cheap
+            let end = if granule - page_start >= 48_000 {
+                page_start = granule;
+                PacketWriteEndInfo::EndPage
+            } else {
+                PacketWriteEndInfo::NormalPacket
+            };
+            writer.write_packet(packet, serial, end, granule)?;
+            written += 1;
+        }
+        granule += opus_packet_samples(&frame.data);
+        pending = Some(std::mem::take(&mut frame.data));
+    }
+    if let Some(packet) = pending {
+        writer.write_packet(packet, serial, PacketWriteEndInfo::EndStream, granule)?;
+        written += 1;
+    }
+    Ok(Some(written))
+}
+
+/// Ogg stream serial, stable for the same input.
+fn stream_serial(input: &Path) -> u32 {
+    let digest = md5::compute(input.to_string_lossy().as_bytes());
+    u32::from_le_bytes([digest[0], digest[1], digest[2], digest[3]])
+}
+
+/// Replace a downloaded MP4 or WebM by its audio stream, without
+/// re-encoding, and point the audio at the new file.
+///
+/// `local` is only changed once the new file is in place. Returns `false`
+/// and leaves everything as is when the file is not a video container with
+/// an AAC or Opus track, or is referenced in place. Blocking; run it on a
+/// blocking thread.
+pub fn demux(local: &mut LocalAudio, app_dir: &Path) -> AppResult<bool> {
+    if local.is_external() {
+        return Ok(false);
+    }
+    let input = app_dir.join(&local.path);
+    let probe = crate::probe::probe(&input).ok();
+    let ext = match probe.as_ref().map(|i| i.container.as_str()) {
+        Some("mp4") => "m4a",
+        // Lofty does not read WebM, so fall back to the extension
+        _ if input.extension().is_some_and(|e| e == "webm") => "opus",
+        _ => return Ok(false),
+    };
+    let output = demuxed_path(&input, ext);
+    // A file that is already named like its output has nothing to drop
+    if output == input {
+        return Ok(false);
+    }
+
+    let result = if ext == "m4a" {
+        mp4_to_m4a(&input, &output)
+    } else {
+        webm_to_opus(&input, &output)
+    };
+    // The original is only deleted once the output is known to play
+    let info = match result.and_then(|written| match written {
+        None => Ok(None),
+        Some(0) => Err(demux_error(&input, "no audio samples")),
+        Some(_) => match crate::probe::probe(&output) {
+            Ok(info) if info.duration.is_some_and(|d| d > 0.0) => Ok(Some(info)),
+            Ok(_) => Err(demux_error(&output, "no duration")),
+            Err(e) => Err(demux_error(&output, e)),
+        },
+    }) {
+        Ok(Some(info)) => info,
+        Ok(None) => {
+            let _ = std::fs::remove_file(&output);
+            return Ok(false);
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&output);
+            return Err(e);
+        }
+    };
+
+    let before = std::fs::metadata(&input).map(|m| m.len()).unwrap_or(0);
+    let after = std::fs::metadata(&output).map(|m| m.len()).unwrap_or(0);
+    std::fs::remove_file(&input)?;
+    info!(
+        "Demuxed {} into {} ({} -> {} bytes)",
+        input.display(),
+        output.display(),
+        before,
+        after
+    );
+
+    local.path = demuxed_path(Path::new(&local.path), ext)
+        .to_string_lossy()
+        .replace("\\", "/");
+    local.audio.format = demuxed_format(&local.path).or(local.audio.format.take());
+    local.info = Some(info);
+    Ok(true)
+}
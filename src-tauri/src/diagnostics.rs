@@ -0,0 +1,201 @@
+use crate::{
+    api,
+    core::Config,
+    error::{AppError, AppResult},
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+use zip::write::FileOptions;
+
+const REDACTED: &str = "[redacted]";
+
+/// Keys whose values are always removed, matched case-insensitively as substrings.
+const SECRET_KEYS: &[&str] = &["token", "secret", "password", "authorization", "cookie"];
+
+/// Assets referenced by the config but absent on disk, and files on disk
+/// that no playlist references.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub missing: Vec<String>,
+    pub orphaned: Vec<String>,
+    /// Files referenced in place that are gone, counted only since their
+    /// paths are outside the app directory
+    #[serde(default)]
+    pub missing_external: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageStats {
+    pub total: u64,
+    pub cache: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemInfo {
+    pub version: String,
+    pub git_hash: String,
+    pub os: String,
+    pub os_version: String,
+    pub arch: String,
+}
+
+/// Content of `diagnostics.json` inside the bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticsReport {
+    pub generated_at: String,
+    pub system: SystemInfo,
+    pub storage: StorageStats,
+    pub integrity: IntegrityReport,
+}
+
+pub fn system_info(version: &str, git_hash: &str) -> SystemInfo {
+    SystemInfo {
+        version: version.to_string(),
+        git_hash: git_hash.to_string(),
+        os: std::env::consts::OS.to_string(),
+        os_version: tauri_plugin_os::version().to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+    }
+}
+
+pub async fn integrity_report(
+    app_handle: &tauri::AppHandle,
+    config: &Config,
+) -> AppResult<IntegrityReport> {
+    let app_dir = api::app_dir(app_handle).await?;
+
+    let mut missing = Vec::new();
+    for path in api::get_used_paths(config) {
+        if !tokio::fs::try_exists(app_dir.join(&path))
+            .await
+            .unwrap_or(false)
+        {
+            missing.push(path);
+        }
+    }
+    missing.sort();
+
+    let external: HashSet<&str> = config
+        .playlists
+        .iter()
+        .flat_map(|p| &p.audios)
+        .filter_map(|a| a.external_path.as_deref())
+        .collect();
+    let mut missing_external = 0;
+    for path in external {
+        if !tokio::fs::try_exists(path).await.unwrap_or(false) {
+            missing_external += 1;
+        }
+    }
+
+    let mut orphaned: Vec<String> = api::get_cache_files(app_handle, config)
+        .await?
+        .iter()
+        .filter_map(|p| p.strip_prefix(&app_dir).ok())
+        .map(|p| p.to_string_lossy().replace("\\", "/"))
+        .collect();
+    orphaned.sort();
+
+    Ok(IntegrityReport {
+        missing,
+        orphaned,
+        missing_external,
+    })
+}
+
+/// Keep only the scheme and host of a URL.
+fn redact_url(url: &str) -> Option<String> {
+    let parsed = reqwest::Url::parse(url).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return None;
+    }
+    Some(format!(
+        "{}://{}/{}",
+        parsed.scheme(),
+        parsed.host_str().unwrap_or_default(),
+        REDACTED
+    ))
+}
+
+/// Replace every URL within `text`, such as one in a log message.
+fn redact_text(text: &str) -> String {
+    static URL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"https?://[^\s"'<>\\]+"#).unwrap());
+    URL.replace_all(text, |caps: &regex::Captures| {
+        redact_url(&caps[0]).unwrap_or_else(|| REDACTED.to_string())
+    })
+    .into_owned()
+}
+
+/// Scrub secrets and URLs from an arbitrary JSON document, in place.
+pub fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                let key = key.to_ascii_lowercase();
+                if SECRET_KEYS.iter().any(|k| key.contains(k)) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        Value::String(s) => *s = redact_text(s),
+        _ => {}
+    }
+}
+
+/// Write the diagnostics bundle. Blocking; run it on a blocking thread.
+pub fn write_bundle(
+    zip_path: &Path,
+    report: &DiagnosticsReport,
+    config: Option<Value>,
+    log_files: &[PathBuf],
+) -> AppResult<()> {
+    let file = File::create(zip_path)?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = FileOptions::<()>::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .unix_permissions(0o644);
+
+    zip.start_file("diagnostics.json", options)?;
+    zip.write_all(&serde_json::to_vec_pretty(report)?)?;
+
+    if let Some(mut config) = config {
+        redact(&mut config);
+        zip.start_file("config.redacted.json", options)?;
+        zip.write_all(&serde_json::to_vec_pretty(&config)?)?;
+    }
+
+    for log_file in log_files {
+        let name = log_file
+            .file_name()
+            .ok_or_else(|| AppError::PathError(log_file.display().to_string()))?
+            .to_string_lossy();
+        zip.start_file(format!("logs/{}", name), options)?;
+        // Log messages and span fields carry URLs and repositories too
+        for line in BufReader::new(File::open(log_file)?).split(b'\n') {
+            let line = String::from_utf8_lossy(&line?).into_owned();
+            let redacted = match serde_json::from_str::<Value>(&line) {
+                Ok(mut value) => {
+                    redact(&mut value);
+                    serde_json::to_string(&value)?
+                }
+                Err(_) => redact_text(&line),
+            };
+            zip.write_all(redacted.as_bytes())?;
+            zip.write_all(b"\n")?;
+        }
+    }
+
+    zip.finish()?;
+    Ok(())
+}
@@ -0,0 +1,363 @@
+use crate::error::{AppError, AppResult};
+use musicfree::{Audio, Platform};
+use regex::Regex;
+use reqwest::Client;
+use serde_json::{Value, json};
+use std::{collections::HashSet, sync::LazyLock, time::Duration};
+use tracing::warn;
+
+/// Platforms compiled into the `musicfree` dependency.
+pub const ENABLED_PLATFORMS: &[Platform] = &[Platform::Bilibili, Platform::Youtube];
+
+/// Home page of Bilibili, also the `Referer` its APIs expect.
+pub const BILIBILI_HOME: &str = "https://www.bilibili.com";
+const BILIBILI_VIDEO_URL: &str = "https://www.bilibili.com/video/";
+const YOUTUBE_VIDEO_URL: &str = "https://www.youtube.com/watch?v=";
+
+/// Client version sent to the YouTube web API when the home page does not
+/// tell the current one, see [`youtube_client_version`].
+const YOUTUBE_CLIENT_VERSION: &str = "2.20240726.00.00";
+
+/// `params` of the YouTube web search restricting results to videos.
+const YOUTUBE_VIDEOS_ONLY: &str = "EgIQAQ==";
+
+/// Both sites serve reduced or blocked results to unknown clients.
+pub const BROWSER_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0 Safari/537.36";
+
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Search hits point at the video page rather than a stream.
+pub fn is_page_url(url: &str) -> bool {
+    url.starts_with(BILIBILI_VIDEO_URL) || url.starts_with(YOUTUBE_VIDEO_URL)
+}
+
+/// Turn a search hit into the audio its extractor returns for the page.
+/// Hits are resolved only when downloaded, since every resolve is a full
+/// extraction and the stream URLs it returns expire.
+pub async fn resolve(audio: &Audio) -> AppResult<Audio> {
+    let url = &audio.download_url;
+    let (playlist, default) = musicfree::extract(url)
+        .await
+        .map_err(|e| AppError::extractor(format!("Failed to extract {}", url), e))?;
+    let index = default.unwrap_or(0);
+    let mut resolved = playlist
+        .audios
+        .into_iter()
+        .nth(index)
+        .ok_or_else(|| AppError::NotFound(format!("No audio at {}", url)))?;
+    if resolved.cover.is_none() {
+        resolved.cover = audio.cover.clone();
+    }
+    Ok(resolved)
+}
+
+/// `3:45` or `1:02:03` in seconds.
+fn parse_duration(text: &str) -> Option<f64> {
+    text.trim().split(':').try_fold(0.0, |acc, part| {
+        part.parse::<f64>().ok().map(|n| acc * 60.0 + n)
+    })
+}
+
+static HTML_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+fn strip_html(text: &str) -> String {
+    HTML_TAG
+        .replace_all(text, "")
+        .replace("&amp;", "&")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+}
+
+/// Every value under `key`, in document order.
+fn collect<'a>(value: &'a Value, key: &str, out: &mut Vec<&'a Value>) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                if k == key {
+                    out.push(v);
+                } else {
+                    collect(v, key, out);
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| collect(v, key, out)),
+        _ => {}
+    }
+}
+
+fn client() -> AppResult<Client> {
+    Client::builder()
+        .cookie_store(true)
+        .timeout(SEARCH_TIMEOUT)
+        .user_agent(BROWSER_USER_AGENT)
+        .build()
+        .map_err(|e| AppError::network("Failed to build HTTP client", e))
+}
+
+/// Order in which the characters of the two WBI keys make the mixin key.
+const WBI_MIXIN: [usize; 64] = [
+    46, 47, 18, 2, 53, 8, 23, 32, 15, 50, 10, 31, 58, 3, 45, 35, 27, 43, 5, 49, 33, 9, 42, 19, 29,
+    28, 14, 39, 12, 38, 41, 13, 37, 48, 7, 16, 24, 55, 40, 61, 26, 17, 0, 1, 60, 51, 30, 4, 22, 25,
+    54, 21, 56, 59, 6, 63, 57, 62, 11, 36, 20, 34, 44, 52,
+];
+
+/// Key of a WBI image URL such as `https://i0.hdslb.com/bfs/wbi/<key>.png`.
+fn wbi_key(url: Option<&str>) -> Option<&str> {
+    let name = url?.rsplit('/').next()?;
+    name.split('.').next()
+}
+
+/// Mixin key of the current WBI keys, announced by the nav endpoint.
+async fn wbi_mixin_key(client: &Client) -> reqwest::Result<String> {
+    let nav = client
+        .get("https://api.bilibili.com/x/web-interface/nav")
+        .header("Referer", BILIBILI_HOME)
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    let img = &nav["data"]["wbi_img"];
+    let keys = [img["img_url"].as_str(), img["sub_url"].as_str()]
+        .map(wbi_key)
+        .map(Option::unwrap_or_default)
+        .concat();
+    let keys = keys.as_bytes();
+    Ok(WBI_MIXIN
+        .iter()
+        .filter_map(|&i| keys.get(i).map(|&b| b as char))
+        .take(32)
+        .collect())
+}
+
+/// `params` with the `wts` and `w_rid` WBI endpoints check.
+fn wbi_sign(params: &[(&str, String)], mixin_key: &str) -> Vec<(String, String)> {
+    let wts = chrono::Utc::now().timestamp().to_string();
+    let mut signed: Vec<(String, String)> = params
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.replace(['!', '\'', '(', ')', '*'], "")))
+        .chain([("wts".to_string(), wts)])
+        .collect();
+    signed.sort();
+    let query = signed
+        .iter()
+        .map(|(k, v)| format!("{}={}", encode(k), encode(v)))
+        .collect::<Vec<_>>()
+        .join("&");
+    let w_rid = format!("{:x}", md5::compute(format!("{query}{mixin_key}")));
+    signed.push(("w_rid".to_string(), w_rid));
+    signed
+}
+
+/// Percent-encoding of `encodeURIComponent`, which the signature is taken over.
+fn encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+async fn search_bilibili(client: &Client, query: &str, page: u32) -> AppResult<Vec<Audio>> {
+    let request = async {
+        // The search API answers 412 without the cookies set by the home page
+        client.get(BILIBILI_HOME).send().await?;
+        let mixin_key = wbi_mixin_key(client).await?;
+        let params = [
+            ("search_type", "video".to_string()),
+            ("keyword", query.to_string()),
+            ("page", page.to_string()),
+        ];
+        client
+            .get("https://api.bilibili.com/x/web-interface/wbi/search/type")
+            .query(&wbi_sign(&params, &mixin_key))
+            .header("Referer", BILIBILI_HOME)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await
+    };
+    let response = request
+        .await
+        .map_err(|e| AppError::network("Bilibili search failed", e))?;
+
+    let results = response["data"]["result"].as_array();
+    Ok(results
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            let bvid = item["bvid"].as_str()?;
+            let cover = item["pic"].as_str().map(|p| match p.strip_prefix("//") {
+                Some(rest) => format!("https://{rest}"),
+                None => p.to_string(),
+            });
+            Some(Audio {
+                id: bvid.to_string(),
+                title: strip_html(item["title"].as_str()?),
+                download_url: format!("{BILIBILI_VIDEO_URL}{bvid}"),
+                cover,
+                platform: Platform::Bilibili,
+                duration: item["duration"]
+                    .as_str()
+                    .and_then(parse_duration)
+                    .map(|d| d.round() as _),
+                format: None,
+            })
+        })
+        .collect())
+}
+
+/// Client version the YouTube web app currently sends, read from the
+/// `INNERTUBE_CLIENT_VERSION` of its home page.
+async fn youtube_client_version(client: &Client) -> String {
+    static VERSION: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r#""INNERTUBE_CLIENT_VERSION":"([^"]+)""#).unwrap());
+    let page = async {
+        client
+            .get("https://www.youtube.com")
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await
+    };
+    match page.await {
+        Ok(html) => match VERSION.captures(&html) {
+            Some(caps) => return caps[1].to_string(),
+            None => warn!("No client version on the YouTube home page"),
+        },
+        Err(e) => warn!("Failed to read the YouTube client version: {}", e),
+    }
+    YOUTUBE_CLIENT_VERSION.to_string()
+}
+
+async fn search_youtube(client: &Client, query: &str, page: u32) -> AppResult<Vec<Audio>> {
+    let version = youtube_client_version(client).await;
+    let context = json!({
+        "client": { "clientName": "WEB", "clientVersion": version, "hl": "en" }
+    });
+    let mut body = json!({ "context": context, "query": query, "params": YOUTUBE_VIDEOS_ONLY });
+    // Later pages are only reachable through the continuation of the previous one
+    let mut response = Value::Null;
+    let page = page.max(1);
+    for current in 1..=page {
+        let request = async {
+            client
+                .post("https://www.youtube.com/youtubei/v1/search?prettyPrint=false")
+                .json(&body)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Value>()
+                .await
+        };
+        response = request
+            .await
+            .map_err(|e| AppError::network("YouTube search failed", e))?;
+        if current == page {
+            break;
+        }
+        let mut tokens = Vec::new();
+        collect(&response, "continuationCommand", &mut tokens);
+        let Some(token) = tokens.first().and_then(|c| c["token"].as_str()) else {
+            return Ok(Vec::new());
+        };
+        body = json!({ "context": context, "continuation": token });
+    }
+
+    let mut renderers = Vec::new();
+    collect(&response, "videoRenderer", &mut renderers);
+    Ok(renderers
+        .into_iter()
+        .filter_map(|video| {
+            let id = video["videoId"].as_str()?;
+            let title = video["title"]["runs"][0]["text"].as_str()?;
+            let cover = video["thumbnail"]["thumbnails"]
+                .as_array()
+                .and_then(|t| t.last())
+                .and_then(|t| t["url"].as_str())
+                .map(str::to_string);
+            Some(Audio {
+                id: id.to_string(),
+                title: title.to_string(),
+                download_url: format!("{YOUTUBE_VIDEO_URL}{id}"),
+                cover,
+                platform: Platform::Youtube,
+                duration: video["lengthText"]["simpleText"]
+                    .as_str()
+                    .and_then(parse_duration)
+                    .map(|d| d.round() as _),
+                format: None,
+            })
+        })
+        .collect())
+}
+
+/// Titles compared for duplicates: lowercase, without bracketed tags such
+/// as `【MV】` or `(Official Video)`, and without punctuation or spaces.
+pub fn normalize_title(title: &str) -> String {
+    static BRACKETS: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"【[^】]*】|\[[^\]]*\]|\([^)]*\)|（[^）]*）|「|」|『|』").unwrap()
+    });
+    BRACKETS
+        .replace_all(title, "")
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Search `platforms` for `query` and merge the results, taking them from
+/// each platform in turn and dropping titles seen before. A platform that
+/// fails is logged and left out.
+pub async fn search(platforms: &[Platform], query: &str, page: u32) -> AppResult<Vec<Audio>> {
+    let client = client()?;
+    let mut searches = tokio::task::JoinSet::new();
+    for (i, &platform) in platforms.iter().enumerate() {
+        let client = client.clone();
+        let query = query.to_string();
+        searches.spawn(async move {
+            let result = match platform {
+                Platform::Bilibili => search_bilibili(&client, &query, page).await,
+                Platform::Youtube => search_youtube(&client, &query, page).await,
+                _ => Ok(Vec::new()),
+            };
+            (i, platform, result)
+        });
+    }
+    let mut results = Vec::new();
+    while let Some(joined) = searches.join_next().await {
+        match joined.map_err(|e| AppError::Unknown(e.to_string()))? {
+            (i, _, Ok(audios)) => results.push((i, audios)),
+            (_, platform, Err(e)) => warn!("Skipping {:?} search: {}", platform, e),
+        }
+    }
+    // Keep the order platforms were asked in, whichever answered first
+    results.sort_by_key(|(i, _)| *i);
+    let mut lists: Vec<_> = results.into_iter().map(|(_, a)| a.into_iter()).collect();
+
+    let mut seen = HashSet::new();
+    let mut merged = Vec::new();
+    loop {
+        let mut any = false;
+        for list in &mut lists {
+            let Some(audio) = list.next() else {
+                continue;
+            };
+            any = true;
+            let key = normalize_title(&audio.title);
+            if key.is_empty() || seen.insert(key) {
+                merged.push(audio);
+            }
+        }
+        if !any {
+            break;
+        }
+    }
+    Ok(merged)
+}
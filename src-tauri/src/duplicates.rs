@@ -0,0 +1,334 @@
+use crate::{
+    api,
+    core::{Config, LocalAudio, LocalPlaylist},
+};
+use musicfree::Platform;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    path::Path,
+};
+use tracing::warn;
+
+/// Why audios ended up in the same cluster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateReason {
+    /// Same platform and id, downloaded more than once
+    SameId,
+    /// Byte-identical files
+    SameContent,
+    /// Close titles and durations, such as two uploads of a song
+    SimilarTitle,
+    /// Matching acoustic fingerprints, see [`crate::fingerprint`]
+    SameRecording,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateEntry {
+    pub audio: LocalAudio,
+    /// Playlists referencing the file, in config order
+    pub playlist_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateCluster {
+    pub reasons: Vec<DuplicateReason>,
+    /// The suggested audio to keep first
+    pub entries: Vec<DuplicateEntry>,
+}
+
+/// Title similarity from which two audios count as the same song.
+const TITLE_SIMILARITY: f64 = 0.85;
+
+/// Largest duration difference of the same song in two uploads.
+const DURATION_TOLERANCE_SECS: f64 = 3.0;
+
+fn duration(audio: &LocalAudio) -> Option<f64> {
+    audio
+        .info
+        .as_ref()
+        .and_then(|i| i.duration)
+        .or(audio.audio.duration.map(|d| d as f64))
+}
+
+/// Sørensen–Dice coefficient of the character bigrams of two titles.
+fn similarity(a: &[char], b: &[char]) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    if a.len() < 2 || b.len() < 2 {
+        return 0.0;
+    }
+    let mut bigrams: HashMap<(char, char), usize> = HashMap::new();
+    for w in a.windows(2) {
+        *bigrams.entry((w[0], w[1])).or_default() += 1;
+    }
+    let mut shared = 0;
+    for w in b.windows(2) {
+        if let Some(n) = bigrams.get_mut(&(w[0], w[1]))
+            && *n > 0
+        {
+            *n -= 1;
+            shared += 1;
+        }
+    }
+    2.0 * shared as f64 / (a.len() + b.len() - 2) as f64
+}
+
+/// Disjoint sets over `0..len`, joining everything linked directly or
+/// through other members.
+pub(crate) struct UnionFind(Vec<usize>);
+
+impl UnionFind {
+    pub(crate) fn new(len: usize) -> Self {
+        Self((0..len).collect())
+    }
+
+    pub(crate) fn find(&mut self, i: usize) -> usize {
+        let parent = self.0[i];
+        if parent == i {
+            return i;
+        }
+        let root = self.find(parent);
+        self.0[i] = root;
+        root
+    }
+
+    pub(crate) fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.0[b] = a;
+        }
+    }
+}
+
+/// The file an audio plays from, as used in the config.
+fn normalized(path: &str) -> String {
+    path.replace('\\', "/")
+}
+
+/// Group the files of `config` that hold the same song.
+///
+/// Each file is considered once however many playlists reference it. Files
+/// are only hashed when another file has the same size.
+/// Blocking; run it on a blocking thread.
+pub fn find(config: &Config, app_dir: &Path) -> Vec<DuplicateCluster> {
+    let mut entries: Vec<DuplicateEntry> = Vec::new();
+    let mut by_path: HashMap<String, usize> = HashMap::new();
+    for playlist in &config.playlists {
+        for audio in &playlist.audios {
+            let i = *by_path.entry(normalized(&audio.path)).or_insert_with(|| {
+                entries.push(DuplicateEntry {
+                    audio: audio.clone(),
+                    playlist_ids: Vec::new(),
+                });
+                entries.len() - 1
+            });
+            if let Some(id) = &playlist.id
+                && !entries[i].playlist_ids.contains(id)
+            {
+                entries[i].playlist_ids.push(id.clone());
+            }
+        }
+    }
+
+    let mut links = UnionFind::new(entries.len());
+    let mut reasons: Vec<(usize, DuplicateReason)> = Vec::new();
+    let mut link = |a: usize, b: usize, reason| {
+        links.union(a, b);
+        reasons.push((a, reason));
+    };
+
+    // Same platform and id. Local files all share the `File` platform and
+    // derive their id from the path, so they never collide here
+    let mut by_id: HashMap<(String, &str), usize> = HashMap::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let key = (
+            format!("{:?}", entry.audio.audio.platform),
+            entry.audio.audio.id.as_str(),
+        );
+        match by_id.get(&key) {
+            Some(&first) => link(first, i, DuplicateReason::SameId),
+            None => {
+                by_id.insert(key, i);
+            }
+        }
+    }
+
+    // Same content, hashing only the files whose size is shared
+    let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, entry) in entries.iter().enumerate() {
+        match std::fs::metadata(entry.audio.file(app_dir)) {
+            Ok(meta) => by_size.entry(meta.len()).or_default().push(i),
+            Err(e) => warn!("Skipping missing file {}: {}", entry.audio.path, e),
+        }
+    }
+    let mut by_hash: HashMap<String, usize> = HashMap::new();
+    for group in by_size.values().filter(|g| g.len() > 1) {
+        for &i in group {
+            let hash = match api::hash_file(entries[i].audio.file(app_dir)) {
+                Ok(hash) => hash,
+                Err(e) => {
+                    warn!("Failed to hash {}: {}", entries[i].audio.path, e);
+                    continue;
+                }
+            };
+            match by_hash.get(&hash) {
+                Some(&first) => link(first, i, DuplicateReason::SameContent),
+                None => {
+                    by_hash.insert(hash, i);
+                }
+            }
+        }
+    }
+
+    // Similar titles among audios of about the same duration, compared
+    // within a sliding window over the audios sorted by duration
+    let mut timed: Vec<(f64, usize, Vec<char>)> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, e)| {
+            let title: Vec<char> = crate::discover::normalize_title(&e.audio.audio.title)
+                .chars()
+                .collect();
+            Some((duration(&e.audio)?, i, title)).filter(|(_, _, t)| !t.is_empty())
+        })
+        .collect();
+    timed.sort_by(|a, b| a.0.total_cmp(&b.0));
+    for (n, (secs, i, title)) in timed.iter().enumerate() {
+        for (other_secs, j, other_title) in &timed[n + 1..] {
+            if other_secs - secs > DURATION_TOLERANCE_SECS {
+                break;
+            }
+            if similarity(title, other_title) >= TITLE_SIMILARITY {
+                link(*i, *j, DuplicateReason::SimilarTitle);
+            }
+        }
+    }
+
+    // The same recording under any title
+    let audios: Vec<&LocalAudio> = entries.iter().map(|e| &e.audio).collect();
+    for (i, j, _) in crate::fingerprint::matching_pairs(&audios) {
+        link(i, j, DuplicateReason::SameRecording);
+    }
+
+    let mut clusters: HashMap<usize, (Vec<usize>, BTreeSet<DuplicateReason>)> = HashMap::new();
+    for i in 0..entries.len() {
+        let root = links.find(i);
+        clusters.entry(root).or_default().0.push(i);
+    }
+    for (a, reason) in reasons {
+        let root = links.find(a);
+        clusters.entry(root).or_default().1.insert(reason);
+    }
+
+    let mut result: Vec<DuplicateCluster> = clusters
+        .into_values()
+        .filter(|(members, _)| members.len() > 1)
+        .map(|(mut members, reasons)| {
+            members.sort_by_key(|&i| std::cmp::Reverse(keep_rank(&entries[i])));
+            DuplicateCluster {
+                reasons: reasons.into_iter().collect(),
+                entries: members.into_iter().map(|i| entries[i].clone()).collect(),
+            }
+        })
+        .collect();
+    result.sort_by(|a, b| {
+        a.entries[0]
+            .audio
+            .audio
+            .title
+            .cmp(&b.entries[0].audio.audio.title)
+    });
+    result
+}
+
+/// Rank of the copy worth keeping: the one in the most playlists, then the
+/// higher bitrate, then a platform download over a local file.
+fn keep_rank(entry: &DuplicateEntry) -> (usize, u32, bool) {
+    (
+        entry.playlist_ids.len(),
+        entry
+            .audio
+            .info
+            .as_ref()
+            .and_then(|i| i.bitrate)
+            .unwrap_or(0),
+        !matches!(entry.audio.audio.platform, Platform::File),
+    )
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeResult {
+    pub playlists: Vec<LocalPlaylist>,
+    /// App files of the duplicates no longer referenced, to delete once the
+    /// playlists are saved
+    pub removed: Vec<String>,
+}
+
+/// Point every reference to the files of `duplicates` at `keep`. A playlist
+/// holding both keeps `keep` at the first position either appeared.
+/// Lyrics and covers of the duplicates fill in what `keep` lacks.
+pub fn merge(config: &Config, keep: &LocalAudio, duplicates: &[LocalAudio]) -> MergeResult {
+    let keep_path = normalized(&keep.path);
+    let replaced: HashSet<String> = duplicates
+        .iter()
+        .map(|d| normalized(&d.path))
+        .filter(|p| *p != keep_path)
+        .collect();
+
+    let mut kept = keep.clone();
+    for duplicate in duplicates {
+        kept.lyrics_path = kept.lyrics_path.or_else(|| duplicate.lyrics_path.clone());
+        kept.cover_path = kept.cover_path.or_else(|| duplicate.cover_path.clone());
+        if kept.audio.cover.is_none() {
+            kept.audio.cover = duplicate.audio.cover.clone();
+        }
+    }
+
+    let playlists: Vec<LocalPlaylist> = config
+        .playlists
+        .iter()
+        .map(|playlist| {
+            let mut seen = false;
+            let audios = playlist
+                .audios
+                .iter()
+                .filter_map(|audio| {
+                    let path = normalized(&audio.path);
+                    if path != keep_path && !replaced.contains(&path) {
+                        return Some(audio.clone());
+                    }
+                    if std::mem::replace(&mut seen, true) {
+                        return None;
+                    }
+                    Some(kept.clone())
+                })
+                .collect();
+            LocalPlaylist {
+                audios,
+                ..playlist.clone()
+            }
+        })
+        .collect();
+
+    let used = api::get_used_paths(&Config {
+        playlists: playlists.clone(),
+    });
+    let mut removed: Vec<String> = duplicates
+        .iter()
+        .flat_map(|d| {
+            // Files referenced in place belong to the user, only app files go
+            let audio = (!d.is_external()).then_some(&d.path);
+            [audio, d.cover_path.as_ref(), d.lyrics_path.as_ref()]
+        })
+        .flatten()
+        .map(|p| normalized(p))
+        .filter(|p| !used.contains(p))
+        .collect();
+    removed.sort();
+    removed.dedup();
+
+    MergeResult { playlists, removed }
+}
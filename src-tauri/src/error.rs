@@ -1,6 +1,8 @@
 use serde::Serialize;
 use thiserror::Error;
 
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
 #[derive(Debug, Error)]
 pub enum AppError {
     #[error("IO error: {0}")]
@@ -21,6 +23,58 @@ pub enum AppError {
     #[error("Sync error: {0}")]
     Sync(#[from] SyncError),
 
+    #[error("Network error: {message}")]
+    Network {
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Permission denied: {0}")]
+    Permission(String),
+
+    #[error("Extractor error: {message}")]
+    Extractor {
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    #[error("Transcode error: {message}")]
+    Transcode {
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    #[error("Archive error: {0}")]
+    Archive(#[from] zip::result::ZipError),
+
+    #[error("Invalid backup: {0}")]
+    InvalidBackup(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
+    #[error("Invalid input: {0}")]
+    Invalid(String),
+
+    #[error("Parse error: {message}")]
+    Parse {
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
@@ -30,6 +84,9 @@ pub enum SyncError {
     #[error("Invalid repository URL: {0}")]
     InvalidRepoUrl(String),
 
+    #[error("Invalid commit SHA: {0}")]
+    InvalidSha(String),
+
     #[error("HTTP request failed: {0}")]
     HttpRequest(#[from] reqwest::Error),
 
@@ -42,8 +99,152 @@ pub enum SyncError {
     #[error("File not found in repository: {0}")]
     FileNotFound(String),
 
+    #[error("Invalid sync data: {0}")]
+    InvalidData(#[from] serde_json::Error),
+
     #[error("GitHub API error: {0}")]
     GitHubApi(String),
+
+    #[error("GitHub API error (status {status}): {message}")]
+    Status { status: u16, message: String },
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Hash mismatch for {0}")]
+    HashMismatch(String),
+
+    #[error("Sync credentials not configured")]
+    MissingCredentials,
+
+    #[error("Credential error: {0}")]
+    Credentials(String),
+}
+
+/// Error category exposed to the frontend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Io,
+    Serialization,
+    Path,
+    Network,
+    Timeout,
+    NotFound,
+    Permission,
+    Extractor,
+    Transcode,
+    Archive,
+    Conflict,
+    Cancelled,
+    /// The request itself is wrong, retrying it cannot help
+    Invalid,
+    /// A file or response is not in the expected format
+    Parse,
+    Unknown,
+}
+
+impl AppError {
+    pub fn network<E: Into<BoxError>>(message: impl Into<String>, source: E) -> Self {
+        AppError::Network {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn extractor<E: Into<BoxError>>(message: impl Into<String>, source: E) -> Self {
+        AppError::Extractor {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn transcode<E: Into<BoxError>>(message: impl Into<String>, source: E) -> Self {
+        AppError::Transcode {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn parse<E: Into<BoxError>>(message: impl Into<String>, source: E) -> Self {
+        AppError::Parse {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            AppError::Io(e) => io_kind(e),
+            AppError::Serde(_) => ErrorKind::Serialization,
+            AppError::MusicFree(_) => ErrorKind::Extractor,
+            AppError::PathError(_) | AppError::InvalidUtf8 => ErrorKind::Path,
+            AppError::Sync(e) => e.kind(),
+            AppError::Network { .. } => ErrorKind::Network,
+            AppError::Timeout(_) => ErrorKind::Timeout,
+            AppError::NotFound(_) => ErrorKind::NotFound,
+            AppError::Permission(_) => ErrorKind::Permission,
+            AppError::Extractor { .. } => ErrorKind::Extractor,
+            AppError::Transcode { .. } => ErrorKind::Transcode,
+            AppError::Archive(_) | AppError::InvalidBackup(_) => ErrorKind::Archive,
+            AppError::Conflict(_) => ErrorKind::Conflict,
+            AppError::Cancelled(_) => ErrorKind::Cancelled,
+            AppError::Invalid(_) => ErrorKind::Invalid,
+            AppError::Parse { .. } => ErrorKind::Parse,
+            AppError::Unknown(_) => ErrorKind::Unknown,
+        }
+    }
+
+    /// Whether the same call may succeed later without user action.
+    pub fn retryable(&self) -> bool {
+        match self {
+            AppError::Sync(SyncError::Status { status, .. }) => *status == 429 || *status >= 500,
+            _ => matches!(self.kind(), ErrorKind::Network | ErrorKind::Timeout),
+        }
+    }
+}
+
+impl SyncError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            SyncError::InvalidRepoUrl(_) | SyncError::InvalidSha(_) => ErrorKind::Path,
+            SyncError::HttpRequest(e) if e.is_timeout() => ErrorKind::Timeout,
+            SyncError::HttpRequest(_) => ErrorKind::Network,
+            SyncError::Base64Decode(_) | SyncError::Utf8Decode(_) | SyncError::InvalidData(_) => {
+                ErrorKind::Serialization
+            }
+            SyncError::FileNotFound(_) => ErrorKind::NotFound,
+            SyncError::GitHubApi(_) => ErrorKind::Unknown,
+            SyncError::Status { status, .. } => match status {
+                401 | 403 => ErrorKind::Permission,
+                404 => ErrorKind::NotFound,
+                409 | 422 => ErrorKind::Conflict,
+                _ => ErrorKind::Network,
+            },
+            SyncError::Io(e) => io_kind(e),
+            SyncError::HashMismatch(_) => ErrorKind::Conflict,
+            SyncError::MissingCredentials | SyncError::Credentials(_) => ErrorKind::Permission,
+        }
+    }
+}
+
+fn io_kind(e: &std::io::Error) -> ErrorKind {
+    match e.kind() {
+        std::io::ErrorKind::NotFound => ErrorKind::NotFound,
+        std::io::ErrorKind::PermissionDenied => ErrorKind::Permission,
+        std::io::ErrorKind::TimedOut => ErrorKind::Timeout,
+        _ => ErrorKind::Io,
+    }
+}
+
+/// Shape of an error as seen by the frontend
+#[derive(Serialize)]
+struct ErrorPayload {
+    kind: ErrorKind,
+    message: String,
+    retryable: bool,
+    /// Messages of the underlying causes, outermost first
+    context: Vec<String>,
 }
 
 // Implement Serialize so we can return it to Tauri frontend
@@ -52,7 +253,20 @@ impl Serialize for AppError {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&self.to_string())
+        let mut context = Vec::new();
+        let mut source = std::error::Error::source(self);
+        while let Some(e) = source {
+            context.push(e.to_string());
+            source = e.source();
+        }
+
+        ErrorPayload {
+            kind: self.kind(),
+            message: self.to_string(),
+            retryable: self.retryable(),
+            context,
+        }
+        .serialize(serializer)
     }
 }
 
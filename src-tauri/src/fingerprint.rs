@@ -0,0 +1,415 @@
+use crate::{
+    core::{Config, LocalAudio},
+    decode::decode,
+    duplicates::UnionFind,
+    error::{AppError, AppResult},
+    progress::Task,
+};
+use base64::{Engine as _, engine::general_purpose};
+use musicfree::Platform;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+use tracing::{info, warn};
+
+/// Audio is downmixed and resampled to this rate before analysis.
+const SAMPLE_RATE: u32 = 11025;
+
+const FRAME_SIZE: usize = 4096;
+
+/// About 0.19s between sub-fingerprints.
+const HOP_SIZE: usize = 2048;
+
+/// Only the start of a file is fingerprinted, which is enough to tell
+/// recordings apart and keeps the config small.
+const MAX_SECONDS: usize = 120;
+
+/// Lowest and highest frequencies folded into the chroma.
+const MIN_FREQ: f32 = 28.0;
+const MAX_FREQ: f32 = 3520.0;
+
+/// Frames quieter than this are leading silence and skipped.
+const SILENCE_RMS: f32 = 1e-3;
+
+/// Energy of the 12 pitch classes in a frame.
+type Chroma = [f32; 12];
+
+/// Averages the input down to [`SAMPLE_RATE`]. Crude, but the chroma only
+/// looks at frequencies far below the new Nyquist.
+struct Resampler {
+    step: f64,
+    next: f64,
+    position: f64,
+    sum: f32,
+    count: u32,
+}
+
+impl Resampler {
+    fn new(rate: u32) -> Self {
+        let step = rate as f64 / SAMPLE_RATE as f64;
+        Self {
+            step,
+            next: step,
+            position: 0.0,
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn push(&mut self, sample: f32, out: &mut Vec<f32>) {
+        self.sum += sample;
+        self.count += 1;
+        self.position += 1.0;
+        if self.position >= self.next {
+            out.push(self.sum / self.count as f32);
+            (self.sum, self.count) = (0.0, 0);
+            self.next += self.step;
+        }
+    }
+}
+
+/// In-place radix-2 FFT; `re` and `im` have a power of two length.
+fn fft(re: &mut [f32], im: &mut [f32], twiddles: &[(f32, f32)]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let stride = n / len;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (c, s) = twiddles[k * stride];
+                let (a, b) = (start + k, start + k + len / 2);
+                let tr = re[b] * c - im[b] * s;
+                let ti = re[b] * s + im[b] * c;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+fn chromagram(samples: &[f32]) -> Vec<Chroma> {
+    let twiddles: Vec<(f32, f32)> = (0..FRAME_SIZE / 2)
+        .map(|k| {
+            let angle = -2.0 * std::f32::consts::PI * k as f32 / FRAME_SIZE as f32;
+            (angle.cos(), angle.sin())
+        })
+        .collect();
+    let window: Vec<f32> = (0..FRAME_SIZE)
+        .map(|i| {
+            let x = 2.0 * std::f32::consts::PI * i as f32 / (FRAME_SIZE - 1) as f32;
+            0.5 - 0.5 * x.cos()
+        })
+        .collect();
+    // Pitch class of every FFT bin in range
+    let classes: Vec<Option<usize>> = (0..FRAME_SIZE / 2)
+        .map(|bin| {
+            let freq = bin as f32 * SAMPLE_RATE as f32 / FRAME_SIZE as f32;
+            (MIN_FREQ..=MAX_FREQ).contains(&freq).then(|| {
+                let note = 12.0 * (freq / 440.0).log2() + 69.0;
+                (note.round() as i64).rem_euclid(12) as usize
+            })
+        })
+        .collect();
+
+    let mut re = vec![0.0; FRAME_SIZE];
+    let mut im = vec![0.0; FRAME_SIZE];
+    let mut frames = Vec::new();
+    let mut audible = false;
+    for start in (0..samples.len().saturating_sub(FRAME_SIZE)).step_by(HOP_SIZE) {
+        let frame = &samples[start..start + FRAME_SIZE];
+        if !audible {
+            let rms = (frame.iter().map(|s| s * s).sum::<f32>() / FRAME_SIZE as f32).sqrt();
+            if rms < SILENCE_RMS {
+                continue;
+            }
+            audible = true;
+        }
+        for (i, s) in frame.iter().enumerate() {
+            re[i] = s * window[i];
+            im[i] = 0.0;
+        }
+        fft(&mut re, &mut im, &twiddles);
+        let mut chroma = [0.0; 12];
+        for (bin, class) in classes.iter().enumerate() {
+            if let Some(class) = class {
+                chroma[*class] += re[bin] * re[bin] + im[bin] * im[bin];
+            }
+        }
+        let norm = chroma.iter().map(|c| c * c).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            chroma.iter_mut().for_each(|c| *c /= norm);
+        }
+        frames.push(chroma);
+    }
+    frames
+}
+
+/// One 32-bit sub-fingerprint per frame: 12 bits comparing each pitch
+/// class with the next, 12 comparing it with the previous frame and 8
+/// comparing it with the class a whole tone up.
+fn sub_fingerprints(chroma: &[Chroma]) -> Vec<u32> {
+    chroma
+        .iter()
+        .enumerate()
+        .map(|(t, frame)| {
+            let previous = if t > 0 { &chroma[t - 1] } else { frame };
+            let mut bits = 0u32;
+            for (c, value) in frame.iter().enumerate() {
+                bits = (bits << 1) | (*value > frame[(c + 1) % 12]) as u32;
+            }
+            for (value, before) in frame.iter().zip(previous) {
+                bits = (bits << 1) | (value > before) as u32;
+            }
+            for (value, up) in frame[..8].iter().zip(&frame[2..10]) {
+                bits = (bits << 1) | (value > up) as u32;
+            }
+            bits
+        })
+        .collect()
+}
+
+/// Compute the fingerprint of the first [`MAX_SECONDS`] of a file, as
+/// base64 of the little-endian sub-fingerprints.
+/// Blocking; run it on a blocking thread.
+pub fn compute(path: &Path) -> AppResult<String> {
+    let limit = SAMPLE_RATE as usize * MAX_SECONDS;
+    let mut samples = Vec::with_capacity(limit);
+    let mut resampler: Option<Resampler> = None;
+    let mut full = false;
+
+    let result = decode(path, |spec, data| {
+        let resampler = resampler.get_or_insert_with(|| Resampler::new(spec.sample_rate));
+        for frame in data.chunks_exact(spec.channels) {
+            let mono = frame.iter().sum::<f32>() / spec.channels as f32;
+            resampler.push(mono, &mut samples);
+        }
+        if samples.len() >= limit {
+            // Stop decoding, the rest of the file is not needed
+            full = true;
+            return Err(AppError::Cancelled("Fingerprint complete".to_string()));
+        }
+        Ok(())
+    });
+    match result {
+        Err(_) if full => samples.truncate(limit),
+        result => result?,
+    }
+
+    let fingerprint = sub_fingerprints(&chromagram(&samples));
+    if fingerprint.is_empty() {
+        return Err(AppError::Transcode {
+            message: format!("{} is too short or silent", path.display()),
+            source: None,
+        });
+    }
+    let bytes: Vec<u8> = fingerprint.iter().flat_map(|v| v.to_le_bytes()).collect();
+    Ok(general_purpose::STANDARD.encode(bytes))
+}
+
+/// The sub-fingerprints of [`compute`], `None` when malformed.
+pub fn decode_fingerprint(encoded: &str) -> Option<Vec<u32>> {
+    let bytes = general_purpose::STANDARD.decode(encoded).ok()?;
+    Some(
+        bytes
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+    )
+}
+
+/// Largest shift between two fingerprints tried when aligning them, about
+/// 10 seconds of a different intro.
+const MAX_OFFSET: usize = 54;
+
+/// Fewest overlapping sub-fingerprints compared, about 10 seconds.
+const MIN_OVERLAP: usize = 50;
+
+/// Similarity from which two fingerprints are the same recording.
+pub const MATCH_THRESHOLD: f64 = 0.8;
+
+/// One minus the bit error rate of the best alignment of two fingerprints.
+/// Unrelated audio lands around 0.5 to 0.65, the same recording above
+/// [`MATCH_THRESHOLD`] even through different encodings.
+pub fn similarity(a: &[u32], b: &[u32]) -> f64 {
+    let mut best = 0.0;
+    for offset in -(MAX_OFFSET as isize)..=MAX_OFFSET as isize {
+        let (a, b) = if offset >= 0 {
+            (a.get(offset as usize..).unwrap_or_default(), b)
+        } else {
+            (a, b.get(offset.unsigned_abs()..).unwrap_or_default())
+        };
+        let overlap = a.len().min(b.len());
+        if overlap < MIN_OVERLAP {
+            continue;
+        }
+        let errors: u32 = a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum();
+        let score = 1.0 - errors as f64 / (overlap * 32) as f64;
+        if score > best {
+            best = score;
+        }
+    }
+    best
+}
+
+/// Fingerprint every audio of the config without one yet. Files that cannot
+/// be decoded are skipped. The result is keyed by audio path and applied to
+/// the config by the frontend.
+/// Blocking; run it on a blocking thread.
+pub fn analyze_config(
+    config: &Config,
+    app_dir: &Path,
+    task: &mut Task,
+) -> AppResult<HashMap<String, String>> {
+    let mut seen = HashSet::new();
+    let pending: Vec<&LocalAudio> = config
+        .playlists
+        .iter()
+        .flat_map(|p| &p.audios)
+        .filter(|a| a.fingerprint.is_none() && seen.insert(a.path.as_str()))
+        .collect();
+    task.set_total(pending.len() as u64);
+
+    let mut fingerprints = HashMap::new();
+    for audio in pending {
+        match compute(&audio.file(app_dir)) {
+            Ok(fingerprint) => {
+                fingerprints.insert(audio.path.clone(), fingerprint);
+            }
+            Err(e) => warn!("Skipping fingerprint of '{}': {}", audio.audio.title, e),
+        }
+        task.advance(1, &audio.audio.title)?;
+    }
+
+    task.finish();
+    info!("Fingerprinted {} files", fingerprints.len());
+    Ok(fingerprints)
+}
+
+/// Audios holding the same recording, the best source first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackMatch {
+    pub audios: Vec<LocalAudio>,
+    /// Lowest similarity of the matched pairs linking the group
+    pub similarity: f64,
+}
+
+fn duration(audio: &LocalAudio) -> Option<f64> {
+    audio
+        .info
+        .as_ref()
+        .and_then(|i| i.duration)
+        .or(audio.audio.duration.map(|d| d as f64))
+}
+
+/// Recordings of the same song differ by at most this much in length, a
+/// longer intro or outro of an upload included.
+const DURATION_TOLERANCE_SECS: f64 = 20.0;
+
+/// Pairs of fingerprinted audios holding the same recording, as indices
+/// into `audios` with their similarity. Only audios of about the same
+/// duration are compared.
+pub fn matching_pairs(audios: &[&LocalAudio]) -> Vec<(usize, usize, f64)> {
+    let mut prints: Vec<(f64, usize, Vec<u32>)> = audios
+        .iter()
+        .enumerate()
+        .filter_map(|(i, a)| {
+            let print = decode_fingerprint(a.fingerprint.as_deref()?)?;
+            Some((duration(a).unwrap_or(0.0), i, print))
+        })
+        .collect();
+    prints.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut pairs = Vec::new();
+    for (n, (secs, i, print)) in prints.iter().enumerate() {
+        for (other_secs, j, other) in &prints[n + 1..] {
+            if other_secs - secs > DURATION_TOLERANCE_SECS {
+                break;
+            }
+            let score = similarity(print, other);
+            if score >= MATCH_THRESHOLD {
+                pairs.push((*i, *j, score));
+            }
+        }
+    }
+    pairs
+}
+
+/// Rank of a source: higher bitrate first, then a platform download over a
+/// local file.
+fn source_rank(audio: &LocalAudio) -> (u32, bool) {
+    (
+        audio.info.as_ref().and_then(|i| i.bitrate).unwrap_or(0),
+        !matches!(audio.audio.platform, Platform::File),
+    )
+}
+
+/// Group the files of the config holding the same recording. With
+/// `cross_platform` only groups spanning more than one platform are kept.
+pub fn match_tracks(config: &Config, cross_platform: bool) -> Vec<TrackMatch> {
+    let mut seen = HashSet::new();
+    let audios: Vec<&LocalAudio> = config
+        .playlists
+        .iter()
+        .flat_map(|p| &p.audios)
+        .filter(|a| seen.insert(a.path.replace('\\', "/")))
+        .collect();
+
+    // Grouped like duplicates, so pairs found in any order join one group
+    let pairs = matching_pairs(&audios);
+    let mut links = UnionFind::new(audios.len());
+    for &(i, j, _) in &pairs {
+        links.union(i, j);
+    }
+    let mut groups: HashMap<usize, (Vec<usize>, f64)> = HashMap::new();
+    for &(i, j, score) in &pairs {
+        let root = links.find(i);
+        let group = groups.entry(root).or_insert_with(|| (Vec::new(), 1.0));
+        group.0.extend([i, j]);
+        group.1 = group.1.min(score);
+    }
+
+    let mut matches: Vec<TrackMatch> = groups
+        .into_values()
+        .map(|(mut members, similarity)| {
+            members.sort_unstable();
+            members.dedup();
+            (members, similarity)
+        })
+        .filter(|(members, _)| {
+            let platforms: HashSet<String> = members
+                .iter()
+                .map(|&i| format!("{:?}", audios[i].audio.platform))
+                .collect();
+            !cross_platform || platforms.len() > 1
+        })
+        .map(|(members, similarity)| {
+            let mut members: Vec<LocalAudio> =
+                members.into_iter().map(|i| audios[i].clone()).collect();
+            members.sort_by_key(|a| std::cmp::Reverse(source_rank(a)));
+            TrackMatch {
+                audios: members,
+                similarity,
+            }
+        })
+        .collect();
+    matches.sort_by(|a, b| a.audios[0].audio.title.cmp(&b.audios[0].audio.title));
+    matches
+}
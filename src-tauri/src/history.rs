@@ -0,0 +1,327 @@
+use crate::{
+    core::HISTORY_FILE,
+    error::{AppError, AppResult},
+};
+use chrono::{Datelike, Local, TimeZone};
+use musicfree::Platform;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::Write,
+    path::Path,
+    sync::{LazyLock, Mutex},
+};
+use tracing::{info, warn};
+
+/// Records older than this are dropped when the store is compacted.
+const RETENTION_DAYS: i64 = 730;
+
+/// Most records kept, the newest ones.
+const MAX_RECORDS: usize = 100_000;
+
+/// The store is compacted once it grows past this many bytes.
+const COMPACT_SIZE: u64 = 8 * 1024 * 1024;
+
+/// A play counts once this much of it was heard, or half of shorter audio.
+const PLAY_MIN_SECS: f64 = 30.0;
+
+/// Serializes writers of the store within the app.
+static LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+
+/// One playback of an audio, a line of `history.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayRecord {
+    pub audio_id: String,
+    pub playlist_id: String,
+    /// Platform of the audio when played, kept once the audio is deleted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<Platform>,
+    /// Seconds into the audio when playback stopped
+    pub position: f64,
+    /// Length of the audio in seconds
+    pub duration: f64,
+    /// Unix milliseconds
+    pub played_at: i64,
+}
+
+impl PlayRecord {
+    /// Seconds listened, never more than the audio is long.
+    pub fn listen_secs(&self) -> f64 {
+        let position = self.position.max(0.0);
+        if self.duration > 0.0 {
+            position.min(self.duration)
+        } else {
+            position
+        }
+    }
+
+    /// Whether enough was heard to count as a play.
+    pub fn is_play(&self) -> bool {
+        let needed = if self.duration > 0.0 {
+            PLAY_MIN_SECS.min(self.duration / 2.0)
+        } else {
+            PLAY_MIN_SECS
+        };
+        self.listen_secs() >= needed
+    }
+
+    fn key(&self) -> (i64, &str, &str) {
+        (self.played_at, &self.audio_id, &self.playlist_id)
+    }
+}
+
+fn lock() -> std::sync::MutexGuard<'static, ()> {
+    LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Parse JSON lines, skipping the ones a crash left truncated.
+fn parse(content: &[u8]) -> Vec<PlayRecord> {
+    let mut skipped = 0;
+    let records = String::from_utf8_lossy(content)
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .filter_map(|l| {
+            let record = serde_json::from_str(l).ok();
+            skipped += record.is_none() as usize;
+            record
+        })
+        .collect();
+    if skipped > 0 {
+        warn!("Skipped {} malformed history records", skipped);
+    }
+    records
+}
+
+fn serialize(records: &[PlayRecord]) -> AppResult<Vec<u8>> {
+    let mut out = Vec::new();
+    for record in records {
+        serde_json::to_writer(&mut out, record)?;
+        out.push(b'\n');
+    }
+    Ok(out)
+}
+
+/// Every record of the store, oldest first.
+/// Blocking; run it on a blocking thread.
+pub fn read(app_dir: &Path) -> AppResult<Vec<PlayRecord>> {
+    match std::fs::read(app_dir.join(HISTORY_FILE)) {
+        Ok(content) => Ok(parse(&content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The raw store, for backups and sync. Empty when nothing was played.
+pub fn read_raw(app_dir: &Path) -> AppResult<Vec<u8>> {
+    match std::fs::read(app_dir.join(HISTORY_FILE)) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Apply the retention policy: drop expired records, then the oldest ones
+/// beyond [`MAX_RECORDS`]. `records` is sorted oldest first.
+fn retain(records: &mut Vec<PlayRecord>) {
+    let cutoff = Local::now().timestamp_millis() - RETENTION_DAYS * 24 * 60 * 60 * 1000;
+    records.retain(|r| r.played_at >= cutoff);
+    if records.len() > MAX_RECORDS {
+        records.drain(..records.len() - MAX_RECORDS);
+    }
+}
+
+/// Replace the store, through a temporary file so it is never half written.
+fn write_store(app_dir: &Path, records: &[PlayRecord]) -> AppResult<()> {
+    let path = app_dir.join(HISTORY_FILE);
+    let part = path.with_extension("jsonl.part");
+    std::fs::write(&part, serialize(records)?)?;
+    std::fs::rename(&part, &path)?;
+    Ok(())
+}
+
+/// Append a record to the store, compacting it once it grew too large.
+/// Blocking; run it on a blocking thread.
+pub fn append(app_dir: &Path, record: &PlayRecord) -> AppResult<()> {
+    let _guard = lock();
+    let path = app_dir.join(HISTORY_FILE);
+    let mut line = serde_json::to_vec(record)?;
+    line.push(b'\n');
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)?;
+    file.write_all(&line)?;
+
+    if file.metadata()?.len() > COMPACT_SIZE {
+        let mut records = read(app_dir)?;
+        let before = records.len();
+        retain(&mut records);
+        write_store(app_dir, &records)?;
+        info!(
+            "Compacted play history from {} to {} records",
+            before,
+            records.len()
+        );
+    }
+    Ok(())
+}
+
+/// Merge records from a backup or another device into the store. Records
+/// already present are kept once. Returns the merged store.
+/// Blocking; run it on a blocking thread.
+pub fn merge(app_dir: &Path, other: &[u8]) -> AppResult<Vec<u8>> {
+    let _guard = lock();
+    let mut records = read(app_dir)?;
+    let before = records.len();
+    records.extend(parse(other));
+    records.sort_by(|a, b| a.key().cmp(&b.key()));
+    records.dedup_by(|a, b| a.key() == b.key());
+    let count = records.len().saturating_sub(before);
+    retain(&mut records);
+    write_store(app_dir, &records)?;
+    info!("Merged {} play history records", count);
+    serialize(&records)
+}
+
+/// Remove the store.
+pub fn clear(app_dir: &Path) -> AppResult<()> {
+    let _guard = lock();
+    match std::fs::remove_file(app_dir.join(HISTORY_FILE)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(AppError::Io(e)),
+        _ => Ok(()),
+    }
+}
+
+/// The last play of each audio, most recent first.
+pub fn recent(records: &[PlayRecord], limit: usize) -> Vec<PlayRecord> {
+    let mut seen = HashSet::new();
+    records
+        .iter()
+        .rev()
+        .filter(|r| seen.insert(r.audio_id.as_str()))
+        .take(limit)
+        .cloned()
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackStats {
+    pub audio_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<Platform>,
+    pub plays: u32,
+    pub listen_secs: f64,
+    /// Unix milliseconds
+    pub last_played: i64,
+}
+
+/// Plays and listening time of a playlist, platform, day or week.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GroupStats {
+    /// Playlist id, platform, `2024-05-01` or ISO week `2024-W18`
+    pub key: String,
+    pub plays: u32,
+    pub listen_secs: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HistoryStats {
+    pub plays: u32,
+    pub listen_secs: f64,
+    /// Most played first
+    pub tracks: Vec<TrackStats>,
+    /// Most listened first
+    pub playlists: Vec<GroupStats>,
+    /// Most listened first
+    pub platforms: Vec<GroupStats>,
+    /// Oldest first, local time
+    pub daily: Vec<GroupStats>,
+    /// Oldest first, local time
+    pub weekly: Vec<GroupStats>,
+}
+
+fn add(group: &mut GroupStats, record: &PlayRecord) {
+    group.plays += record.is_play() as u32;
+    group.listen_secs += record.listen_secs();
+}
+
+fn add_keyed(groups: &mut HashMap<String, GroupStats>, key: String, record: &PlayRecord) {
+    let group = groups.entry(key.clone()).or_insert_with(|| GroupStats {
+        key,
+        ..Default::default()
+    });
+    add(group, record);
+}
+
+fn by_listening(groups: HashMap<String, GroupStats>) -> Vec<GroupStats> {
+    let mut groups: Vec<GroupStats> = groups.into_values().collect();
+    groups.sort_by(|a, b| b.listen_secs.total_cmp(&a.listen_secs));
+    groups
+}
+
+/// Aggregate the records played since `since` (Unix milliseconds). Only
+/// the `limit` most played tracks are listed.
+pub fn stats(records: &[PlayRecord], since: Option<i64>, limit: usize) -> HistoryStats {
+    let mut result = HistoryStats::default();
+    let mut tracks: HashMap<&str, TrackStats> = HashMap::new();
+    let mut playlists: HashMap<String, GroupStats> = HashMap::new();
+    let mut platforms: HashMap<String, GroupStats> = HashMap::new();
+    let mut daily: BTreeMap<String, GroupStats> = BTreeMap::new();
+    let mut weekly: BTreeMap<String, GroupStats> = BTreeMap::new();
+
+    for record in records
+        .iter()
+        .filter(|r| since.is_none_or(|s| r.played_at >= s))
+    {
+        result.plays += record.is_play() as u32;
+        result.listen_secs += record.listen_secs();
+
+        let track = tracks
+            .entry(&record.audio_id)
+            .or_insert_with(|| TrackStats {
+                audio_id: record.audio_id.clone(),
+                platform: record.platform,
+                plays: 0,
+                listen_secs: 0.0,
+                last_played: 0,
+            });
+        track.plays += record.is_play() as u32;
+        track.listen_secs += record.listen_secs();
+        track.last_played = track.last_played.max(record.played_at);
+
+        add_keyed(&mut playlists, record.playlist_id.clone(), record);
+        let platform = record
+            .platform
+            .map_or("Unknown".to_string(), |p| format!("{:?}", p));
+        add_keyed(&mut platforms, platform, record);
+
+        if let Some(time) = Local.timestamp_millis_opt(record.played_at).single() {
+            let date = time.date_naive();
+            let week = date.iso_week();
+            for (groups, key) in [
+                (&mut daily, date.format("%Y-%m-%d").to_string()),
+                (&mut weekly, format!("{}-W{:02}", week.year(), week.week())),
+            ] {
+                let entry = groups.entry(key.clone()).or_insert_with(|| GroupStats {
+                    key,
+                    ..Default::default()
+                });
+                add(entry, record);
+            }
+        }
+    }
+
+    let mut tracks: Vec<TrackStats> = tracks.into_values().collect();
+    tracks.sort_by(|a, b| {
+        b.plays
+            .cmp(&a.plays)
+            .then(b.listen_secs.total_cmp(&a.listen_secs))
+    });
+    tracks.truncate(limit);
+    result.tracks = tracks;
+    result.playlists = by_listening(playlists);
+    result.platforms = by_listening(platforms);
+    result.daily = daily.into_values().collect();
+    result.weekly = weekly.into_values().collect();
+    result
+}
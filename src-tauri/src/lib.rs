@@ -1,14 +1,37 @@
 pub mod android;
 pub mod api;
+pub mod backup;
 pub mod cmd;
 pub mod core;
+pub mod credentials;
+pub mod decode;
+pub mod demux;
+pub mod diagnostics;
+pub mod discover;
+pub mod duplicates;
 pub mod error;
+pub mod fingerprint;
+pub mod history;
+pub mod library;
+pub mod logging;
+pub mod loudness;
+pub mod lyrics;
+pub mod playlists;
+pub mod probe;
+pub mod progress;
+pub mod search;
+pub mod silence;
+pub mod split;
+pub mod subtitles;
 pub mod sync;
+pub mod transcode;
 
 use android::request_storage_permission;
 use std::io::SeekFrom;
 use std::path::Path;
+use tauri::Manager;
 use tauri::http::{Response, StatusCode};
+use tracing::error;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -48,6 +71,14 @@ pub fn run() {
         .plugin(tauri_plugin_single_instance::init(|_app, _args, _cwd| {}));
 
     builder
+        .setup(|app| {
+            let app_data_dir = app.path().app_data_dir()?;
+            let app_cache_dir = app.path().app_cache_dir()?;
+            logging::init(crate::core::get_log_path(app_data_dir.clone()));
+            // Off the main thread: a crashed import may have left large files
+            std::thread::spawn(move || backup::cleanup_staging(&app_data_dir, &app_cache_dir));
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             cmd::extract_audios,
             cmd::app_dir,
@@ -63,6 +94,15 @@ pub fn run() {
             cmd::exists_cover,
             cmd::export_data,
             cmd::import_data,
+            cmd::cancel_task,
+            cmd::import_playlist_file,
+            cmd::export_playlist,
+            cmd::scan_local_folders,
+            cmd::watch_local_folders,
+            cmd::unwatch_local_folders,
+            cmd::list_backups,
+            cmd::inspect_backup,
+            cmd::export_diagnostics,
             cmd::remove_file,
             cmd::get_storage_size,
             cmd::get_cache_size,
@@ -70,13 +110,43 @@ pub fn run() {
             cmd::sync_download,
             cmd::sync_update,
             cmd::sync_file_info,
+            cmd::sync_history,
+            cmd::sync_restore,
+            cmd::set_sync_credentials,
+            cmd::clear_sync_credentials,
+            cmd::sync_status,
+            cmd::sync_upload_assets,
+            cmd::sync_download_assets,
             cmd::write_log,
             cmd::get_log_path,
             cmd::clear_log,
             cmd::get_log_size,
             cmd::read_log,
+            cmd::query_logs,
+            cmd::set_log_level,
             cmd::save_audio,
             cmd::transcode_audio,
+            cmd::probe_audio,
+            cmd::analyze_loudness,
+            cmd::replay_gain,
+            cmd::detect_silence,
+            cmd::trim_audio,
+            cmd::parse_timestamps,
+            cmd::split_audio,
+            cmd::import_lyrics,
+            cmd::attach_lyrics,
+            cmd::get_lyrics,
+            cmd::set_lyrics_offset,
+            cmd::search_library,
+            cmd::search_platform,
+            cmd::find_duplicates,
+            cmd::merge_duplicates,
+            cmd::fingerprint_audios,
+            cmd::match_tracks,
+            cmd::record_play,
+            cmd::recent_plays,
+            cmd::play_stats,
+            cmd::sync_play_history,
             request_storage_permission,
         ])
         .run(tauri::generate_context!())
@@ -85,16 +155,29 @@ pub fn run() {
 
 /// Handler for musicfree:// protocol (async version)
 /// Example: musicfree://assets/covers/bilibili/q.jpg
+///
+/// Only serves the app directory. Files referenced in place have no file
+/// under their `external/` key, the frontend plays them from
+/// `external_path` through the asset protocol.
 async fn musicfree_protocol_handler_async(
     app_handle: &tauri::AppHandle,
     path: &str,
     range: Option<&str>,
 ) -> Response<Vec<u8>> {
+    if path.starts_with(&format!("{}/", crate::core::EXTERNAL_DIR)) {
+        error!("Referenced file requested from the app directory: {}", path);
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .header("Content-Type", "text/plain")
+            .body(b"Referenced files are not served from the app directory".to_vec())
+            .unwrap_or_else(|_| Response::new(vec![]));
+    }
+
     // Get app data directory
     let app_data_dir = match api::app_dir(app_handle).await {
         Ok(dir) => dir,
         Err(e) => {
-            eprintln!("Failed to get app data directory: {}", e);
+            error!("Failed to get app data directory: {}", e);
             return match Response::builder()
                 .status(StatusCode::INTERNAL_SERVER_ERROR)
                 .header("Content-Type", "text/plain")
@@ -102,7 +185,7 @@ async fn musicfree_protocol_handler_async(
             {
                 Ok(response) => response,
                 Err(e) => {
-                    eprintln!("Failed to build error response: {}", e);
+                    error!("Failed to build error response: {}", e);
                     Response::new(vec![])
                 }
             };
@@ -120,7 +203,7 @@ async fn musicfree_protocol_handler_async(
         match handle_range_request_async(&file_path, range_header, mime_type).await {
             Ok(response) => response,
             Err(e) => {
-                eprintln!("Failed to handle range request: {}", e);
+                error!("Failed to handle range request: {}", e);
                 match Response::builder()
                     .status(StatusCode::INTERNAL_SERVER_ERROR)
                     .header("Content-Type", "text/plain")
@@ -128,7 +211,7 @@ async fn musicfree_protocol_handler_async(
                 {
                     Ok(response) => response,
                     Err(e) => {
-                        eprintln!("Failed to build error response: {}", e);
+                        error!("Failed to build error response: {}", e);
                         Response::new(vec![])
                     }
                 }
@@ -147,7 +230,7 @@ async fn musicfree_protocol_handler_async(
                 {
                     Ok(response) => response,
                     Err(e) => {
-                        eprintln!("Failed to build response: {}", e);
+                        error!("Failed to build response: {}", e);
                         Response::builder()
                             .status(StatusCode::INTERNAL_SERVER_ERROR)
                             .header("Content-Type", "text/plain")
@@ -157,7 +240,7 @@ async fn musicfree_protocol_handler_async(
                 }
             }
             Err(e) => {
-                eprintln!("Failed to read file {:?}: {}", file_path, e);
+                error!("Failed to read file {:?}: {}", file_path, e);
                 match Response::builder()
                     .status(StatusCode::NOT_FOUND)
                     .header("Content-Type", "text/plain")
@@ -165,7 +248,7 @@ async fn musicfree_protocol_handler_async(
                 {
                     Ok(response) => response,
                     Err(e) => {
-                        eprintln!("Failed to build error response: {}", e);
+                        error!("Failed to build error response: {}", e);
                         Response::builder()
                             .status(StatusCode::INTERNAL_SERVER_ERROR)
                             .body(vec![])
@@ -260,7 +343,7 @@ fn get_mime_type(path: &Path) -> &'static str {
         Some("flac") => "audio/flac",
         Some("wav") => "audio/wav",
         Some("aac") => "audio/aac",
-        Some("ogg") => "audio/ogg",
+        Some("ogg") | Some("opus") => "audio/ogg",
         Some("mp4") => "video/mp4",
         Some("webm") => "video/webm",
         Some("json") => "application/json",
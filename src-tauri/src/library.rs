@@ -0,0 +1,564 @@
+use crate::{
+    core::{
+        ASSETS_DIR, AUDIOS_DIR, COVERS_DIR, Config, EXTERNAL_DIR, LocalAudio, LocalPlaylist,
+        get_config_path,
+    },
+    error::{AppError, AppResult},
+    progress::Task,
+};
+use lofty::{
+    picture::{MimeType, PictureType},
+    prelude::*,
+};
+use musicfree::{Audio, Platform};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+use tauri::Emitter;
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+/// Emitted with a [`LibraryChange`] when a watched folder changes.
+pub const LIBRARY_EVENT: &str = "library-changed";
+
+/// Extensions picked up by scans and watchers.
+const AUDIO_EXTENSIONS: &[&str] = &[
+    "mp3", "m4a", "flac", "wav", "aac", "ogg", "opus", "wma", "ape", "aif", "aiff",
+];
+
+/// Active watchers by root folder. Dropping a watcher stops it.
+static WATCHERS: LazyLock<Mutex<HashMap<PathBuf, notify::RecommendedWatcher>>> =
+    LazyLock::new(Default::default);
+
+/// A changed file is imported once its size and mtime held for this long.
+const SETTLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Files still changing after this long are left for their next event.
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Files waiting to settle, and the state each watched file was imported at.
+static SETTLING: LazyLock<Mutex<HashSet<PathBuf>>> = LazyLock::new(Default::default);
+static IMPORTED: LazyLock<Mutex<HashMap<PathBuf, FileState>>> = LazyLock::new(Default::default);
+
+/// Size and mtime of a file.
+type FileState = (u64, Option<SystemTime>);
+
+fn file_state(path: &Path) -> Option<FileState> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()))
+}
+
+pub fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .is_some_and(|e| AUDIO_EXTENSIONS.contains(&e.as_str()))
+}
+
+fn path_hash(path: &Path) -> String {
+    format!("{:x}", md5::compute(path.to_string_lossy().as_bytes()))
+}
+
+/// Audio id of a local file: the hash of its absolute path, so rescans and
+/// watcher events refer to the same track.
+pub fn local_audio_id(path: &Path) -> String {
+    path_hash(path)
+}
+
+/// Id of the playlist holding the files of a scanned folder.
+pub fn local_playlist_id(root: &Path) -> String {
+    format!("local-{}", &path_hash(root)[..8])
+}
+
+/// `assets/File/audios/{title}-{hash}.{ext}`, stable for the same source path.
+pub fn local_asset_path(source: &Path, title: &str) -> String {
+    format!(
+        "{}/{:?}/{}/{}-{}{}",
+        ASSETS_DIR,
+        Platform::File,
+        AUDIOS_DIR,
+        sanitize_filename::sanitize(title),
+        &path_hash(source)[..6],
+        extension(source)
+    )
+}
+
+/// Lowercase extension with its dot, empty when there is none.
+fn extension(source: &Path) -> String {
+    source
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy().to_ascii_lowercase()))
+        .unwrap_or_default()
+}
+
+/// Tags read from an audio file.
+#[derive(Debug, Default)]
+struct FileTags {
+    title: Option<String>,
+    /// Embedded front cover, with its file extension
+    cover: Option<(Vec<u8>, &'static str)>,
+}
+
+fn read_tags(path: &Path) -> FileTags {
+    let tagged = match lofty::read_from_path(path) {
+        Ok(tagged) => tagged,
+        Err(e) => {
+            warn!("Could not read tags of {}: {}", path.display(), e);
+            return FileTags::default();
+        }
+    };
+    let mut tags = FileTags::default();
+    let Some(tag) = tagged.primary_tag().or_else(|| tagged.first_tag()) else {
+        return tags;
+    };
+
+    tags.title = tag
+        .title()
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty());
+    let picture = tag
+        .pictures()
+        .iter()
+        .find(|p| p.pic_type() == PictureType::CoverFront)
+        .or_else(|| tag.pictures().first());
+    if let Some(picture) = picture {
+        let ext = match picture.mime_type() {
+            Some(MimeType::Png) => "png",
+            Some(MimeType::Gif) => "gif",
+            Some(MimeType::Bmp) => "bmp",
+            _ => "jpg",
+        };
+        tags.cover = Some((picture.data().to_vec(), ext));
+    }
+    tags
+}
+
+/// `external/{hash}.{ext}`, the key of a file referenced in place.
+fn external_key(source: &Path) -> String {
+    format!(
+        "{}/{}{}",
+        EXTERNAL_DIR,
+        path_hash(source),
+        extension(source)
+    )
+}
+
+/// Write an embedded cover to `assets/File/covers/` once per source file.
+fn save_cover(source: &Path, data: &[u8], ext: &str, app_dir: &Path) -> Option<String> {
+    let cover_path = format!(
+        "{}/{:?}/{}/{}.{}",
+        ASSETS_DIR,
+        Platform::File,
+        COVERS_DIR,
+        path_hash(source),
+        ext
+    );
+    let dest = app_dir.join(&cover_path);
+    if !dest.exists() {
+        let result = dest
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&dest, data));
+        if let Err(e) = result {
+            warn!("Failed to write cover for {}: {}", source.display(), e);
+            return None;
+        }
+    }
+    Some(cover_path)
+}
+
+/// Copy `source` to `dest` unless it already holds the same size and mtime.
+/// The copy is written next to `dest` and renamed, and takes the mtime of
+/// the source, so an edited source is copied again.
+fn copy_asset(source: &Path, dest: &Path) -> AppResult<()> {
+    let state = file_state(source);
+    if state.is_some() && file_state(dest) == state {
+        return Ok(());
+    }
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut part = dest.as_os_str().to_os_string();
+    part.push(".part");
+    let part = PathBuf::from(part);
+    let copied = std::fs::copy(source, &part).and_then(|_| {
+        if let Some(mtime) = state.and_then(|(_, mtime)| mtime) {
+            std::fs::File::options()
+                .write(true)
+                .open(&part)?
+                .set_modified(mtime)?;
+        }
+        std::fs::rename(&part, dest)
+    });
+    if copied.is_err() {
+        let _ = std::fs::remove_file(&part);
+    }
+    Ok(copied?)
+}
+
+/// Describe a local file as a [`LocalAudio`], reading its tags, cover and
+/// stream properties.
+///
+/// With `copy`, the file is copied into `assets/File/audios`. Otherwise it is
+/// referenced in place through [`LocalAudio::external_path`].
+/// Hints from a playlist file are used when the file carries no tags.
+/// Blocking; run it on a blocking thread.
+pub fn local_audio(
+    source: &Path,
+    app_dir: &Path,
+    copy: bool,
+    title_hint: Option<&str>,
+    duration_hint: Option<f64>,
+) -> AppResult<LocalAudio> {
+    if !source.is_file() {
+        return Err(AppError::NotFound(source.display().to_string()));
+    }
+    let tags = read_tags(source);
+    let info = crate::probe::probe(source)
+        .inspect_err(|e| warn!("Could not probe {}: {}", source.display(), e))
+        .ok();
+    let title = tags
+        .title
+        .or_else(|| title_hint.map(str::to_string))
+        .or_else(|| source.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .unwrap_or_else(|| source.display().to_string());
+
+    let (path, external_path) = if copy {
+        let path = local_asset_path(source, &title);
+        copy_asset(source, &app_dir.join(&path))?;
+        (path, None)
+    } else {
+        (
+            external_key(source),
+            Some(source.to_string_lossy().to_string()),
+        )
+    };
+    let cover_path = tags
+        .cover
+        .and_then(|(data, ext)| save_cover(source, &data, ext, app_dir));
+
+    Ok(LocalAudio {
+        path,
+        external_path,
+        cover_path,
+        audio: Audio {
+            id: local_audio_id(source),
+            title,
+            download_url: source.to_string_lossy().to_string(),
+            cover: None,
+            platform: Platform::File,
+            duration: info
+                .as_ref()
+                .and_then(|i| i.duration)
+                .or(duration_hint)
+                .map(|d| d.round() as _),
+            format: None,
+        },
+        info,
+        loudness: None,
+        start_ms: None,
+        end_ms: None,
+        lyrics_path: None,
+        fingerprint: None,
+    })
+}
+
+/// Result of [`scan_folders`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanResult {
+    /// One playlist per scanned folder, see [`local_playlist_id`]
+    pub playlists: Vec<LocalPlaylist>,
+    /// Files that could not be read
+    pub failed: Vec<String>,
+}
+
+/// Walk `roots` for audio files and describe each folder as a playlist.
+/// Blocking; run it on a blocking thread.
+pub fn scan_folders(
+    roots: &[PathBuf],
+    app_dir: &Path,
+    copy: bool,
+    task: &mut Task,
+) -> AppResult<ScanResult> {
+    let mut files: Vec<(usize, PathBuf)> = Vec::new();
+    for (i, root) in roots.iter().enumerate() {
+        if !root.is_dir() {
+            return Err(AppError::NotFound(root.display().to_string()));
+        }
+        for entry in WalkDir::new(root).follow_links(true).sort_by_file_name() {
+            match entry {
+                Ok(entry) if entry.file_type().is_file() && is_audio_file(entry.path()) => {
+                    files.push((i, entry.into_path()));
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Skipping unreadable entry: {}", e),
+            }
+        }
+    }
+    task.set_total(files.len() as u64);
+
+    let mut playlists: Vec<LocalPlaylist> = roots
+        .iter()
+        .map(|root| LocalPlaylist {
+            id: Some(local_playlist_id(root)),
+            download_url: None,
+            title: root
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .or_else(|| Some(root.display().to_string())),
+            cover_path: None,
+            cover: None,
+            audios: Vec::new(),
+            platform: Platform::File,
+        })
+        .collect();
+    let mut failed = Vec::new();
+
+    for (i, path) in files {
+        let label = path.display().to_string();
+        match local_audio(&path, app_dir, copy, None, None) {
+            Ok(audio) => playlists[i].audios.push(audio),
+            Err(e) => {
+                warn!("Skipping {}: {}", label, e);
+                failed.push(label.clone());
+            }
+        }
+        task.advance(1, &label)?;
+    }
+    for playlist in &mut playlists {
+        playlist.cover_path = playlist.audios.iter().find_map(|a| a.cover_path.clone());
+    }
+
+    task.finish();
+    info!(
+        "Scanned {} folders: {} tracks, {} failed",
+        roots.len(),
+        playlists.iter().map(|p| p.audios.len()).sum::<usize>(),
+        failed.len()
+    );
+    Ok(ScanResult { playlists, failed })
+}
+
+/// Payload of [`LIBRARY_EVENT`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryChange {
+    pub playlist_id: String,
+    pub added: Vec<LocalAudio>,
+    /// Audios of the playlist whose file changed, to replace in place
+    #[serde(default)]
+    pub updated: Vec<LocalAudio>,
+    /// Ids of audios whose file was removed
+    pub removed: Vec<String>,
+}
+
+impl LibraryChange {
+    fn new(root: &Path) -> Self {
+        Self {
+            playlist_id: local_playlist_id(root),
+            added: Vec::new(),
+            updated: Vec::new(),
+            removed: Vec::new(),
+        }
+    }
+}
+
+fn emit(app_handle: &tauri::AppHandle, change: LibraryChange) {
+    if let Err(e) = app_handle.emit(LIBRARY_EVENT, change) {
+        warn!("Failed to emit library change: {}", e);
+    }
+}
+
+/// Ids of the audios in the saved playlist `playlist_id`.
+fn known_ids(app_dir: &Path, playlist_id: &str) -> HashSet<String> {
+    let config: Config = std::fs::read(get_config_path(app_dir.to_path_buf()))
+        .ok()
+        .and_then(|content| serde_json::from_slice(&content).ok())
+        .unwrap_or_default();
+    config
+        .playlists
+        .into_iter()
+        .filter(|p| p.id.as_deref() == Some(playlist_id))
+        .flat_map(|p| p.audios)
+        .map(|a| a.audio.id)
+        .collect()
+}
+
+/// Poll `path` until its size and mtime stop changing. `None` once it is
+/// gone or still changing after [`SETTLE_TIMEOUT`].
+fn settle(path: &Path) -> Option<FileState> {
+    let started = Instant::now();
+    let mut last = file_state(path)?;
+    loop {
+        std::thread::sleep(SETTLE_INTERVAL);
+        let state = file_state(path)?;
+        if state == last {
+            return Some(state);
+        }
+        if started.elapsed() > SETTLE_TIMEOUT {
+            warn!("{} is still changing, not importing it", path.display());
+            return None;
+        }
+        last = state;
+    }
+}
+
+/// Import `path` once it has settled and emit it as added, or as updated
+/// when the playlist already holds it.
+fn import_settled(
+    app_handle: &tauri::AppHandle,
+    root: &Path,
+    app_dir: &Path,
+    copy: bool,
+    path: &Path,
+) {
+    let settled = settle(path);
+    SETTLING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(path);
+    let Some(state) = settled else {
+        return;
+    };
+    // Events that left the file as it was last imported change nothing
+    let imported = IMPORTED.lock().unwrap_or_else(|e| e.into_inner()).get(path) == Some(&state);
+    if imported {
+        return;
+    }
+    let audio = match local_audio(path, app_dir, copy, None, None) {
+        Ok(audio) => audio,
+        Err(e) => {
+            warn!("Could not import {}: {}", path.display(), e);
+            return;
+        }
+    };
+    IMPORTED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(path.to_path_buf(), state);
+
+    let mut change = LibraryChange::new(root);
+    if known_ids(app_dir, &change.playlist_id).contains(&audio.audio.id) {
+        change.updated.push(audio);
+    } else {
+        change.added.push(audio);
+    }
+    emit(app_handle, change);
+}
+
+fn handle_event(
+    app_handle: &tauri::AppHandle,
+    root: &Path,
+    app_dir: &Path,
+    copy: bool,
+    event: notify::Event,
+) {
+    use notify::{EventKind, event::ModifyKind};
+
+    if !matches!(
+        event.kind,
+        EventKind::Create(_)
+            | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Name(_) | ModifyKind::Any)
+            | EventKind::Remove(_)
+    ) {
+        return;
+    }
+    let mut change = LibraryChange::new(root);
+    // Renames arrive as paths that exist (added) or not (removed)
+    for path in event.paths.iter().filter(|p| is_audio_file(p)) {
+        if path.is_file() {
+            // Files still being written are imported once they settle, later
+            // events for them are covered by the same wait
+            if !SETTLING
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(path.clone())
+            {
+                continue;
+            }
+            let (app_handle, root, app_dir, path) = (
+                app_handle.clone(),
+                root.to_path_buf(),
+                app_dir.to_path_buf(),
+                path.clone(),
+            );
+            std::thread::spawn(move || import_settled(&app_handle, &root, &app_dir, copy, &path));
+        } else if !path.exists() {
+            IMPORTED
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(path);
+            change.removed.push(local_audio_id(path));
+        }
+    }
+    if !change.removed.is_empty() {
+        emit(app_handle, change);
+    }
+}
+
+fn watch_error(message: String, e: notify::Error) -> AppError {
+    match &e.kind {
+        notify::ErrorKind::PathNotFound => AppError::NotFound(message),
+        notify::ErrorKind::Io(io) if io.kind() == std::io::ErrorKind::PermissionDenied => {
+            AppError::Permission(message)
+        }
+        _ => AppError::Io(std::io::Error::other(format!("{}: {}", message, e))),
+    }
+}
+
+/// Watch `roots` recursively and emit [`LIBRARY_EVENT`] for added and
+/// removed audio files. Folders already watched are kept as they are.
+pub fn watch(
+    app_handle: &tauri::AppHandle,
+    roots: &[PathBuf],
+    app_dir: &Path,
+    copy: bool,
+) -> AppResult<()> {
+    use notify::Watcher;
+
+    let mut watchers = WATCHERS.lock().unwrap_or_else(|e| e.into_inner());
+    for root in roots {
+        if watchers.contains_key(root) {
+            continue;
+        }
+        let (app_handle, watched_root, app_dir) =
+            (app_handle.clone(), root.clone(), app_dir.to_path_buf());
+        let mut watcher =
+            notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+                Ok(event) => handle_event(&app_handle, &watched_root, &app_dir, copy, event),
+                Err(e) => warn!("Watch error on {}: {}", watched_root.display(), e),
+            })
+            .map_err(|e| watch_error("Failed to create watcher".to_string(), e))?;
+        watcher
+            .watch(root, notify::RecursiveMode::Recursive)
+            .map_err(|e| watch_error(format!("Failed to watch {}", root.display()), e))?;
+        info!("Watching {}", root.display());
+        watchers.insert(root.clone(), watcher);
+    }
+    Ok(())
+}
+
+/// Stop watching `roots`, or every folder when `None`.
+pub fn unwatch(roots: Option<&[PathBuf]>) {
+    let mut watchers = WATCHERS.lock().unwrap_or_else(|e| e.into_inner());
+    match roots {
+        Some(roots) => {
+            for root in roots {
+                watchers.remove(root);
+            }
+        }
+        None => watchers.clear(),
+    }
+}
+
+/// Folders currently being watched.
+pub fn watched() -> Vec<PathBuf> {
+    WATCHERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .keys()
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,318 @@
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+use tracing_subscriber::{
+    EnvFilter, Registry, fmt, layer::SubscriberExt, reload, util::SubscriberInitExt,
+};
+
+/// Rotate the active log file once it grows past this size.
+pub const MAX_LOG_SIZE: u64 = 5 * 1024 * 1024;
+
+/// Number of rotated files kept besides the active one
+/// (`musicfree.log.1` is the newest, `musicfree.log.4` the oldest).
+pub const MAX_ROTATED_LOGS: usize = 4;
+
+const DEFAULT_FILTER: &str = "info";
+
+static ACTIVE_LOG: OnceLock<Mutex<RotatingFile>> = OnceLock::new();
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Size-rotated log file shared by every tracing event.
+struct RotatingFile {
+    path: PathBuf,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(&mut self) -> io::Result<&mut File> {
+        let file = match self.file.take() {
+            Some(file) => file,
+            None => {
+                if let Some(dir) = self.path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)?;
+                self.size = file.metadata().map(|m| m.len()).unwrap_or(0);
+                file
+            }
+        };
+        Ok(self.file.insert(file))
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        let oldest = rotated_path(&self.path, MAX_ROTATED_LOGS);
+        if oldest.exists() {
+            std::fs::remove_file(&oldest)?;
+        }
+        for i in (1..MAX_ROTATED_LOGS).rev() {
+            let from = rotated_path(&self.path, i);
+            if from.exists() {
+                std::fs::rename(&from, rotated_path(&self.path, i + 1))?;
+            }
+        }
+        if self.path.exists() {
+            std::fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > MAX_LOG_SIZE {
+            self.rotate()?;
+        }
+        let n = self.open()?.write(buf)?;
+        self.size += n as u64;
+        Ok(n)
+    }
+}
+
+/// `MakeWriter` handing out the global [`RotatingFile`].
+struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match ACTIVE_LOG.get() {
+            Some(file) => file
+                .lock()
+                .map_err(|_| io::Error::other("log file lock poisoned"))?
+                .write(buf),
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> fmt::MakeWriter<'a> for LogWriter {
+    type Writer = LogWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LogWriter
+    }
+}
+
+/// `musicfree.log` for index 0, `musicfree.log.{index}` otherwise.
+pub fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        return path.to_path_buf();
+    }
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", index));
+    path.with_file_name(name)
+}
+
+/// All log files that currently exist, oldest first.
+pub fn log_files(path: &Path) -> Vec<PathBuf> {
+    (0..=MAX_ROTATED_LOGS)
+        .rev()
+        .map(|i| rotated_path(path, i))
+        .filter(|p| p.exists())
+        .collect()
+}
+
+/// Install the global subscriber: JSON lines into the rotating file, plus
+/// human-readable output on stderr for development.
+pub fn init(log_path: PathBuf) {
+    if ACTIVE_LOG
+        .set(Mutex::new(RotatingFile {
+            path: log_path,
+            file: None,
+            size: 0,
+        }))
+        .is_err()
+    {
+        return;
+    }
+
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let (filter, handle) = reload::Layer::new(filter);
+    let _ = FILTER.set(handle);
+
+    let file_layer = fmt::layer()
+        .json()
+        .with_timer(fmt::time::ChronoUtc::rfc_3339())
+        .with_current_span(true)
+        .with_span_list(false)
+        .with_writer(LogWriter);
+    let stderr_layer = fmt::layer().with_writer(io::stderr);
+
+    if let Err(e) = tracing_subscriber::registry()
+        .with(filter)
+        .with(file_layer)
+        .with(stderr_layer)
+        .try_init()
+    {
+        eprintln!("Failed to install log subscriber: {}", e);
+    }
+}
+
+/// Change the level filter at runtime, e.g. `debug` or `info,musicfree_tauri_lib::sync=trace`.
+pub fn set_level(directives: &str) -> AppResult<()> {
+    let filter = EnvFilter::try_new(directives)
+        .map_err(|e| AppError::Invalid(format!("Log filter '{}': {}", directives, e)))?;
+    if let Some(handle) = FILTER.get() {
+        handle
+            .reload(filter)
+            .map_err(|e| AppError::Unknown(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Close the active file and delete every log file.
+pub fn clear(path: &Path) -> io::Result<()> {
+    let _guard = ACTIVE_LOG.get().map(|file| {
+        let mut guard = file.lock().unwrap_or_else(|e| e.into_inner());
+        guard.file = None;
+        guard.size = 0;
+        guard
+    });
+    for file in log_files(path) {
+        std::fs::remove_file(file)?;
+    }
+    Ok(())
+}
+
+/// One line of the JSON log, as written by `tracing_subscriber`.
+#[derive(Debug, Deserialize)]
+struct RawLogLine {
+    timestamp: String,
+    level: String,
+    #[serde(default)]
+    target: String,
+    #[serde(default)]
+    fields: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    span: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    /// `module` field for frontend logs, the Rust module path otherwise
+    pub module: String,
+    pub message: String,
+    /// Remaining structured fields of the event
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<serde_json::Value>,
+}
+
+fn level_rank(level: &str) -> u8 {
+    match level.to_ascii_uppercase().as_str() {
+        "TRACE" => 0,
+        "DEBUG" => 1,
+        "INFO" => 2,
+        "WARN" => 3,
+        "ERROR" => 4,
+        _ => 2,
+    }
+}
+
+/// Filters for [`query`]. Every field is optional.
+#[derive(Debug, Clone, Default)]
+pub struct LogQuery {
+    /// Minimum level, e.g. `warn` also returns errors
+    pub level: Option<String>,
+    /// Substring matched against the entry module
+    pub module: Option<String>,
+    /// Only entries at or after this time (milliseconds since epoch)
+    pub since: Option<i64>,
+    /// Return at most this many of the newest matches
+    pub limit: usize,
+}
+
+/// Scan the log files line by line, oldest first, keeping only the newest
+/// `limit` matches in memory.
+pub fn query(path: &Path, query: &LogQuery) -> io::Result<Vec<LogEntry>> {
+    let min_rank = query.level.as_deref().map(level_rank).unwrap_or(0);
+    let mut entries = VecDeque::with_capacity(query.limit.min(1024));
+
+    for file in log_files(path) {
+        let reader = BufReader::new(File::open(file)?);
+        for line in reader.lines() {
+            let Ok(line) = line else { continue };
+            // Lines written before the JSON format are skipped
+            let Ok(mut raw) = serde_json::from_str::<RawLogLine>(&line) else {
+                continue;
+            };
+            if level_rank(&raw.level) < min_rank {
+                continue;
+            }
+            if let Some(since) = query.since {
+                let Ok(time) = chrono::DateTime::parse_from_rfc3339(&raw.timestamp) else {
+                    continue;
+                };
+                if time.timestamp_millis() < since {
+                    continue;
+                }
+            }
+
+            let message = match raw.fields.remove("message") {
+                Some(serde_json::Value::String(s)) => s,
+                Some(v) => v.to_string(),
+                None => String::new(),
+            };
+            let module = match raw.fields.remove("module") {
+                Some(serde_json::Value::String(s)) => s,
+                _ => raw.target,
+            };
+            if let Some(filter) = query.module.as_deref()
+                && !module.contains(filter)
+            {
+                continue;
+            }
+
+            if query.limit > 0 && entries.len() == query.limit {
+                entries.pop_front();
+            }
+            entries.push_back(LogEntry {
+                timestamp: raw.timestamp,
+                level: raw.level,
+                module,
+                message,
+                fields: raw.fields,
+                span: raw.span,
+            });
+        }
+    }
+
+    Ok(entries.into())
+}
+
+/// Read at most the last `max_bytes` of the active log file.
+pub fn tail(path: &Path, max_bytes: u64) -> io::Result<String> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(max_bytes);
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut buf = Vec::with_capacity((len - start) as usize);
+    file.read_to_end(&mut buf)?;
+    let mut text = String::from_utf8_lossy(&buf).into_owned();
+    // Drop the partial first line when starting mid-file
+    if start > 0
+        && let Some(pos) = text.find('\n')
+    {
+        text.replace_range(..=pos, "");
+    }
+    Ok(text)
+}
@@ -0,0 +1,229 @@
+use crate::{
+    core::{Config, LocalAudio},
+    decode::{Spec, decode},
+    error::{AppError, AppResult},
+    progress::Task,
+};
+use ebur128::{EbuR128, Mode};
+use lofty::{
+    config::WriteOptions,
+    prelude::*,
+    tag::{ItemKey, Tag},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+use tracing::{info, warn};
+
+/// Target loudness of ReplayGain 2.0, in LUFS.
+pub const REFERENCE_LOUDNESS: f64 = -18.0;
+
+/// EBU R128 measurements of a file and the gains derived from them.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Loudness {
+    /// Integrated loudness, in LUFS
+    pub integrated: f64,
+    /// True peak, linear where 1.0 is full scale
+    pub true_peak: f64,
+    /// dB to reach [`REFERENCE_LOUDNESS`]
+    pub track_gain: f64,
+    /// dB to bring the playlist to [`REFERENCE_LOUDNESS`], keeping the
+    /// level differences between its tracks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album_gain: Option<f64>,
+    /// Highest true peak of the playlist
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub album_peak: Option<f64>,
+}
+
+impl Loudness {
+    /// Gain for playback in dB, lowered when needed so the peak does not clip.
+    pub fn gain(&self, album: bool) -> f64 {
+        let (gain, peak) = match (album, self.album_gain, self.album_peak) {
+            (true, Some(gain), Some(peak)) => (gain, peak),
+            _ => (self.track_gain, self.true_peak),
+        };
+        if peak > 0.0 {
+            gain.min(-20.0 * peak.log10())
+        } else {
+            gain
+        }
+    }
+}
+
+fn meter_error(e: ebur128::Error) -> AppError {
+    AppError::Transcode {
+        message: format!("Loudness measurement failed: {:?}", e),
+        source: None,
+    }
+}
+
+/// Measure the integrated loudness and true peak of a file.
+/// Blocking; run it on a blocking thread.
+pub fn analyze(path: &Path) -> AppResult<Loudness> {
+    let mut state: Option<(Spec, EbuR128)> = None;
+    decode(path, |spec, samples| {
+        let meter = match &mut state {
+            Some((current, meter)) if *current == spec => meter,
+            // A spec change mid-stream is rare enough to just start over
+            _ => {
+                let new = EbuR128::new(
+                    spec.channels as u32,
+                    spec.sample_rate,
+                    Mode::I | Mode::TRUE_PEAK,
+                )
+                .map_err(meter_error)?;
+                &mut state.insert((spec, new)).1
+            }
+        };
+        meter.add_frames_f32(samples).map_err(meter_error)
+    })?;
+
+    let (spec, meter) = state.ok_or_else(|| AppError::Transcode {
+        message: format!("No audio decoded from {}", path.display()),
+        source: None,
+    })?;
+    let integrated = meter.loudness_global().map_err(meter_error)?;
+    let mut true_peak: f64 = 0.0;
+    for channel in 0..spec.channels as u32 {
+        true_peak = true_peak.max(meter.true_peak(channel).map_err(meter_error)?);
+    }
+    if !integrated.is_finite() {
+        // Silence has no loudness to normalize
+        return Err(AppError::Transcode {
+            message: format!("{} is silent", path.display()),
+            source: None,
+        });
+    }
+
+    Ok(Loudness {
+        integrated,
+        true_peak,
+        track_gain: REFERENCE_LOUDNESS - integrated,
+        album_gain: None,
+        album_peak: None,
+    })
+}
+
+/// Album gain and peak of tracks given with their duration in seconds.
+///
+/// Loudness is averaged by energy weighted with duration, which is close to
+/// measuring the tracks as one stream without decoding them again.
+pub fn album(tracks: &[(Loudness, f64)]) -> Option<(f64, f64)> {
+    let total: f64 = tracks.iter().map(|(_, d)| d).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let energy: f64 = tracks
+        .iter()
+        .map(|(l, d)| d * 10f64.powf(l.integrated / 10.0))
+        .sum::<f64>()
+        / total;
+    let peak = tracks.iter().map(|(l, _)| l.true_peak).fold(0.0, f64::max);
+    Some((REFERENCE_LOUDNESS - 10.0 * energy.log10(), peak))
+}
+
+/// Result of [`analyze_config`], applied to the config by the frontend.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoudnessReport {
+    /// Newly measured files, by audio path
+    pub tracks: HashMap<String, Loudness>,
+    /// Album gain and peak by playlist id
+    pub albums: HashMap<String, (f64, f64)>,
+}
+
+fn duration(audio: &LocalAudio) -> f64 {
+    audio
+        .info
+        .as_ref()
+        .and_then(|i| i.duration)
+        .or(audio.audio.duration.map(|d| d as f64))
+        .unwrap_or(1.0)
+}
+
+/// Measure every audio of the config without loudness yet, then compute the
+/// album gain of each playlist. Files that cannot be decoded are skipped.
+/// Blocking; run it on a blocking thread.
+pub fn analyze_config(
+    config: &Config,
+    app_dir: &Path,
+    task: &mut Task,
+) -> AppResult<LoudnessReport> {
+    let mut seen = HashSet::new();
+    let pending: Vec<&LocalAudio> = config
+        .playlists
+        .iter()
+        .flat_map(|p| &p.audios)
+        .filter(|a| a.loudness.is_none() && seen.insert(a.path.as_str()))
+        .collect();
+    task.set_total(pending.len() as u64);
+
+    let mut report = LoudnessReport::default();
+    for audio in pending {
+        match analyze(&audio.file(app_dir)) {
+            Ok(loudness) => {
+                report.tracks.insert(audio.path.clone(), loudness);
+            }
+            Err(e) => warn!("Skipping loudness of '{}': {}", audio.audio.title, e),
+        }
+        task.advance(1, &audio.audio.title)?;
+    }
+
+    for playlist in &config.playlists {
+        let Some(id) = &playlist.id else {
+            continue;
+        };
+        let tracks: Vec<(Loudness, f64)> = playlist
+            .audios
+            .iter()
+            .filter_map(|a| {
+                let loudness = report.tracks.get(&a.path).copied().or(a.loudness)?;
+                Some((loudness, duration(a)))
+            })
+            .collect();
+        if let Some(album) = album(&tracks) {
+            report.albums.insert(id.clone(), album);
+        }
+    }
+
+    task.finish();
+    info!(
+        "Analyzed loudness of {} files across {} playlists",
+        report.tracks.len(),
+        report.albums.len()
+    );
+    Ok(report)
+}
+
+/// Write REPLAYGAIN_* tags into a file, creating a tag when it has none.
+/// Blocking; run it on a blocking thread.
+pub fn write_tags(path: &Path, loudness: &Loudness) -> AppResult<()> {
+    let tag_error = |e: lofty::error::LoftyError| {
+        AppError::parse(format!("Failed to tag {}", path.display()), e)
+    };
+    let mut tagged = lofty::read_from_path(path).map_err(tag_error)?;
+    let tag_type = tagged.primary_tag_type();
+    if tagged.primary_tag().is_none() {
+        tagged.insert_tag(Tag::new(tag_type));
+    }
+    let Some(tag) = tagged.primary_tag_mut() else {
+        return Ok(());
+    };
+
+    tag.insert_text(
+        ItemKey::ReplayGainTrackGain,
+        format!("{:.2} dB", loudness.track_gain),
+    );
+    tag.insert_text(
+        ItemKey::ReplayGainTrackPeak,
+        format!("{:.6}", loudness.true_peak),
+    );
+    if let (Some(gain), Some(peak)) = (loudness.album_gain, loudness.album_peak) {
+        tag.insert_text(ItemKey::ReplayGainAlbumGain, format!("{:.2} dB", gain));
+        tag.insert_text(ItemKey::ReplayGainAlbumPeak, format!("{:.6}", peak));
+    }
+    tag.save_to_path(path, WriteOptions::default())
+        .map_err(tag_error)
+}
@@ -0,0 +1,714 @@
+use crate::{
+    core::{ASSETS_DIR, LYRICS_DIR, LocalAudio},
+    error::{AppError, AppResult},
+};
+use lofty::{prelude::*, tag::ItemKey};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{future::Future, path::Path, sync::LazyLock};
+use tracing::{info, warn};
+
+/// A word of an enhanced LRC line, `<mm:ss.xx>word`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LyricWord {
+    pub time_ms: u64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LyricLine {
+    /// Zero for every line of unsynced lyrics
+    pub time_ms: u64,
+    pub text: String,
+    /// Word timings of enhanced LRC, empty otherwise
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<LyricWord>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lyrics {
+    pub lines: Vec<LyricLine>,
+    /// The `[offset:]` tag. Positive values show lines earlier
+    pub offset_ms: i64,
+    /// Whether the lines carry timestamps
+    pub synced: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+}
+
+impl Lyrics {
+    /// The lines with the offset applied to every timestamp.
+    pub fn shifted(mut self) -> Self {
+        let shift = |t: &mut u64| *t = t.saturating_add_signed(-self.offset_ms);
+        for line in &mut self.lines {
+            shift(&mut line.time_ms);
+            line.words.iter_mut().for_each(|w| shift(&mut w.time_ms));
+        }
+        self
+    }
+}
+
+static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\[([^\]]*)\]").unwrap());
+
+static TIME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\d+):(\d{1,2})(?:[.:](\d{1,3}))?$").unwrap());
+
+static WORD_TIME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<(\d+):(\d{1,2})(?:[.:](\d{1,3}))?>").unwrap());
+
+/// `mm`, `ss` and an optional fraction of a second, as captured by [`TIME`].
+fn time_ms(caps: &regex::Captures) -> u64 {
+    let part = |i: usize| {
+        caps.get(i)
+            .map_or(0, |p| p.as_str().parse::<u64>().unwrap_or(0))
+    };
+    // `.5` is tenths, `.50` hundredths and `.500` milliseconds
+    let fraction = caps
+        .get(3)
+        .map_or(0, |f| part(3) * 10u64.pow(3 - f.as_str().len() as u32));
+    (part(1) * 60 + part(2)) * 1000 + fraction
+}
+
+/// Split an enhanced LRC line into its words. Text before the first word
+/// timestamp belongs to the line start.
+fn parse_words(text: &str, line_ms: u64) -> (String, Vec<LyricWord>) {
+    if !WORD_TIME.is_match(text) {
+        return (text.trim().to_string(), Vec::new());
+    }
+    let mut words = Vec::new();
+    let mut time = line_ms;
+    let mut last = 0;
+    for caps in WORD_TIME.captures_iter(text) {
+        let whole = caps.get(0).unwrap();
+        let word = &text[last..whole.start()];
+        if !word.trim().is_empty() {
+            words.push(LyricWord {
+                time_ms: time,
+                text: word.to_string(),
+            });
+        }
+        time = time_ms(&caps);
+        last = whole.end();
+    }
+    if !text[last..].trim().is_empty() {
+        words.push(LyricWord {
+            time_ms: time,
+            text: text[last..].to_string(),
+        });
+    }
+    let line = words.iter().map(|w| w.text.as_str()).collect::<String>();
+    (line.trim().to_string(), words)
+}
+
+/// Read LRC and enhanced LRC. Several timestamps may share a line, and
+/// text without any timestamp is read as unsynced lyrics.
+pub fn parse_lrc(text: &str) -> Lyrics {
+    let mut lyrics = Lyrics::default();
+    let mut plain = Vec::new();
+    for raw in text.trim_start_matches('\u{feff}').lines() {
+        let mut rest = raw.trim();
+        let mut times = Vec::new();
+        while let Some(caps) = TAG.captures(rest) {
+            let tag = caps[1].trim();
+            rest = &rest[caps[0].len()..];
+            if let Some(time) = TIME.captures(tag) {
+                times.push(time_ms(&time));
+                continue;
+            }
+            let Some((key, value)) = tag.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "offset" => lyrics.offset_ms = value.trim_start_matches('+').parse().unwrap_or(0),
+                "ti" if !value.is_empty() => lyrics.title = Some(value.to_string()),
+                "ar" if !value.is_empty() => lyrics.artist = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        if times.is_empty() {
+            // Lines made of tags only are metadata, not lyrics
+            if !rest.is_empty() && !raw.trim_start().starts_with('[') {
+                plain.push(rest.to_string());
+            }
+            continue;
+        }
+        for time in times {
+            let (text, words) = parse_words(rest, time);
+            lyrics.lines.push(LyricLine {
+                time_ms: time,
+                text,
+                words,
+            });
+        }
+    }
+
+    lyrics.synced = !lyrics.lines.is_empty();
+    if lyrics.synced {
+        lyrics.lines.sort_by_key(|l| l.time_ms);
+    } else {
+        lyrics.lines = plain
+            .into_iter()
+            .map(|text| LyricLine {
+                time_ms: 0,
+                text,
+                words: Vec::new(),
+            })
+            .collect();
+    }
+    lyrics
+}
+
+/// Put `offset_ms` into LRC text, replacing any `[offset:]` tag, and keep
+/// everything else as it is.
+pub fn with_offset(text: &str, offset_ms: i64) -> String {
+    let mut out = String::new();
+    if offset_ms != 0 {
+        out.push_str(&format!("[offset:{:+}]\n", offset_ms));
+    }
+    for line in text.trim_start_matches('\u{feff}').lines() {
+        let is_offset = line
+            .trim()
+            .strip_prefix('[')
+            .is_some_and(|l| l.trim_start().to_ascii_lowercase().starts_with("offset:"));
+        if !is_offset {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// A source of lyrics for an audio, as LRC or plain text.
+///
+/// Commands take providers as a type parameter, so any of them can be
+/// replaced by one that answers from memory.
+pub trait LyricsProvider {
+    fn name(&self) -> &'static str;
+
+    fn fetch(
+        &self,
+        audio: &LocalAudio,
+        app_dir: &Path,
+    ) -> impl Future<Output = AppResult<Option<String>>> + Send;
+}
+
+/// Lyrics embedded in the tags of the file itself.
+pub struct Embedded;
+
+/// The `LYRICS`/`USLT` tag of a file, if any.
+/// Blocking; run it on a blocking thread.
+pub fn read_embedded(path: &Path) -> Option<String> {
+    let tagged = lofty::read_from_path(path).ok()?;
+    tagged
+        .primary_tag()
+        .into_iter()
+        .chain(tagged.tags())
+        .find_map(|tag| tag.get_string(&ItemKey::Lyrics))
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+}
+
+impl LyricsProvider for Embedded {
+    fn name(&self) -> &'static str {
+        "embedded"
+    }
+
+    async fn fetch(&self, audio: &LocalAudio, app_dir: &Path) -> AppResult<Option<String>> {
+        let path = audio.file(app_dir);
+        tokio::task::spawn_blocking(move || read_embedded(&path))
+            .await
+            .map_err(|e| AppError::Unknown(e.to_string()))
+    }
+}
+
+/// Search of the public LRCLIB database.
+pub struct Lrclib {
+    client: reqwest::Client,
+}
+
+impl Default for Lrclib {
+    fn default() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LrclibTrack {
+    duration: Option<f64>,
+    plain_lyrics: Option<String>,
+    synced_lyrics: Option<String>,
+}
+
+/// Results further than this from the audio's duration are other recordings.
+const DURATION_TOLERANCE_SECS: f64 = 3.0;
+
+impl LyricsProvider for Lrclib {
+    fn name(&self) -> &'static str {
+        "lrclib"
+    }
+
+    async fn fetch(&self, audio: &LocalAudio, _app_dir: &Path) -> AppResult<Option<String>> {
+        let search = async {
+            self.client
+                .get("https://lrclib.net/api/search")
+                .query(&[("q", audio.audio.title.as_str())])
+                .header("User-Agent", "musicfree-tauri")
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Vec<LrclibTrack>>()
+                .await
+        };
+        let tracks = search
+            .await
+            .map_err(|e| AppError::network("LRCLIB search failed", e))?;
+
+        let duration = audio
+            .info
+            .as_ref()
+            .and_then(|i| i.duration)
+            .or(audio.audio.duration.map(|d| d as f64));
+        let fits = |t: &&LrclibTrack| match (duration, t.duration) {
+            (Some(a), Some(b)) => (a - b).abs() <= DURATION_TOLERANCE_SECS,
+            _ => true,
+        };
+        // Synced lyrics of the right length first, then plain ones
+        let synced = tracks
+            .iter()
+            .filter(fits)
+            .find_map(|t| t.synced_lyrics.clone());
+        let plain = || {
+            tracks
+                .iter()
+                .filter(fits)
+                .find_map(|t| t.plain_lyrics.clone())
+        };
+        Ok(synced.or_else(plain).filter(|l| !l.trim().is_empty()))
+    }
+}
+
+/// The built-in providers, so the list can be passed around as one type.
+pub enum Provider {
+    Embedded(Embedded),
+    Captions(crate::subtitles::Captions),
+    Lrclib(Lrclib),
+}
+
+impl LyricsProvider for Provider {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Embedded(p) => p.name(),
+            Self::Captions(p) => p.name(),
+            Self::Lrclib(p) => p.name(),
+        }
+    }
+
+    async fn fetch(&self, audio: &LocalAudio, app_dir: &Path) -> AppResult<Option<String>> {
+        match self {
+            Self::Embedded(p) => p.fetch(audio, app_dir).await,
+            Self::Captions(p) => p.fetch(audio, app_dir).await,
+            Self::Lrclib(p) => p.fetch(audio, app_dir).await,
+        }
+    }
+}
+
+/// What `attach_lyrics` asks, in order: the tags of the file, the captions
+/// of the video, then LRCLIB.
+pub fn default_providers() -> Vec<Provider> {
+    vec![
+        Provider::Embedded(Embedded),
+        Provider::Captions(crate::subtitles::Captions::default()),
+        Provider::Lrclib(Lrclib::default()),
+    ]
+}
+
+/// `assets/{Platform}/lyrics/{id}.lrc`
+pub fn lyrics_path(audio: &LocalAudio) -> String {
+    format!(
+        "{}/{:?}/{}/{}.lrc",
+        ASSETS_DIR,
+        audio.audio.platform,
+        LYRICS_DIR,
+        sanitize_filename::sanitize(&audio.audio.id)
+    )
+}
+
+/// Write lyrics into the store and point the audio at them.
+pub async fn store(audio: &LocalAudio, text: &str, app_dir: &Path) -> AppResult<LocalAudio> {
+    let path = lyrics_path(audio);
+    let dest = app_dir.join(&path);
+    if let Some(parent) = dest.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(&dest, text).await?;
+    Ok(LocalAudio {
+        lyrics_path: Some(path),
+        ..audio.clone()
+    })
+}
+
+/// Fetch lyrics from `provider` and store them. `None` when it has none.
+pub async fn attach<P: LyricsProvider>(
+    provider: &P,
+    audio: &LocalAudio,
+    app_dir: &Path,
+) -> AppResult<Option<LocalAudio>> {
+    let Some(text) = provider.fetch(audio, app_dir).await? else {
+        return Ok(None);
+    };
+    if parse_lrc(&text).lines.is_empty() {
+        warn!(
+            "Ignoring empty lyrics from {} for '{}'",
+            provider.name(),
+            audio.audio.title
+        );
+        return Ok(None);
+    }
+    info!(
+        "Attached lyrics from {} to '{}'",
+        provider.name(),
+        audio.audio.title
+    );
+    store(audio, &text, app_dir).await.map(Some)
+}
+
+/// Ask `providers` in order and attach the first lyrics found. A provider
+/// that fails is logged and the next one asked. `None` when none has any;
+/// fails only when none had lyrics and one of them failed, so an offline
+/// lookup is not taken for missing lyrics.
+pub async fn attach_first<P: LyricsProvider>(
+    providers: &[P],
+    audio: &LocalAudio,
+    app_dir: &Path,
+) -> AppResult<Option<LocalAudio>> {
+    let mut failure = None;
+    for provider in providers {
+        match attach(provider, audio, app_dir).await {
+            Ok(Some(attached)) => return Ok(Some(attached)),
+            Ok(None) => {}
+            Err(e) => {
+                warn!(
+                    "{} lyrics lookup failed for '{}': {}",
+                    provider.name(),
+                    audio.audio.title,
+                    e
+                );
+                failure.get_or_insert(e);
+            }
+        }
+    }
+    failure.map_or(Ok(None), Err)
+}
+
+/// The raw LRC text of an audio: its stored lyrics, or else the ones
+/// embedded in the file.
+pub async fn read_text(audio: &LocalAudio, app_dir: &Path) -> AppResult<Option<String>> {
+    if let Some(path) = &audio.lyrics_path {
+        match tokio::fs::read(app_dir.join(path)).await {
+            Ok(bytes) => return Ok(Some(String::from_utf8_lossy(&bytes).into_owned())),
+            Err(e) => warn!("Stored lyrics {} are unreadable: {}", path, e),
+        }
+    }
+    Embedded.fetch(audio, app_dir).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use musicfree::{Audio, Platform};
+    use std::path::PathBuf;
+
+    fn line(time_ms: u64, text: &str) -> LyricLine {
+        LyricLine {
+            time_ms,
+            text: text.to_string(),
+            words: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parses_synced_lines_and_tags() {
+        let lyrics = parse_lrc(
+            "\u{feff}[ti: Song]\n[ar:Singer]\n[offset:+250]\n\
+             [00:12.5]second\n[00:01.00][01:00.123]first and again\n",
+        );
+        assert!(lyrics.synced);
+        assert_eq!(lyrics.title.as_deref(), Some("Song"));
+        assert_eq!(lyrics.artist.as_deref(), Some("Singer"));
+        assert_eq!(lyrics.offset_ms, 250);
+        assert_eq!(
+            lyrics.lines,
+            vec![
+                line(1_000, "first and again"),
+                line(12_500, "second"),
+                line(60_123, "first and again"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_text_without_timestamps_as_unsynced() {
+        let lyrics = parse_lrc("[ar:Singer]\nfirst\n\nsecond");
+        assert!(!lyrics.synced);
+        assert_eq!(lyrics.lines, vec![line(0, "first"), line(0, "second")]);
+    }
+
+    #[test]
+    fn parses_enhanced_word_timings() {
+        let lyrics = parse_lrc("[00:01.00]<00:01.00>Hello <00:01.50>world");
+        let line = &lyrics.lines[0];
+        assert_eq!(line.text, "Hello world");
+        assert_eq!(
+            line.words,
+            vec![
+                LyricWord {
+                    time_ms: 1_000,
+                    text: "Hello ".to_string(),
+                },
+                LyricWord {
+                    time_ms: 1_500,
+                    text: "world".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn with_offset_replaces_the_offset_tag() {
+        let text = "[ti:Song]\n[offset:-100]\n[00:01.00]line\n";
+        assert_eq!(
+            with_offset(text, 300),
+            "[offset:+300]\n[ti:Song]\n[00:01.00]line\n"
+        );
+        assert_eq!(with_offset(text, 0), "[ti:Song]\n[00:01.00]line\n");
+        assert_eq!(parse_lrc(&with_offset(text, -50)).offset_ms, -50);
+    }
+
+    #[test]
+    fn shifted_applies_the_offset_and_stops_at_zero() {
+        let lyrics = parse_lrc("[offset:500]\n[00:00.20]early\n[00:02.00]<00:02.00>a <00:03.00>b");
+        let shifted = lyrics.shifted();
+        assert_eq!(shifted.lines[0].time_ms, 0);
+        assert_eq!(shifted.lines[1].time_ms, 1_500);
+        assert_eq!(shifted.lines[1].words[1].time_ms, 2_500);
+
+        let late = parse_lrc("[offset:-500]\n[00:01.00]late").shifted();
+        assert_eq!(late.lines[0].time_ms, 1_500);
+    }
+
+    /// Answers from memory, the way a test replaces the network.
+    enum Memory {
+        Lyrics(&'static str),
+        Nothing,
+        Offline,
+    }
+
+    impl LyricsProvider for Memory {
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+
+        async fn fetch(&self, _audio: &LocalAudio, _app_dir: &Path) -> AppResult<Option<String>> {
+            match self {
+                Memory::Lyrics(text) => Ok(Some(text.to_string())),
+                Memory::Nothing => Ok(None),
+                Memory::Offline => Err(AppError::Network {
+                    message: "offline".to_string(),
+                    source: None,
+                }),
+            }
+        }
+    }
+
+    fn audio(id: &str) -> LocalAudio {
+        LocalAudio {
+            path: format!("assets/Youtube/audios/{id}.mp3"),
+            external_path: None,
+            cover_path: None,
+            audio: Audio {
+                id: id.to_string(),
+                title: "Song".to_string(),
+                download_url: String::new(),
+                cover: None,
+                platform: Platform::Youtube,
+                duration: None,
+                format: None,
+            },
+            info: None,
+            loudness: None,
+            start_ms: None,
+            end_ms: None,
+            lyrics_path: None,
+            fingerprint: None,
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("musicfree-lyrics-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[tokio::test]
+    async fn attach_first_stores_the_first_lyrics_found() {
+        let app_dir = temp_dir("attach");
+        let providers = [
+            Memory::Nothing,
+            Memory::Lyrics("   "),
+            Memory::Lyrics("[00:01.00]hi"),
+        ];
+        let attached = attach_first(&providers, &audio("abc"), &app_dir)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let path = attached.lyrics_path.unwrap();
+        assert_eq!(path, "assets/Youtube/lyrics/abc.lrc");
+        assert_eq!(
+            std::fs::read_to_string(app_dir.join(&path)).unwrap(),
+            "[00:01.00]hi"
+        );
+        let _ = std::fs::remove_dir_all(&app_dir);
+    }
+
+    #[tokio::test]
+    async fn attach_first_is_none_without_lyrics() {
+        let app_dir = temp_dir("none");
+        let providers = [Memory::Nothing, Memory::Lyrics("")];
+        let attached = attach_first(&providers, &audio("abc"), &app_dir)
+            .await
+            .unwrap();
+        assert!(attached.is_none());
+        assert!(!app_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn attach_first_asks_the_next_provider_after_a_failure() {
+        let app_dir = temp_dir("failure");
+        let providers = [Memory::Offline, Memory::Lyrics("[00:02.00]still here")];
+        let attached = attach_first(&providers, &audio("abc"), &app_dir)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            attached.lyrics_path.as_deref(),
+            Some("assets/Youtube/lyrics/abc.lrc")
+        );
+        let _ = std::fs::remove_dir_all(&app_dir);
+
+        let providers = [Memory::Offline, Memory::Nothing];
+        let err = attach_first(&providers, &audio("abc"), &app_dir)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), crate::error::ErrorKind::Network);
+        assert!(!app_dir.exists());
+    }
+
+    fn cue(start_ms: u64, end_ms: u64, text: &str) -> crate::subtitles::Cue {
+        crate::subtitles::Cue {
+            start_ms,
+            end_ms,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn converts_srt_cues() {
+        let cues = crate::subtitles::parse_srt(
+            "\u{feff}1\r\n00:00:01,500 --> 00:00:03,000\r\n<i>Hello</i> &amp; world\r\n\r\n\
+             2\r\n00:01:02,000 --> 00:01:04,250\r\nSecond\r\nline\r\n",
+        );
+        assert_eq!(
+            cues,
+            vec![
+                cue(1_500, 3_000, "Hello & world"),
+                cue(62_000, 64_250, "Second line"),
+            ]
+        );
+    }
+
+    #[test]
+    fn merges_rolling_vtt_cues() {
+        let cues = crate::subtitles::parse_vtt(
+            "WEBVTT\nKind: captions\n\nNOTE a comment\n\n\
+             00:01.000 --> 00:02.000 align:start\n<c.colorE5E5E5>la la</c>\n\n\
+             00:02.000 --> 00:03.500\nla la\n\n\
+             00:00:04.000 --> 00:00:05.000\n{\\an8}next\n",
+        );
+        assert_eq!(
+            cues,
+            vec![cue(1_000, 3_500, "la la"), cue(4_000, 5_000, "next")]
+        );
+    }
+
+    #[test]
+    fn converts_bilibili_subtitles() {
+        let cues = crate::subtitles::parse_bilibili(
+            r#"{"font_size":0.4,"body":[
+                {"from":0.5,"to":2.25,"content":"first\nline"},
+                {"from":3,"to":4,"content":"  "},
+                {"from":4.0,"to":6.0,"content":"second"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            cues,
+            vec![cue(500, 2_250, "first line"), cue(4_000, 6_000, "second")]
+        );
+        assert!(crate::subtitles::parse_bilibili("{\"body\": 1}").is_err());
+    }
+
+    #[test]
+    fn writes_cues_as_lrc_with_gap_lines() {
+        let lrc = crate::subtitles::cues_to_lrc(&[
+            cue(65_000, 66_000, "c"),
+            cue(500, 2_250, "a"),
+            cue(2_500, 3_900, "b"),
+        ]);
+        assert_eq!(
+            lrc,
+            "[00:00.50]a\n[00:02.50]b\n[00:03.90]\n[01:05.00]c\n[01:06.00]\n"
+        );
+        let lyrics = parse_lrc(&lrc);
+        assert!(lyrics.synced);
+        assert_eq!(lyrics.lines[0], line(500, "a"));
+    }
+
+    #[test]
+    fn detects_subtitle_formats() {
+        use crate::subtitles::{SubtitleFormat, to_lrc};
+        use std::path::Path;
+
+        let detect = SubtitleFormat::detect;
+        assert_eq!(detect(Path::new("a.SRT"), ""), Some(SubtitleFormat::Srt));
+        assert_eq!(
+            detect(Path::new("a.txt"), "\u{feff}WEBVTT\n"),
+            Some(SubtitleFormat::Vtt)
+        );
+        assert_eq!(
+            detect(Path::new("a.json"), "{\"body\": []}"),
+            Some(SubtitleFormat::Bilibili)
+        );
+        assert_eq!(
+            detect(Path::new("a"), "1\n00:00:01,000 --> 00:00:02,000\nhi\n"),
+            Some(SubtitleFormat::Srt)
+        );
+        assert_eq!(detect(Path::new("a.lrc"), "[00:01.00]hi"), None);
+
+        let err = to_lrc("WEBVTT\n\n", SubtitleFormat::Vtt).unwrap_err();
+        assert_eq!(err.kind(), crate::error::ErrorKind::NotFound);
+        assert_eq!(
+            to_lrc("00:01.000 --> 00:02.000\nhi\n", SubtitleFormat::Vtt).unwrap(),
+            "[00:01.00]hi\n[00:02.00]\n"
+        );
+    }
+}
@@ -0,0 +1,727 @@
+use crate::{
+    api,
+    core::{LocalAudio, LocalPlaylist},
+    error::{AppError, AppResult},
+    progress::Task,
+};
+use musicfree::Platform;
+use quick_xml::{Reader, events::Event};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Playlist file formats understood by [`parse_file`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaylistFormat {
+    /// M3U and M3U8, with or without `#EXTM3U` headers
+    M3u,
+    Xspf,
+    Pls,
+    /// Backup JSON of the MusicFree mobile app
+    MusicFree,
+    /// Plain JSON written by [`write_playlist`]
+    Json,
+}
+
+impl PlaylistFormat {
+    /// Guess the format from the extension, falling back to the content.
+    pub fn detect(path: &Path, content: &str) -> Option<Self> {
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("m3u" | "m3u8") => return Some(Self::M3u),
+            Some("xspf") => return Some(Self::Xspf),
+            Some("pls") => return Some(Self::Pls),
+            _ => {}
+        }
+        let head = content.trim_start();
+        if head.starts_with('{') && content.contains("\"musicSheets\"") {
+            Some(Self::MusicFree)
+        } else if head.starts_with('{') {
+            Some(Self::Json)
+        } else if head.starts_with("<?xml") || head.starts_with("<playlist") {
+            Some(Self::Xspf)
+        } else if head.starts_with("[playlist]") {
+            Some(Self::Pls)
+        } else if head.starts_with("#EXTM3U") {
+            Some(Self::M3u)
+        } else {
+            None
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::M3u => "m3u8",
+            Self::Xspf => "xspf",
+            Self::Pls => "pls",
+            Self::MusicFree | Self::Json => "json",
+        }
+    }
+}
+
+/// One track of a playlist file. Parsed entries hold an absolute path or a
+/// URL; written entries a path relative to the playlist file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+    pub location: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+}
+
+/// Also the layout of [`PlaylistFormat::Json`] files.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ParsedPlaylist {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, rename = "tracks")]
+    pub entries: Vec<PlaylistEntry>,
+}
+
+fn is_url(location: &str) -> bool {
+    location.starts_with("http://") || location.starts_with("https://")
+}
+
+/// Turn a playlist location into a URL or an absolute path. Relative paths
+/// are resolved against the directory of the playlist file.
+fn resolve_location(location: &str, base_dir: &Path) -> String {
+    let location = location.trim();
+    if is_url(location) {
+        return location.to_string();
+    }
+    if location.starts_with("file:")
+        && let Ok(url) = reqwest::Url::parse(location)
+        && let Ok(path) = url.to_file_path()
+    {
+        return path.to_string_lossy().to_string();
+    }
+    let path = Path::new(location);
+    if path.is_absolute() {
+        location.to_string()
+    } else {
+        base_dir.join(path).to_string_lossy().to_string()
+    }
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+/// Parse M3U/M3U8. `#EXTINF:<seconds>,<title>` lines describe the next entry.
+pub fn parse_m3u(content: &str, base_dir: &Path) -> ParsedPlaylist {
+    let mut playlist = ParsedPlaylist::default();
+    let mut pending = PlaylistEntry::default();
+
+    for line in content.lines() {
+        let line = line.trim_start_matches('\u{feff}').trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            let (duration, title) = info.split_once(',').unwrap_or((info, ""));
+            // Attributes such as `tvg-id="..."` may follow the duration
+            let duration = duration.split_whitespace().next().unwrap_or_default();
+            pending.duration = duration.parse::<f64>().ok().filter(|d| *d >= 0.0);
+            pending.title = non_empty(title);
+        } else if let Some(title) = line.strip_prefix("#PLAYLIST:") {
+            playlist.title = non_empty(title);
+        } else if !line.starts_with('#') {
+            pending.location = resolve_location(line, base_dir);
+            playlist.entries.push(std::mem::take(&mut pending));
+        }
+    }
+    playlist
+}
+
+/// Parse PLS (`File1=`, `Title1=`, `Length1=` keys of a `[playlist]` section).
+pub fn parse_pls(content: &str, base_dir: &Path) -> ParsedPlaylist {
+    let mut entries: std::collections::BTreeMap<u32, PlaylistEntry> = Default::default();
+
+    for line in content.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+        let (field, index) = if let Some(i) = key.strip_prefix("file") {
+            ("file", i)
+        } else if let Some(i) = key.strip_prefix("title") {
+            ("title", i)
+        } else if let Some(i) = key.strip_prefix("length") {
+            ("length", i)
+        } else {
+            continue;
+        };
+        let Ok(index) = index.parse::<u32>() else {
+            continue;
+        };
+        let entry = entries.entry(index).or_default();
+        match field {
+            "file" => entry.location = resolve_location(value, base_dir),
+            "title" => entry.title = non_empty(value),
+            // -1 marks streams of unknown length
+            _ => entry.duration = value.parse::<f64>().ok().filter(|d| *d >= 0.0),
+        }
+    }
+
+    ParsedPlaylist {
+        title: None,
+        entries: entries
+            .into_values()
+            .filter(|e| !e.location.is_empty())
+            .collect(),
+    }
+}
+
+/// Parse XSPF. Only `title`, `location` and `duration` (milliseconds) of each
+/// track are used.
+pub fn parse_xspf(content: &str, base_dir: &Path) -> AppResult<ParsedPlaylist> {
+    let mut reader = Reader::from_str(content);
+    reader.config_mut().trim_text(true);
+
+    let mut playlist = ParsedPlaylist::default();
+    let mut path: Vec<String> = Vec::new();
+    let mut track: Option<PlaylistEntry> = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+                if name == "track" {
+                    track = Some(PlaylistEntry::default());
+                }
+                path.push(name);
+            }
+            Ok(Event::End(_)) => {
+                if path.pop().as_deref() == Some("track")
+                    && let Some(entry) = track.take()
+                    && !entry.location.is_empty()
+                {
+                    playlist.entries.push(entry);
+                }
+            }
+            Ok(Event::Text(e)) => {
+                let text = e
+                    .unescape()
+                    .map_err(|e| AppError::parse("Invalid XSPF", e))?;
+                let parent = path.len().checked_sub(2).map(|i| path[i].as_str());
+                match (parent, path.last().map(String::as_str), track.as_mut()) {
+                    (Some("track"), Some("location"), Some(entry)) if entry.location.is_empty() => {
+                        let location = if text.contains(':') {
+                            text.to_string()
+                        } else {
+                            decode_uri_path(&text)
+                        };
+                        entry.location = resolve_location(&location, base_dir);
+                    }
+                    (Some("track"), Some("title"), Some(entry)) => entry.title = non_empty(&text),
+                    (Some("track"), Some("duration"), Some(entry)) => {
+                        entry.duration = text.trim().parse::<f64>().ok().map(|ms| ms / 1000.0);
+                    }
+                    (Some("playlist"), Some("title"), None) => playlist.title = non_empty(&text),
+                    _ => {}
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(AppError::parse("Invalid XSPF", e)),
+            _ => {}
+        }
+    }
+    Ok(playlist)
+}
+
+fn str_field<'a>(value: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .find_map(|k| value.get(*k).and_then(Value::as_str))
+        .filter(|s| !s.is_empty())
+}
+
+/// A URL the extractors understand for an item of the MusicFree app, which
+/// stores plugin specific ids rather than URLs.
+fn musicfree_item_url(item: &Value) -> Option<String> {
+    if let Some(url) = str_field(item, &["url", "webUrl", "link"])
+        && is_url(url)
+    {
+        return Some(url.to_string());
+    }
+    let platform = str_field(item, &["platform"])?.to_ascii_lowercase();
+    if platform.contains("bilibili") {
+        let bvid = str_field(item, &["bvid"])?;
+        Some(format!("https://www.bilibili.com/video/{}", bvid))
+    } else if platform.contains("youtube") {
+        let id = str_field(item, &["id"])?;
+        Some(format!("https://www.youtube.com/watch?v={}", id))
+    } else {
+        None
+    }
+}
+
+/// Parse the backup of the MusicFree mobile app: `musicSheets`, each with a
+/// `musicList`. Items without a resolvable URL keep an empty location and
+/// are reported as failed.
+pub fn parse_musicfree_backup(content: &str) -> AppResult<Vec<ParsedPlaylist>> {
+    let backup: Value = serde_json::from_str(content)?;
+    let sheets = backup
+        .get("musicSheets")
+        .and_then(Value::as_array)
+        .ok_or_else(|| AppError::Parse {
+            message: "Not a MusicFree backup: no musicSheets".to_string(),
+            source: None,
+        })?;
+
+    Ok(sheets
+        .iter()
+        .map(|sheet| ParsedPlaylist {
+            title: str_field(sheet, &["title", "name"]).map(str::to_string),
+            entries: sheet
+                .get("musicList")
+                .and_then(Value::as_array)
+                .map(|items| {
+                    items
+                        .iter()
+                        .map(|item| PlaylistEntry {
+                            location: musicfree_item_url(item).unwrap_or_default(),
+                            title: str_field(item, &["title"]).map(str::to_string),
+                            duration: item.get("duration").and_then(Value::as_f64),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+        .collect())
+}
+
+/// Decode a playlist file. M3U and PLS files from Windows players are
+/// often in the ANSI code page rather than UTF-8: without a BOM, text that
+/// is not UTF-8 is read as GBK when it is valid GBK, else as Windows-1252.
+fn decode_text(bytes: &[u8]) -> String {
+    if let Some((encoding, _)) = encoding_rs::Encoding::for_bom(bytes) {
+        return encoding.decode_with_bom_removal(bytes).0.into_owned();
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return text.to_string();
+    }
+    if let Some(text) = encoding_rs::GBK.decode_without_bom_handling_and_without_replacement(bytes)
+    {
+        return text.into_owned();
+    }
+    encoding_rs::WINDOWS_1252
+        .decode_without_bom_handling(bytes)
+        .0
+        .into_owned()
+}
+
+/// Parse a playlist file of any supported format.
+pub fn parse_file(path: &Path) -> AppResult<(PlaylistFormat, Vec<ParsedPlaylist>)> {
+    let content = decode_text(&std::fs::read(path)?);
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let format = PlaylistFormat::detect(path, &content).ok_or_else(|| {
+        AppError::Invalid(format!("Unsupported playlist file {}", path.display()))
+    })?;
+
+    let mut playlists = match format {
+        PlaylistFormat::M3u => vec![parse_m3u(&content, base_dir)],
+        PlaylistFormat::Xspf => vec![parse_xspf(&content, base_dir)?],
+        PlaylistFormat::Pls => vec![parse_pls(&content, base_dir)],
+        PlaylistFormat::MusicFree => parse_musicfree_backup(&content)?,
+        PlaylistFormat::Json => {
+            let mut playlist: ParsedPlaylist = serde_json::from_str(&content)?;
+            for entry in &mut playlist.entries {
+                entry.location = resolve_location(&entry.location, base_dir);
+            }
+            vec![playlist]
+        }
+    };
+    // Name single playlists after the file when they carry no title
+    if playlists.len() == 1 && playlists[0].title.is_none() {
+        playlists[0].title = path.file_stem().map(|s| s.to_string_lossy().to_string());
+    }
+    Ok((format, playlists))
+}
+
+/// Decode `%XX` escapes of a relative URI reference.
+fn decode_uri_path(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(hex) = path.get(i + 1..i + 3)
+            && let Ok(b) = u8::from_str_radix(hex, 16)
+        {
+            out.push(b);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Percent-encode a relative path for use as a URI reference.
+fn encode_uri_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for b in path.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+fn escape_xml(s: &str) -> String {
+    quick_xml::escape::escape(s).to_string()
+}
+
+/// M3U8 with `#EXTINF` lines. Unknown durations are written as `-1`.
+pub fn write_m3u(playlist: &ParsedPlaylist) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    if let Some(title) = &playlist.title {
+        out.push_str(&format!("#PLAYLIST:{}\n", title));
+    }
+    for entry in &playlist.entries {
+        let duration = entry.duration.map(|d| d.round() as i64).unwrap_or(-1);
+        let title = entry.title.as_deref().unwrap_or_default();
+        out.push_str(&format!(
+            "#EXTINF:{},{}\n{}\n",
+            duration, title, entry.location
+        ));
+    }
+    out
+}
+
+pub fn write_pls(playlist: &ParsedPlaylist) -> String {
+    let mut out = String::from("[playlist]\n");
+    for (i, entry) in playlist.entries.iter().enumerate() {
+        let n = i + 1;
+        out.push_str(&format!("File{}={}\n", n, entry.location));
+        if let Some(title) = &entry.title {
+            out.push_str(&format!("Title{}={}\n", n, title));
+        }
+        let duration = entry.duration.map(|d| d.round() as i64).unwrap_or(-1);
+        out.push_str(&format!("Length{}={}\n", n, duration));
+    }
+    out.push_str(&format!(
+        "NumberOfEntries={}\nVersion=2\n",
+        playlist.entries.len()
+    ));
+    out
+}
+
+/// XSPF with relative, percent-encoded locations and durations in milliseconds.
+pub fn write_xspf(playlist: &ParsedPlaylist) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n",
+    );
+    if let Some(title) = &playlist.title {
+        out.push_str(&format!("  <title>{}</title>\n", escape_xml(title)));
+    }
+    out.push_str("  <trackList>\n");
+    for entry in &playlist.entries {
+        out.push_str("    <track>\n");
+        out.push_str(&format!(
+            "      <location>{}</location>\n",
+            escape_xml(&encode_uri_path(&entry.location))
+        ));
+        if let Some(title) = &entry.title {
+            out.push_str(&format!("      <title>{}</title>\n", escape_xml(title)));
+        }
+        if let Some(duration) = entry.duration {
+            out.push_str(&format!(
+                "      <duration>{}</duration>\n",
+                (duration * 1000.0).round() as u64
+            ));
+        }
+        out.push_str("    </track>\n");
+    }
+    out.push_str("  </trackList>\n</playlist>\n");
+    out
+}
+
+/// Serialize a playlist in `format`.
+pub fn write_playlist(playlist: &ParsedPlaylist, format: PlaylistFormat) -> AppResult<String> {
+    match format {
+        PlaylistFormat::M3u => Ok(write_m3u(playlist)),
+        PlaylistFormat::Xspf => Ok(write_xspf(playlist)),
+        PlaylistFormat::Pls => Ok(write_pls(playlist)),
+        PlaylistFormat::Json => Ok(serde_json::to_string_pretty(playlist)?),
+        PlaylistFormat::MusicFree => Err(AppError::Invalid(
+            "Exporting MusicFree mobile backups is not supported".to_string(),
+        )),
+    }
+}
+
+/// Result of [`import_file`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistImport {
+    pub format: PlaylistFormat,
+    pub playlists: Vec<LocalPlaylist>,
+    /// Entries that could not be resolved, as `title (location)`
+    pub failed: Vec<String>,
+}
+
+/// Copy a local file into `assets/File/` and describe it as a [`LocalAudio`].
+async fn import_local(entry: &PlaylistEntry, app_dir: &Path) -> AppResult<LocalAudio> {
+    let source = PathBuf::from(&entry.location);
+    let (title, duration) = (entry.title.clone(), entry.duration);
+    let app_dir = app_dir.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        crate::library::local_audio(&source, &app_dir, true, title.as_deref(), duration)
+    })
+    .await
+    .map_err(|e| AppError::Unknown(e.to_string()))?
+}
+
+/// Resolve a URL through the extractors and download the matching audios.
+async fn import_url(entry: &PlaylistEntry, app_dir: &Path) -> AppResult<Vec<LocalAudio>> {
+    let (playlist, index) = musicfree::extract(&entry.location)
+        .await
+        .map_err(|e| AppError::extractor(format!("Failed to extract {}", entry.location), e))?;
+    let audios = match index {
+        Some(i) => playlist.audios.get(i).cloned().into_iter().collect(),
+        None => playlist.audios,
+    };
+    let mut local = Vec::with_capacity(audios.len());
+    for audio in &audios {
+        local.push(api::download_audio(audio, app_dir.to_path_buf()).await?);
+    }
+    Ok(local)
+}
+
+/// Platform of a playlist holding `audios`: the one they share, or `File`
+/// for local files and playlists mixing platforms.
+fn playlist_platform(audios: &[LocalAudio]) -> Platform {
+    let Some(first) = audios.first().map(|a| a.audio.platform) else {
+        return Platform::File;
+    };
+    // Platforms compare by name, as in the asset paths
+    let name = format!("{:?}", first);
+    if audios
+        .iter()
+        .all(|a| format!("{:?}", a.audio.platform) == name)
+    {
+        first
+    } else {
+        Platform::File
+    }
+}
+
+/// Parse a playlist file and turn its entries into [`LocalPlaylist`]s.
+/// Local files are copied into `assets/File/`, URLs are resolved through
+/// the extractors and downloaded; URLs no extractor takes, such as direct
+/// streams, fail. Entries that fail are skipped and listed in the result.
+pub async fn import_file(
+    path: &Path,
+    app_dir: &Path,
+    task: &mut Task,
+) -> AppResult<PlaylistImport> {
+    let path_clone = path.to_path_buf();
+    let (format, parsed) = tokio::task::spawn_blocking(move || parse_file(&path_clone))
+        .await
+        .map_err(|e| AppError::Unknown(e.to_string()))??;
+    task.set_total(parsed.iter().map(|p| p.entries.len() as u64).sum());
+
+    let source_id = format!("{:x}", md5::compute(path.to_string_lossy().as_bytes()));
+    let mut playlists = Vec::with_capacity(parsed.len());
+    let mut failed = Vec::new();
+
+    for (i, playlist) in parsed.into_iter().enumerate() {
+        let mut audios = Vec::with_capacity(playlist.entries.len());
+        for entry in &playlist.entries {
+            let label = entry.title.as_deref().unwrap_or(&entry.location);
+            let result = if entry.location.is_empty() {
+                Err(AppError::NotFound("no playable location".to_string()))
+            } else if is_url(&entry.location) {
+                import_url(entry, app_dir).await
+            } else {
+                import_local(entry, app_dir).await.map(|a| vec![a])
+            };
+            match result {
+                Ok(local) => audios.extend(local),
+                Err(e) => {
+                    warn!("Skipping playlist entry {}: {}", label, e);
+                    failed.push(format!("{} ({})", label, entry.location));
+                }
+            }
+            task.advance(1, label)?;
+        }
+
+        // Re-importing the same file merges into the same playlists
+        playlists.push(LocalPlaylist {
+            id: Some(format!("{:?}-{}-{}", format, &source_id[..8], i).to_lowercase()),
+            download_url: None,
+            title: playlist.title,
+            cover_path: None,
+            cover: None,
+            platform: playlist_platform(&audios),
+            audios,
+        });
+    }
+
+    task.finish();
+    info!(
+        "Imported {} playlists from {}, {} entries failed",
+        playlists.len(),
+        path.display(),
+        failed.len()
+    );
+    Ok(PlaylistImport {
+        format,
+        playlists,
+        failed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> PathBuf {
+        std::env::temp_dir().join("playlists")
+    }
+
+    fn local(name: &str) -> String {
+        base().join(name).to_string_lossy().to_string()
+    }
+
+    fn entry(location: String, title: Option<&str>, duration: Option<f64>) -> PlaylistEntry {
+        PlaylistEntry {
+            location,
+            title: title.map(str::to_string),
+            duration,
+        }
+    }
+
+    #[test]
+    fn parses_extended_m3u() {
+        let playlist = parse_m3u(
+            "\u{feff}#EXTM3U\n#PLAYLIST:Mix\n\
+             #EXTINF:123 tvg-id=\"x\",First\nfirst.mp3\n\
+             # a comment\n\nhttps://www.youtube.com/watch?v=abc\n\
+             #EXTINF:-1,\nsub/third.flac\n",
+            &base(),
+        );
+        assert_eq!(playlist.title.as_deref(), Some("Mix"));
+        assert_eq!(
+            playlist.entries,
+            vec![
+                entry(local("first.mp3"), Some("First"), Some(123.0)),
+                entry(
+                    "https://www.youtube.com/watch?v=abc".to_string(),
+                    None,
+                    None
+                ),
+                entry(local("sub/third.flac"), None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_pls_in_index_order() {
+        let playlist = parse_pls(
+            "[playlist]\nFile2=second.mp3\nTitle2=Second\nLength2=-1\n\
+             File1=first.mp3\nTitle1=First\nLength1=60\n\
+             Title3=No file\nNumberOfEntries=3\nVersion=2\n",
+            &base(),
+        );
+        assert_eq!(
+            playlist.entries,
+            vec![
+                entry(local("first.mp3"), Some("First"), Some(60.0)),
+                entry(local("second.mp3"), Some("Second"), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_xspf_tracks() {
+        let playlist = parse_xspf(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <title>Road &amp; Rail</title>
+  <trackList>
+    <track>
+      <location>My%20Song.mp3</location>
+      <title>My Song</title>
+      <duration>61500</duration>
+    </track>
+    <track><title>No location</title></track>
+    <track><location>https://www.bilibili.com/video/BV1xx</location></track>
+  </trackList>
+</playlist>"#,
+            &base(),
+        )
+        .unwrap();
+        assert_eq!(playlist.title.as_deref(), Some("Road & Rail"));
+        assert_eq!(
+            playlist.entries,
+            vec![
+                entry(local("My Song.mp3"), Some("My Song"), Some(61.5)),
+                entry(
+                    "https://www.bilibili.com/video/BV1xx".to_string(),
+                    None,
+                    None
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_xspf() {
+        let err = parse_xspf("<playlist><trackList></playlist>", &base()).unwrap_err();
+        assert!(matches!(err, AppError::Parse { .. }));
+    }
+
+    #[test]
+    fn written_playlists_parse_back() {
+        let playlist = ParsedPlaylist {
+            title: Some("Mix".to_string()),
+            entries: vec![
+                entry("first song.mp3".to_string(), Some("First"), Some(60.0)),
+                entry("second.mp3".to_string(), None, None),
+            ],
+        };
+        let expected = vec![
+            entry(local("first song.mp3"), Some("First"), Some(60.0)),
+            entry(local("second.mp3"), None, None),
+        ];
+        assert_eq!(parse_m3u(&write_m3u(&playlist), &base()).entries, expected);
+        assert_eq!(parse_pls(&write_pls(&playlist), &base()).entries, expected);
+        let xspf = parse_xspf(&write_xspf(&playlist), &base()).unwrap();
+        assert_eq!(xspf.title.as_deref(), Some("Mix"));
+        assert_eq!(xspf.entries, expected);
+    }
+
+    #[test]
+    fn decodes_ansi_and_utf16_files() {
+        assert_eq!(decode_text("歌.mp3".as_bytes()), "歌.mp3");
+        // GBK for 歌曲
+        assert_eq!(decode_text(b"\xb8\xe8\xc7\xfa.mp3"), "歌曲.mp3");
+        // Windows-1252 é, not valid GBK before a space
+        assert_eq!(decode_text(b"Caf\xe9 del Mar.mp3"), "Café del Mar.mp3");
+        assert_eq!(decode_text(b"\xff\xfea\x00.\x00"), "a.");
+    }
+
+    #[test]
+    fn detects_format_from_extension_then_content() {
+        let detect = |name: &str, content: &str| PlaylistFormat::detect(Path::new(name), content);
+        assert_eq!(detect("a.M3U8", ""), Some(PlaylistFormat::M3u));
+        assert_eq!(
+            detect("a.txt", "{\"musicSheets\": []}"),
+            Some(PlaylistFormat::MusicFree)
+        );
+        assert_eq!(detect("a.txt", "[playlist]\n"), Some(PlaylistFormat::Pls));
+        assert_eq!(detect("a.txt", "song.mp3"), None);
+    }
+}
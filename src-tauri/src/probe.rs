@@ -0,0 +1,93 @@
+use crate::error::{AppError, AppResult};
+use lofty::{
+    config::ParseOptions,
+    file::{AudioFile, FileType, TaggedFileExt},
+    mp4::{Mp4Codec, Mp4File},
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Stream properties read from an audio file rather than reported by an
+/// extractor.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AudioInfo {
+    /// Seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+    /// e.g. `mp3`, `aac`, `opus` or `pcm`
+    pub codec: String,
+    /// e.g. `mpeg`, `mp4`, `ogg` or `wav`
+    pub container: String,
+    /// Kbps
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitrate: Option<u32>,
+    /// Hz
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channels: Option<u8>,
+}
+
+impl AudioInfo {
+    /// Whether transcoding to `format` (`mp3`, `ogg` or `wav`) would not
+    /// change the codec or container.
+    pub fn matches_format(&self, format: &str) -> bool {
+        match format {
+            "mp3" => self.codec == "mp3" && self.container == "mpeg",
+            "ogg" => self.codec == "vorbis" && self.container == "ogg",
+            "wav" => self.codec == "pcm" && self.container == "wav",
+            _ => false,
+        }
+    }
+}
+
+/// MP4 carries several codecs, which only its own properties tell apart.
+fn mp4_codec(path: &Path) -> AppResult<&'static str> {
+    let mut file = std::fs::File::open(path)?;
+    let mp4 = Mp4File::read_from(&mut file, ParseOptions::new())
+        .map_err(|e| AppError::parse(format!("Failed to read {}", path.display()), e))?;
+    Ok(match mp4.properties().codec() {
+        Mp4Codec::AAC => "aac",
+        Mp4Codec::ALAC => "alac",
+        Mp4Codec::MP3 => "mp3",
+        Mp4Codec::FLAC => "flac",
+        _ => "unknown",
+    })
+}
+
+/// Read the real duration and stream properties of an audio file.
+/// Blocking; run it on a blocking thread.
+pub fn probe(path: &Path) -> AppResult<AudioInfo> {
+    if !path.is_file() {
+        return Err(AppError::NotFound(path.display().to_string()));
+    }
+    let tagged = lofty::read_from_path(path)
+        .map_err(|e| AppError::parse(format!("Failed to probe {}", path.display()), e))?;
+
+    let (codec, container) = match tagged.file_type() {
+        FileType::Mpeg => ("mp3", "mpeg"),
+        FileType::Mp4 => (mp4_codec(path)?, "mp4"),
+        FileType::Aac => ("aac", "adts"),
+        FileType::Flac => ("flac", "flac"),
+        FileType::Opus => ("opus", "ogg"),
+        FileType::Vorbis => ("vorbis", "ogg"),
+        FileType::Speex => ("speex", "ogg"),
+        FileType::Wav => ("pcm", "wav"),
+        FileType::Aiff => ("pcm", "aiff"),
+        FileType::Ape => ("ape", "ape"),
+        FileType::WavPack => ("wavpack", "wavpack"),
+        FileType::Mpc => ("musepack", "mpc"),
+        _ => ("unknown", "unknown"),
+    };
+    let properties = tagged.properties();
+    let duration = properties.duration().as_secs_f64();
+
+    Ok(AudioInfo {
+        duration: (duration > 0.0).then_some(duration),
+        codec: codec.to_string(),
+        container: container.to_string(),
+        bitrate: properties.audio_bitrate(),
+        sample_rate: properties.sample_rate(),
+        channels: properties.channels(),
+    })
+}
@@ -0,0 +1,160 @@
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    sync::{
+        Arc, LazyLock, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tauri::Emitter;
+
+/// Event emitted to the frontend while a long-running task makes progress.
+pub const PROGRESS_EVENT: &str = "task-progress";
+
+/// Minimum delay between two progress events of the same task.
+const EMIT_INTERVAL: Duration = Duration::from_millis(100);
+
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Cancellation flags of the running tasks, by task id.
+static RUNNING: LazyLock<Mutex<HashMap<String, Arc<AtomicBool>>>> = LazyLock::new(Default::default);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskProgress {
+    pub id: String,
+    /// What the task does, e.g. `export` or `import`
+    pub task: String,
+    pub done: u64,
+    pub total: u64,
+    /// Item currently being processed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current: Option<String>,
+    pub finished: bool,
+}
+
+/// A cancellable task reporting progress through [`PROGRESS_EVENT`], in
+/// bytes or items depending on the task.
+/// The task is unregistered when dropped.
+pub struct Task {
+    id: String,
+    task: &'static str,
+    app_handle: tauri::AppHandle,
+    cancelled: Arc<AtomicBool>,
+    done: u64,
+    total: u64,
+    last_emit: Option<Instant>,
+}
+
+impl Task {
+    /// Register a task. Without an `id` from the frontend a random one is used.
+    pub fn start(app_handle: &tauri::AppHandle, task: &'static str, id: Option<String>) -> Self {
+        let id = id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let cancelled = Arc::new(AtomicBool::new(false));
+        RUNNING
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id.clone(), cancelled.clone());
+        Task {
+            id,
+            task,
+            app_handle: app_handle.clone(),
+            cancelled,
+            done: 0,
+            total: 0,
+            last_emit: None,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn set_total(&mut self, total: u64) {
+        self.total = total;
+    }
+
+    /// Fail with [`AppError::Cancelled`] once [`cancel`] was called for this task.
+    pub fn check(&self) -> AppResult<()> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(AppError::Cancelled(self.task.to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn advance(&mut self, bytes: u64, current: &str) -> AppResult<()> {
+        self.done += bytes;
+        if self
+            .last_emit
+            .is_none_or(|last| last.elapsed() >= EMIT_INTERVAL)
+        {
+            self.emit(Some(current), false);
+        }
+        self.check()
+    }
+
+    /// Stream `reader` into `writer`, reporting progress and checking for
+    /// cancellation after every chunk.
+    pub fn copy<R: Read + ?Sized, W: Write + ?Sized>(
+        &mut self,
+        reader: &mut R,
+        writer: &mut W,
+        current: &str,
+    ) -> AppResult<u64> {
+        let mut buffer = vec![0; COPY_BUFFER_SIZE];
+        let mut copied = 0;
+        loop {
+            let n = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            writer.write_all(&buffer[..n])?;
+            copied += n as u64;
+            self.advance(n as u64, current)?;
+        }
+        Ok(copied)
+    }
+
+    pub fn finish(&mut self) {
+        self.emit(None, true);
+    }
+
+    fn emit(&mut self, current: Option<&str>, finished: bool) {
+        self.last_emit = Some(Instant::now());
+        let payload = TaskProgress {
+            id: self.id.clone(),
+            task: self.task.to_string(),
+            done: self.done,
+            total: self.total,
+            current: current.map(str::to_string),
+            finished,
+        };
+        if let Err(e) = self.app_handle.emit(PROGRESS_EVENT, payload) {
+            tracing::warn!("Failed to emit progress: {}", e);
+        }
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        RUNNING
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.id);
+    }
+}
+
+/// Request cancellation of a running task. Returns `false` if no task has this id.
+pub fn cancel(id: &str) -> bool {
+    match RUNNING.lock().unwrap_or_else(|e| e.into_inner()).get(id) {
+        Some(cancelled) => {
+            cancelled.store(true, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
+}
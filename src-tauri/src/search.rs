@@ -0,0 +1,459 @@
+use crate::core::{Config, LocalAudio};
+use pinyin::ToPinyin;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    hash::{DefaultHasher, Hash, Hasher},
+    path::Path,
+    sync::{LazyLock, Mutex},
+    time::SystemTime,
+};
+use tracing::info;
+
+const TITLE_WEIGHT: f64 = 3.0;
+const PLAYLIST_WEIGHT: f64 = 1.5;
+const PLATFORM_WEIGHT: f64 = 1.0;
+const LYRICS_WEIGHT: f64 = 0.5;
+
+/// Index terms tried per query term when matching by prefix.
+const MAX_EXPANSIONS: usize = 64;
+
+/// The index of the library, kept in sync with the config by [`update`]
+/// when it is saved or lyrics are edited.
+static INDEX: LazyLock<Mutex<LibraryIndex>> = LazyLock::new(Default::default);
+
+/// A search result, best first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryHit {
+    pub audio: LocalAudio,
+    /// The first playlist holding the audio
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub playlist_id: Option<String>,
+    pub score: f64,
+}
+
+fn is_han(c: char) -> bool {
+    matches!(c, '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{f900}'..='\u{faff}')
+}
+
+fn is_kana(c: char) -> bool {
+    matches!(c, '\u{3041}'..='\u{30ff}' | '\u{31f0}'..='\u{31ff}')
+}
+
+fn is_hangul(c: char) -> bool {
+    matches!(c, '\u{1100}'..='\u{11ff}' | '\u{3130}'..='\u{318f}' | '\u{ac00}'..='\u{d7af}')
+}
+
+/// Text between separators: a word of letters and digits, or a run of CJK
+/// characters, which are not separated by spaces.
+enum Run {
+    Word(String),
+    Cjk(Vec<char>),
+}
+
+fn runs(text: &str) -> Vec<Run> {
+    let mut runs = Vec::new();
+    let mut word = String::new();
+    let mut cjk = Vec::new();
+    for c in text.chars() {
+        // Full-width ASCII, common in CJK titles, folds to ASCII
+        let c = match c {
+            '\u{ff01}'..='\u{ff5e}' => char::from_u32(c as u32 - 0xfee0).unwrap_or(c),
+            _ => c,
+        };
+        if is_han(c) || is_kana(c) || is_hangul(c) {
+            if !word.is_empty() {
+                runs.push(Run::Word(std::mem::take(&mut word)));
+            }
+            cjk.push(c);
+        } else {
+            if !cjk.is_empty() {
+                runs.push(Run::Cjk(std::mem::take(&mut cjk)));
+            }
+            if c.is_alphanumeric() {
+                word.extend(c.to_lowercase());
+            } else if !word.is_empty() {
+                runs.push(Run::Word(std::mem::take(&mut word)));
+            }
+        }
+    }
+    if !word.is_empty() {
+        runs.push(Run::Word(word));
+    }
+    if !cjk.is_empty() {
+        runs.push(Run::Cjk(cjk));
+    }
+    runs
+}
+
+/// Hiragana and their Hepburn romaji, in the same order. Katakana are
+/// shifted onto hiragana first.
+const HIRAGANA: &str = "あいうえおぁぃぅぇぉかきくけこがぎぐげごさしすせそざじずぜぞたちつてとだぢづでど\
+    なにぬねのはひふへほばびぶべぼぱぴぷぺぽまみむめもやゆよゃゅょらりるれろわをんゔ";
+const ROMAJI: &str = "a i u e o a i u e o ka ki ku ke ko ga gi gu ge go sa shi su se so \
+    za ji zu ze zo ta chi tsu te to da ji zu de do na ni nu ne no ha hi fu he ho \
+    ba bi bu be bo pa pi pu pe po ma mi mu me mo ya yu yo ya yu yo ra ri ru re ro wa o n vu";
+
+fn kana_romaji(c: char) -> Option<&'static str> {
+    // Katakana sit 0x60 above their hiragana
+    let c = match c {
+        '\u{30a1}'..='\u{30f4}' => char::from_u32(c as u32 - 0x60)?,
+        _ => c,
+    };
+    let i = HIRAGANA.chars().position(|k| k == c)?;
+    ROMAJI.split_whitespace().nth(i)
+}
+
+/// Romaji syllables of a kana run: `きょう` is `kyo u`, `っ` doubles the
+/// next consonant and `ー` the previous vowel.
+fn romaji(chars: &[char]) -> Vec<String> {
+    let mut syllables: Vec<String> = Vec::new();
+    let mut double = false;
+    for &c in chars {
+        let small = match c {
+            'ゃ' | 'ャ' => Some("a"),
+            'ゅ' | 'ュ' => Some("u"),
+            'ょ' | 'ョ' => Some("o"),
+            _ => None,
+        };
+        if let (Some(vowel), Some(last)) = (small, syllables.last_mut())
+            && last.ends_with('i')
+        {
+            last.pop();
+            if !matches!(last.as_str(), "sh" | "ch" | "j") {
+                last.push('y');
+            }
+            last.push_str(vowel);
+            continue;
+        }
+        match c {
+            'っ' | 'ッ' => double = true,
+            'ー' => {
+                if let Some(vowel) = syllables.last().and_then(|s| s.chars().last()) {
+                    syllables.push(vowel.to_string());
+                }
+            }
+            _ => {
+                let Some(r) = kana_romaji(c) else {
+                    continue;
+                };
+                let mut syllable = String::new();
+                if std::mem::take(&mut double)
+                    && let Some(first) = r.chars().next()
+                {
+                    syllable.push(first);
+                }
+                syllable.push_str(r);
+                syllables.push(syllable);
+            }
+        }
+    }
+    syllables
+}
+
+/// Latin readings of a CJK run, one per syllable: pinyin for Han
+/// characters and romaji for kana. Hangul has none.
+///
+/// Kanji get their pinyin too, there is no Japanese reading dictionary, so
+/// romaji only finds the kana of a Japanese title.
+fn readings(chars: &[char]) -> Vec<String> {
+    let mut syllables = Vec::new();
+    let mut kana = Vec::new();
+    for &c in chars {
+        if is_kana(c) {
+            kana.push(c);
+            continue;
+        }
+        syllables.extend(romaji(&std::mem::take(&mut kana)));
+        if let Some(p) = c.to_pinyin() {
+            syllables.push(p.plain().to_string());
+        }
+    }
+    syllables.extend(romaji(&kana));
+    syllables
+}
+
+/// Terms indexed for `text`: words, CJK characters and their bigrams, and
+/// with `with_readings` the pinyin/romaji of CJK runs. A reading is indexed
+/// from each syllable on, so `jielun` and `lun` find `周杰伦` by prefix, and
+/// as initials, so `zjl` does too.
+fn index_terms(text: &str, with_readings: bool) -> Vec<String> {
+    let mut terms = Vec::new();
+    for run in runs(text) {
+        match run {
+            Run::Word(word) => terms.push(word),
+            Run::Cjk(chars) => {
+                terms.extend(chars.iter().map(char::to_string));
+                terms.extend(chars.windows(2).map(|w| w.iter().collect::<String>()));
+                if !with_readings {
+                    continue;
+                }
+                let syllables = readings(&chars);
+                for i in 0..syllables.len() {
+                    terms.push(syllables[i..].concat());
+                }
+                if syllables.len() > 1 {
+                    terms.push(syllables.iter().filter_map(|s| s.chars().next()).collect());
+                }
+            }
+        }
+    }
+    terms
+}
+
+/// Terms of a query. Each one must be a prefix of an index term; CJK runs
+/// are matched by their bigrams.
+fn query_terms(query: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for run in runs(query) {
+        match run {
+            Run::Word(word) => terms.push(word),
+            Run::Cjk(chars) if chars.len() == 1 => terms.push(chars[0].to_string()),
+            Run::Cjk(chars) => {
+                terms.extend(chars.windows(2).map(|w| w.iter().collect::<String>()));
+            }
+        }
+    }
+    let mut seen = HashSet::new();
+    terms.retain(|t| seen.insert(t.clone()));
+    terms
+}
+
+struct Doc {
+    audio: LocalAudio,
+    playlist_id: Option<String>,
+    fingerprint: u64,
+    terms: Vec<String>,
+}
+
+/// Ids are only unique within a platform, so documents are keyed by both.
+fn doc_key(audio: &LocalAudio) -> String {
+    format!("{:?}:{}", audio.audio.platform, audio.audio.id)
+}
+
+/// Inverted index from terms to weighted documents, see [`doc_key`].
+#[derive(Default)]
+pub struct LibraryIndex {
+    docs: HashMap<String, Doc>,
+    postings: BTreeMap<String, HashMap<String, f64>>,
+    /// Whether [`LibraryIndex::update`] ran once, so an empty library is
+    /// not read again on every search
+    loaded: bool,
+}
+
+/// An audio with every playlist holding it, as found in the config.
+struct Entry<'a> {
+    audio: &'a LocalAudio,
+    playlist_id: Option<&'a String>,
+    playlists: Vec<&'a str>,
+    /// Modification time of the stored lyrics
+    lyrics_modified: Option<SystemTime>,
+}
+
+impl Entry<'_> {
+    /// Changes whenever a field that is indexed changes. Lyrics are edited
+    /// in place, so their file counts by its modification time, which
+    /// spares reading every lyrics file to find the changed ones.
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.audio.audio.title.hash(&mut hasher);
+        format!("{:?}", self.audio.audio.platform).hash(&mut hasher);
+        self.audio.path.hash(&mut hasher);
+        self.audio.lyrics_path.hash(&mut hasher);
+        self.lyrics_modified.hash(&mut hasher);
+        self.playlists.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+fn lyrics_modified(audio: &LocalAudio, app_dir: &Path) -> Option<SystemTime> {
+    let path = app_dir.join(audio.lyrics_path.as_ref()?);
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn read_lyrics(audio: &LocalAudio, app_dir: &Path) -> Option<String> {
+    let bytes = std::fs::read(app_dir.join(audio.lyrics_path.as_ref()?)).ok()?;
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// The words of LRC text, without timestamps or tags.
+fn lyrics_text(lrc: &str) -> String {
+    crate::lyrics::parse_lrc(lrc)
+        .lines
+        .into_iter()
+        .map(|l| l.text)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl LibraryIndex {
+    fn remove(&mut self, id: &str) {
+        let Some(doc) = self.docs.remove(id) else {
+            return;
+        };
+        for term in doc.terms {
+            if let Some(posting) = self.postings.get_mut(&term) {
+                posting.remove(id);
+                if posting.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, entry: &Entry, fingerprint: u64, app_dir: &Path) {
+        let id = doc_key(entry.audio);
+        let mut weights: HashMap<String, f64> = HashMap::new();
+        let mut add = |text: &str, weight: f64, with_readings: bool| {
+            for term in index_terms(text, with_readings) {
+                *weights.entry(term).or_default() += weight;
+            }
+        };
+        add(&entry.audio.audio.title, TITLE_WEIGHT, true);
+        for playlist in &entry.playlists {
+            add(playlist, PLAYLIST_WEIGHT, true);
+        }
+        add(
+            &format!("{:?}", entry.audio.audio.platform),
+            PLATFORM_WEIGHT,
+            false,
+        );
+        if let Some(lyrics) = read_lyrics(entry.audio, app_dir) {
+            add(&lyrics_text(&lyrics), LYRICS_WEIGHT, false);
+        }
+
+        for (term, weight) in &weights {
+            self.postings
+                .entry(term.clone())
+                .or_default()
+                .insert(id.clone(), *weight);
+        }
+        self.docs.insert(
+            id,
+            Doc {
+                audio: entry.audio.clone(),
+                playlist_id: entry.playlist_id.cloned(),
+                fingerprint,
+                terms: weights.into_keys().collect(),
+            },
+        );
+    }
+
+    /// Reindex the audios of `config` that were added or changed since the
+    /// last update and drop the removed ones. Returns how many were indexed.
+    pub fn update(&mut self, config: &Config, app_dir: &Path) -> usize {
+        let mut entries: HashMap<String, Entry> = HashMap::new();
+        for playlist in &config.playlists {
+            for audio in &playlist.audios {
+                let entry = entries.entry(doc_key(audio)).or_insert_with(|| Entry {
+                    audio,
+                    playlist_id: playlist.id.as_ref(),
+                    playlists: Vec::new(),
+                    lyrics_modified: lyrics_modified(audio, app_dir),
+                });
+                if let Some(title) = &playlist.title {
+                    entry.playlists.push(title);
+                }
+            }
+        }
+
+        let stale: Vec<String> = self
+            .docs
+            .iter()
+            .filter(|(id, doc)| {
+                entries
+                    .get(id.as_str())
+                    .is_none_or(|e| e.fingerprint() != doc.fingerprint)
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &stale {
+            self.remove(id);
+        }
+
+        let mut indexed = 0;
+        for (id, entry) in &entries {
+            match self.docs.get_mut(id) {
+                // Fields that are not indexed may still have changed
+                Some(doc) => {
+                    doc.audio = entry.audio.clone();
+                    doc.playlist_id = entry.playlist_id.cloned();
+                }
+                None => {
+                    self.insert(entry, entry.fingerprint(), app_dir);
+                    indexed += 1;
+                }
+            }
+        }
+        self.loaded = true;
+        indexed
+    }
+
+    /// Audios matching the most query terms, then the best weighted ones.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<LibraryHit> {
+        let terms = query_terms(query);
+        let total = self.docs.len() as f64;
+        let mut scores: HashMap<&str, (usize, f64)> = HashMap::new();
+        for query_term in &terms {
+            let mut best: HashMap<&str, f64> = HashMap::new();
+            let matches = self
+                .postings
+                .range(query_term.clone()..)
+                .take_while(|(term, _)| term.starts_with(query_term.as_str()))
+                .take(MAX_EXPANSIONS);
+            for (term, posting) in matches {
+                // Exact terms count fully, longer ones by how much was typed
+                let coverage = query_term.chars().count() as f64 / term.chars().count() as f64;
+                let idf = (1.0 + total / posting.len() as f64).ln();
+                for (id, weight) in posting {
+                    let score = weight * idf * (0.5 + 0.5 * coverage);
+                    let entry = best.entry(id).or_default();
+                    *entry = entry.max(score);
+                }
+            }
+            for (id, score) in best {
+                let entry = scores.entry(id).or_default();
+                entry.0 += 1;
+                entry.1 += score;
+            }
+        }
+
+        let mut ranked: Vec<(&str, (usize, f64))> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.0.cmp(&a.1.0).then(b.1.1.total_cmp(&a.1.1)));
+        ranked
+            .into_iter()
+            .take(limit)
+            .filter_map(|(id, (_, score))| {
+                let doc = self.docs.get(id)?;
+                Some(LibraryHit {
+                    audio: doc.audio.clone(),
+                    playlist_id: doc.playlist_id.clone(),
+                    score,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Bring the shared index up to date with `config`.
+/// Blocking; run it on a blocking thread.
+pub fn update(config: &Config, app_dir: &Path) {
+    let mut index = INDEX.lock().unwrap_or_else(|e| e.into_inner());
+    let indexed = index.update(config, app_dir);
+    if indexed > 0 {
+        info!("Indexed {} audios for library search", indexed);
+    }
+}
+
+/// Whether the shared index was updated since the app started.
+pub fn is_loaded() -> bool {
+    INDEX.lock().unwrap_or_else(|e| e.into_inner()).loaded
+}
+
+/// Search the shared index, see [`LibraryIndex::search`].
+pub fn search(query: &str, limit: usize) -> Vec<LibraryHit> {
+    INDEX
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .search(query, limit)
+}
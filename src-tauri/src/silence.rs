@@ -0,0 +1,111 @@
+use crate::{
+    core::{Config, LocalAudio},
+    decode::decode,
+    error::{AppError, AppResult},
+    progress::Task,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+use tracing::{info, warn};
+
+/// Windows quieter than this, in dBFS, count as silence.
+const SILENCE_THRESHOLD_DB: f64 = -50.0;
+
+const WINDOW_MS: u64 = 10;
+
+/// Leading or trailing silence shorter than this is kept, so tracks do not
+/// start on a hard cut.
+const MIN_SILENCE_MS: u64 = 300;
+
+/// Audible part of a file, in milliseconds from its start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bounds {
+    pub start_ms: u64,
+    pub end_ms: u64,
+}
+
+/// Find the leading and trailing silence of a file.
+/// Blocking; run it on a blocking thread.
+pub fn detect(path: &Path) -> AppResult<Bounds> {
+    // Window sums of squares run over every channel of a frame
+    let mut window_frames = 0;
+    let mut filled = 0;
+    let mut energy = 0.0;
+    let mut windows: u64 = 0;
+    let mut first_loud = None;
+    let mut last_loud = 0;
+    let threshold = 10f64.powf(SILENCE_THRESHOLD_DB / 10.0);
+
+    decode(path, |spec, samples| {
+        if window_frames == 0 {
+            window_frames = (spec.sample_rate as u64 * WINDOW_MS / 1000).max(1);
+        }
+        for frame in samples.chunks_exact(spec.channels) {
+            energy += frame.iter().map(|s| (*s as f64).powi(2)).sum::<f64>() / spec.channels as f64;
+            filled += 1;
+            if filled == window_frames {
+                if energy / window_frames as f64 > threshold {
+                    first_loud.get_or_insert(windows);
+                    last_loud = windows;
+                }
+                windows += 1;
+                (filled, energy) = (0, 0.0);
+            }
+        }
+        Ok(())
+    })?;
+
+    let Some(first_loud) = first_loud else {
+        return Err(AppError::Transcode {
+            message: format!("{} is silent", path.display()),
+            source: None,
+        });
+    };
+    let total_ms = windows * WINDOW_MS;
+    let mut start_ms = first_loud * WINDOW_MS;
+    let mut end_ms = (last_loud + 1) * WINDOW_MS;
+    if start_ms < MIN_SILENCE_MS {
+        start_ms = 0;
+    }
+    if total_ms - end_ms < MIN_SILENCE_MS {
+        end_ms = total_ms;
+    }
+    Ok(Bounds { start_ms, end_ms })
+}
+
+/// Detect the silence of every audio of the config without bounds yet.
+/// Files that cannot be decoded are skipped. The result is keyed by audio
+/// path and applied to the config by the frontend.
+/// Blocking; run it on a blocking thread.
+pub fn analyze_config(
+    config: &Config,
+    app_dir: &Path,
+    task: &mut Task,
+) -> AppResult<HashMap<String, Bounds>> {
+    let mut seen = HashSet::new();
+    let pending: Vec<&LocalAudio> = config
+        .playlists
+        .iter()
+        .flat_map(|p| &p.audios)
+        .filter(|a| a.end_ms.is_none() && seen.insert(a.path.as_str()))
+        .collect();
+    task.set_total(pending.len() as u64);
+
+    let mut bounds = HashMap::new();
+    for audio in pending {
+        match detect(&audio.file(app_dir)) {
+            Ok(b) => {
+                bounds.insert(audio.path.clone(), b);
+            }
+            Err(e) => warn!("Skipping silence of '{}': {}", audio.audio.title, e),
+        }
+        task.advance(1, &audio.audio.title)?;
+    }
+
+    task.finish();
+    info!("Detected silence of {} files", bounds.len());
+    Ok(bounds)
+}
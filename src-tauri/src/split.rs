@@ -0,0 +1,378 @@
+use crate::{
+    core::{ASSETS_DIR, AUDIOS_DIR, LocalAudio, LocalPlaylist},
+    error::{AppError, AppResult},
+    progress::Task,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+use tracing::info;
+
+/// A part of a track to cut out, in milliseconds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Segment {
+    pub title: String,
+    pub start_ms: u64,
+    /// The end of the file when `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_ms: Option<u64>,
+}
+
+/// `[h:]mm:ss` not part of a longer run of digits and colons, such as the
+/// date `2024:10:15`.
+static TIMESTAMP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:^|[^\d:])((?:(\d{1,2}):)?(\d{1,2}):(\d{2}))(?:$|[^\d:])").unwrap()
+});
+
+/// Numbering and separators around a title, e.g. `1. `, ` - ` or `|`.
+static TITLE_TRIM: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:\d{1,3}[.)]\s+)?[\s\-–—|:.\[\]()]*|[\s\-–—|:\[\]()]*$").unwrap()
+});
+
+/// Read `mm:ss Title` or `h:mm:ss Title` lists, as pasted from a video
+/// description. The timestamp may also follow the title. Each segment ends
+/// where the next one starts.
+pub fn parse_timestamps(text: &str) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = Vec::new();
+    for line in text.lines() {
+        let Some(m) = TIMESTAMP.captures(line) else {
+            continue;
+        };
+        let part = |i: usize| {
+            m.get(i)
+                .map_or(0, |p| p.as_str().parse::<u64>().unwrap_or(0))
+        };
+        // Minutes only run past 59 without hours, as in `75:30`
+        if part(4) >= 60 || (m.get(2).is_some() && part(3) >= 60) {
+            continue;
+        }
+        let start_ms = ((part(2) * 60 + part(3)) * 60 + part(4)) * 1000;
+        let whole = m.get(1).unwrap();
+        let rest = format!("{} {}", &line[..whole.start()], &line[whole.end()..]);
+        let title = TITLE_TRIM.replace_all(rest.trim(), "").trim().to_string();
+
+        // Lines going back in time are not part of the list
+        if segments.last().is_some_and(|s| s.start_ms >= start_ms) {
+            continue;
+        }
+        if let Some(last) = segments.last_mut() {
+            last.end_ms = Some(start_ms);
+        }
+        segments.push(Segment {
+            title: if title.is_empty() {
+                format!("Track {}", segments.len() + 1)
+            } else {
+                title
+            },
+            start_ms,
+            end_ms: None,
+        });
+    }
+    segments
+}
+
+/// Byte offset and start time of every MPEG audio layer III frame.
+struct Mp3Frames {
+    offsets: Vec<usize>,
+    starts_ms: Vec<u64>,
+}
+
+fn mp3_frames(data: &[u8]) -> Option<Mp3Frames> {
+    const BITRATES_V1: [u32; 15] = [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ];
+    const BITRATES_V2: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+    const SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+    // Skip an ID3v2 tag, its size is syncsafe
+    let mut pos = 0;
+    if data.len() > 10 && &data[..3] == b"ID3" {
+        let size = data[6..10]
+            .iter()
+            .fold(0usize, |acc, b| (acc << 7) | (*b as usize & 0x7f));
+        pos = 10 + size;
+    }
+
+    let mut frames = Mp3Frames {
+        offsets: Vec::new(),
+        starts_ms: Vec::new(),
+    };
+    let mut samples: u64 = 0;
+    let mut sample_rate = 0;
+    while pos + 4 <= data.len() {
+        let header = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]);
+        let version = (header >> 19) & 0b11;
+        let layer = (header >> 17) & 0b11;
+        let bitrate = ((header >> 12) & 0xf) as usize;
+        let rate = ((header >> 10) & 0b11) as usize;
+        // Anything but a layer III header with a known bitrate and rate
+        if header >> 21 != 0x7ff
+            || version == 1
+            || layer != 1
+            || bitrate == 0
+            || bitrate == 15
+            || rate == 3
+        {
+            // Trailing tags end the stream, garbage inside it is not cut
+            if frames.offsets.is_empty() || &data[pos..pos + 3] == b"TAG" {
+                break;
+            }
+            return None;
+        }
+        let mpeg1 = version == 3;
+        // MPEG 2 halves the rates of MPEG 1, MPEG 2.5 quarters them
+        let shift = match version {
+            3 => 0,
+            2 => 1,
+            _ => 2,
+        };
+        sample_rate = SAMPLE_RATES[rate] >> shift;
+        let kbps = if mpeg1 {
+            BITRATES_V1[bitrate]
+        } else {
+            BITRATES_V2[bitrate]
+        };
+        let frame_samples = if mpeg1 { 1152 } else { 576 };
+        let padding = ((header >> 9) & 1) as usize;
+        let len = (frame_samples / 8 * kbps as usize * 1000) / sample_rate as usize + padding;
+
+        // A leading Xing/Info frame describes the whole file, not audio
+        let body = &data[pos..(pos + len).min(data.len())];
+        let is_info = frames.offsets.is_empty()
+            && body
+                .windows(4)
+                .take(48)
+                .any(|w| w == b"Xing" || w == b"Info");
+        if !is_info {
+            frames.offsets.push(pos);
+            frames.starts_ms.push(samples * 1000 / sample_rate as u64);
+            samples += frame_samples as u64;
+        }
+        pos += len;
+    }
+    if sample_rate == 0 {
+        return None;
+    }
+    frames.offsets.push(pos.min(data.len()));
+    frames.starts_ms.push(samples * 1000 / sample_rate as u64);
+    (frames.offsets.len() > 1).then_some(frames)
+}
+
+/// What a split cuts its parts from.
+enum Cutter {
+    /// Cut on frame boundaries
+    Mp3(Vec<u8>, Mp3Frames),
+    /// Cut on sample boundaries, see [`crate::demux::cut_aac`]
+    Aac,
+    /// Decoded once, each part is encoded to MP3
+    Pcm(crate::transcode::Pcm),
+}
+
+impl Cutter {
+    fn open(path: &Path) -> AppResult<Self> {
+        let aac = crate::probe::probe(path).is_ok_and(|i| i.codec == "aac" && i.container == "mp4");
+        if aac {
+            return Ok(Self::Aac);
+        }
+        let data = std::fs::read(path)?;
+        match mp3_frames(&data) {
+            Some(frames) => Ok(Self::Mp3(data, frames)),
+            None => crate::transcode::Pcm::decode(path).map(Self::Pcm),
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Aac => "m4a",
+            Self::Mp3(..) | Self::Pcm(_) => "mp3",
+        }
+    }
+
+    /// Write the part between `start_ms` and `end_ms` of `source` to `dest`.
+    fn cut(&self, source: &Path, dest: &Path, start_ms: u64, end_ms: Option<u64>) -> AppResult<()> {
+        let bytes = match self {
+            Self::Mp3(data, frames) => cut_mp3(data, frames, start_ms, end_ms),
+            Self::Pcm(pcm) => crate::transcode::encode(pcm.cut(start_ms, end_ms)?, "mp3")?,
+            Self::Aac => {
+                return match crate::demux::cut_aac(source, dest, start_ms, end_ms)? {
+                    Some(written) if written > 0 => Ok(()),
+                    _ => {
+                        let _ = std::fs::remove_file(dest);
+                        Err(AppError::Transcode {
+                            message: format!("No AAC samples cut from {}", source.display()),
+                            source: None,
+                        })
+                    }
+                };
+            }
+        };
+        std::fs::write(dest, bytes)?;
+        Ok(())
+    }
+}
+
+/// Cut MP3 data on frame boundaries, without re-encoding.
+fn cut_mp3(data: &[u8], frames: &Mp3Frames, start_ms: u64, end_ms: Option<u64>) -> Vec<u8> {
+    let frame_at = |ms: u64| frames.starts_ms.partition_point(|s| *s < ms);
+    let last = frames.offsets.len() - 1;
+    let from = frame_at(start_ms).min(last);
+    let to = end_ms.map_or(last, |ms| frame_at(ms).min(last));
+    data[frames.offsets[from]..frames.offsets[to.max(from)]].to_vec()
+}
+
+/// Cut a downloaded audio into one file per segment, next to the original,
+/// and describe them as a new playlist.
+///
+/// MP3 is cut on frame boundaries and AAC on sample boundaries as is, other
+/// formats are decoded once and each part is encoded to MP3.
+/// Blocking; run it on a blocking thread.
+pub fn split(
+    audio: &LocalAudio,
+    segments: &[Segment],
+    app_dir: &Path,
+    task: &mut Task,
+) -> AppResult<LocalPlaylist> {
+    if segments.is_empty() {
+        return Err(AppError::Invalid("No segments to split into".to_string()));
+    }
+    let source = audio.file(app_dir);
+    let cutter = Cutter::open(&source)?;
+    task.set_total(segments.len() as u64);
+
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| audio.audio.id.clone());
+    let mut audios = Vec::with_capacity(segments.len());
+    for (i, segment) in segments.iter().enumerate() {
+        task.check()?;
+        let path = format!(
+            "{}/{:?}/{}/{}_part{:02}.{}",
+            ASSETS_DIR,
+            audio.audio.platform,
+            AUDIOS_DIR,
+            stem,
+            i + 1,
+            cutter.extension()
+        );
+        let dest: PathBuf = app_dir.join(&path);
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        cutter.cut(&source, &dest, segment.start_ms, segment.end_ms)?;
+
+        let info = crate::probe::probe(&dest).ok();
+        let mut piece = audio.audio.clone();
+        piece.id = format!("{}-{}", audio.audio.id, i + 1);
+        piece.title = segment.title.clone();
+        piece.duration = info
+            .as_ref()
+            .and_then(|i| i.duration)
+            .map(|d| d.round() as _);
+        piece.format = None;
+        audios.push(LocalAudio {
+            path,
+            external_path: None,
+            cover_path: audio.cover_path.clone(),
+            audio: piece,
+            info,
+            loudness: None,
+            start_ms: None,
+            end_ms: None,
+            lyrics_path: None,
+            fingerprint: None,
+        });
+        task.advance(1, &segment.title)?;
+    }
+
+    task.finish();
+    info!("Split '{}' into {} tracks", audio.audio.title, audios.len());
+    Ok(LocalPlaylist {
+        id: Some(format!("split-{}", audio.audio.id)),
+        download_url: Some(audio.audio.download_url.clone()),
+        title: Some(audio.audio.title.clone()),
+        cover_path: audio.cover_path.clone(),
+        cover: audio.audio.cover.clone(),
+        audios,
+        platform: audio.audio.platform,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(title: &str, start_ms: u64, end_ms: Option<u64>) -> Segment {
+        Segment {
+            title: title.to_string(),
+            start_ms,
+            end_ms,
+        }
+    }
+
+    #[test]
+    fn reads_minute_and_hour_timestamps() {
+        let segments = parse_timestamps("0:00 Intro\n1. 03:25 - Second Song\n1:02:03 Third");
+        assert_eq!(
+            segments,
+            vec![
+                segment("Intro", 0, Some(205_000)),
+                segment("Second Song", 205_000, Some(3_723_000)),
+                segment("Third", 3_723_000, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_titles_before_the_timestamp() {
+        let segments = parse_timestamps("Intro 0:00\nOutro (4:10)\n75:30 | Encore");
+        assert_eq!(
+            segments,
+            vec![
+                segment("Intro", 0, Some(250_000)),
+                segment("Outro", 250_000, Some(4_530_000)),
+                segment("Encore", 4_530_000, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn names_untitled_segments_by_position() {
+        let segments = parse_timestamps("0:00\n1:00 -");
+        assert_eq!(
+            segments,
+            vec![
+                segment("Track 1", 0, Some(60_000)),
+                segment("Track 2", 60_000, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_lines_going_back_in_time() {
+        let segments = parse_timestamps("0:00 A\n2:00 B\nRecap 1:00\n3:00 D");
+        assert_eq!(
+            segments,
+            vec![
+                segment("A", 0, Some(120_000)),
+                segment("B", 120_000, Some(180_000)),
+                segment("D", 180_000, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_out_of_range_times_and_dates() {
+        let segments = parse_timestamps(
+            "Released 2024:10:15\n0:00 A\n1:75 Bad seconds\n1:61:00 Bad minutes\n2:00 B",
+        );
+        assert_eq!(
+            segments,
+            vec![segment("A", 0, Some(120_000)), segment("B", 120_000, None)]
+        );
+        assert!(parse_timestamps("No timestamps here\n12:3 either").is_empty());
+    }
+}
@@ -0,0 +1,388 @@
+use crate::{
+    core::LocalAudio,
+    error::{AppError, AppResult},
+    lyrics::LyricsProvider,
+};
+use musicfree::Platform;
+use regex::Regex;
+use serde::Deserialize;
+use std::{path::Path, sync::LazyLock};
+use tracing::{info, warn};
+
+/// Subtitle formats converted by [`to_lrc`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    Srt,
+    /// WebVTT, as served by YouTube
+    Vtt,
+    /// `{"body": [{"from", "to", "content"}]}` of Bilibili CC subtitles
+    Bilibili,
+}
+
+impl SubtitleFormat {
+    /// Guess the format from the extension, falling back to the content.
+    /// `None` for anything else, such as LRC.
+    pub fn detect(path: &Path, content: &str) -> Option<Self> {
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("srt") => return Some(Self::Srt),
+            Some("vtt") => return Some(Self::Vtt),
+            _ => {}
+        }
+        let head = content.trim_start_matches('\u{feff}').trim_start();
+        if head.starts_with("WEBVTT") {
+            Some(Self::Vtt)
+        } else if head.starts_with('{') && content.contains("\"body\"") {
+            Some(Self::Bilibili)
+        } else if SRT_INDEX.is_match(head) {
+            Some(Self::Srt)
+        } else {
+            None
+        }
+    }
+}
+
+/// A caption shown from `start_ms` to `end_ms`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+}
+
+/// `00:01:02,345` in SRT, `01:02.345` or `00:01:02.345` in WebVTT.
+static TIMESTAMP: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:(\d+):)?(\d{1,2}):(\d{2})[.,](\d{1,3})").unwrap());
+
+static SRT_INDEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\d+\s*\r?\n[\d:,.]+\s*-->").unwrap());
+
+/// Markup inside cue text: `<i>`, `<c.color>`, `<00:00:01.000>` or `{\an8}`.
+static MARKUP: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>|\{\\[^}]*\}").unwrap());
+
+fn timestamp_ms(s: &str) -> Option<u64> {
+    let caps = TIMESTAMP.captures(s)?;
+    let part = |i: usize| {
+        caps.get(i)
+            .map_or(0, |p| p.as_str().parse::<u64>().unwrap_or(0))
+    };
+    let fraction = caps.get(4).unwrap();
+    let ms = part(4) * 10u64.pow(3 - fraction.as_str().len() as u32);
+    Some(((part(1) * 60 + part(2)) * 60 + part(3)) * 1000 + ms)
+}
+
+fn clean_text(lines: &[&str]) -> String {
+    let text = lines
+        .iter()
+        .map(|l| MARKUP.replace_all(l, "").trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    text.replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+}
+
+/// Read the cues of SRT or WebVTT, which share the `start --> end` line
+/// followed by the text up to a blank line. Cue numbers, the WebVTT header
+/// and `NOTE`/`STYLE` blocks have no timing line and are skipped.
+fn parse_blocks(text: &str) -> Vec<Cue> {
+    let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut cues = Vec::new();
+    for block in text.split("\n\n") {
+        let lines: Vec<&str> = block.lines().collect();
+        let Some(timing) = lines.iter().position(|l| l.contains("-->")) else {
+            continue;
+        };
+        let Some((start, end)) = lines[timing].split_once("-->") else {
+            continue;
+        };
+        let (Some(start_ms), Some(end_ms)) = (timestamp_ms(start), timestamp_ms(end)) else {
+            continue;
+        };
+        let text = clean_text(&lines[timing + 1..]);
+        if text.is_empty() {
+            continue;
+        }
+        cues.push(Cue {
+            start_ms,
+            end_ms,
+            text,
+        });
+    }
+    cues
+}
+
+pub fn parse_srt(text: &str) -> Vec<Cue> {
+    parse_blocks(text)
+}
+
+/// Read WebVTT. Auto-generated YouTube captions roll each line through
+/// several cues, so a cue repeating the previous text extends it instead.
+pub fn parse_vtt(text: &str) -> Vec<Cue> {
+    let mut cues: Vec<Cue> = Vec::new();
+    for cue in parse_blocks(text) {
+        match cues.last_mut() {
+            Some(last) if last.text == cue.text => last.end_ms = last.end_ms.max(cue.end_ms),
+            _ => cues.push(cue),
+        }
+    }
+    cues
+}
+
+#[derive(Debug, Deserialize)]
+struct BilibiliSubtitle {
+    body: Vec<BilibiliLine>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BilibiliLine {
+    from: f64,
+    to: f64,
+    content: String,
+}
+
+pub fn parse_bilibili(text: &str) -> AppResult<Vec<Cue>> {
+    let subtitle: BilibiliSubtitle = serde_json::from_str(text.trim_start_matches('\u{feff}'))?;
+    Ok(subtitle
+        .body
+        .into_iter()
+        .filter(|l| !l.content.trim().is_empty())
+        .map(|l| Cue {
+            start_ms: (l.from.max(0.0) * 1000.0).round() as u64,
+            end_ms: (l.to.max(0.0) * 1000.0).round() as u64,
+            text: l.content.replace('\n', " ").trim().to_string(),
+        })
+        .collect())
+}
+
+/// Gaps longer than this get an empty line, so the last caption does not
+/// stay on screen through an instrumental part.
+const GAP_MS: u64 = 1000;
+
+fn lrc_time(ms: u64) -> String {
+    format!(
+        "[{:02}:{:02}.{:02}]",
+        ms / 60_000,
+        ms / 1000 % 60,
+        ms % 1000 / 10
+    )
+}
+
+/// Write cues as LRC lines.
+pub fn cues_to_lrc(cues: &[Cue]) -> String {
+    let mut cues = cues.to_vec();
+    cues.sort_by_key(|c| c.start_ms);
+    let mut lrc = String::new();
+    for (i, cue) in cues.iter().enumerate() {
+        lrc.push_str(&lrc_time(cue.start_ms));
+        lrc.push_str(&cue.text);
+        lrc.push('\n');
+        let next = cues.get(i + 1).map_or(u64::MAX, |c| c.start_ms);
+        if next.saturating_sub(cue.end_ms) > GAP_MS {
+            lrc.push_str(&lrc_time(cue.end_ms));
+            lrc.push('\n');
+        }
+    }
+    lrc
+}
+
+/// Convert subtitles to LRC.
+pub fn to_lrc(text: &str, format: SubtitleFormat) -> AppResult<String> {
+    let cues = match format {
+        SubtitleFormat::Srt => parse_srt(text),
+        SubtitleFormat::Vtt => parse_vtt(text),
+        SubtitleFormat::Bilibili => parse_bilibili(text)?,
+    };
+    if cues.is_empty() {
+        return Err(AppError::NotFound("No captions in subtitles".to_string()));
+    }
+    Ok(cues_to_lrc(&cues))
+}
+
+static BVID: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"BV[0-9A-Za-z]{10}").unwrap());
+
+/// `cid=123` in a stream URL, or `p=2` in a page URL of a multi-part video.
+static BILIBILI_CID: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[?&]cid=(\d+)").unwrap());
+static BILIBILI_PAGE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[?&]p=(\d+)").unwrap());
+
+/// The part of a Bilibili video an audio comes from: its cid when the
+/// sources carry it, else the `p=` page, else the part named like the
+/// audio, else the first part.
+fn bilibili_cid(view: &serde_json::Value, sources: &[&str], title: &str) -> Option<u64> {
+    let find = |re: &Regex| {
+        sources
+            .iter()
+            .find_map(|s| re.captures(s))
+            .and_then(|c| c[1].parse::<u64>().ok())
+    };
+    if let Some(cid) = find(&BILIBILI_CID) {
+        return Some(cid);
+    }
+    let pages = view["data"]["pages"].as_array();
+    let page = find(&BILIBILI_PAGE)
+        .and_then(|p| pages?.iter().find(|page| page["page"].as_u64() == Some(p)));
+    let named = || {
+        pages?.iter().find(|page| {
+            page["part"]
+                .as_str()
+                .is_some_and(|part| !part.is_empty() && title.contains(part))
+        })
+    };
+    page.or_else(named)
+        .and_then(|page| page["cid"].as_u64())
+        .or_else(|| view["data"]["cid"].as_u64())
+}
+
+static YOUTUBE_ID: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:^|[?&]v=|youtu\.be/|/shorts/)([0-9A-Za-z_-]{11})(?:$|[&?#/])").unwrap()
+});
+
+/// Captions published with the video an audio was extracted from.
+///
+/// The extractors do not pass captions along, so they are looked up from
+/// the platform by the video id found in the audio id or URL. Downloads
+/// attach them when nothing is stored yet, see
+/// [`crate::api::download_audio`], and lyrics lookups try them through
+/// [`crate::lyrics::default_providers`]. A track that fails to load is
+/// skipped for the next one.
+pub struct Captions {
+    client: reqwest::Client,
+}
+
+impl Default for Captions {
+    fn default() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+/// Caption languages tried on YouTube, in order.
+const YOUTUBE_LANGUAGES: &[&str] = &["en", "zh-Hans", "zh-Hant", "ja", "ko"];
+
+impl Captions {
+    async fn bilibili_get(&self, url: &str) -> AppResult<String> {
+        let request = async {
+            self.client
+                .get(url)
+                .header("User-Agent", crate::discover::BROWSER_USER_AGENT)
+                .header("Referer", crate::discover::BILIBILI_HOME)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await
+        };
+        request
+            .await
+            .map_err(|e| AppError::network("Bilibili request failed", e))
+    }
+
+    async fn bilibili(&self, bvid: &str, audio: &LocalAudio) -> AppResult<Option<String>> {
+        let view: serde_json::Value = serde_json::from_str(
+            &self
+                .bilibili_get(&format!(
+                    "https://api.bilibili.com/x/web-interface/view?bvid={bvid}"
+                ))
+                .await?,
+        )?;
+        let sources = [audio.audio.id.as_str(), audio.audio.download_url.as_str()];
+        let Some(cid) = bilibili_cid(&view, &sources, &audio.audio.title) else {
+            return Ok(None);
+        };
+        let player: serde_json::Value = serde_json::from_str(
+            &self
+                .bilibili_get(&format!(
+                    "https://api.bilibili.com/x/player/v2?bvid={bvid}&cid={cid}"
+                ))
+                .await?,
+        )?;
+        let tracks = player["data"]["subtitle"]["subtitles"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let mut failure = None;
+        for url in tracks
+            .iter()
+            .filter_map(|track| track["subtitle_url"].as_str())
+            .filter(|url| !url.is_empty())
+        {
+            // Subtitle URLs come without a scheme
+            let url = if url.starts_with("//") {
+                format!("https:{url}")
+            } else {
+                url.to_string()
+            };
+            let lrc = self
+                .bilibili_get(&url)
+                .await
+                .and_then(|subtitle| to_lrc(&subtitle, SubtitleFormat::Bilibili));
+            match lrc {
+                Ok(lrc) => return Ok(Some(lrc)),
+                Err(e) => {
+                    warn!("Skipping Bilibili subtitle track '{url}': {e}");
+                    failure.get_or_insert(e);
+                }
+            }
+        }
+        failure.map_or(Ok(None), Err)
+    }
+
+    async fn youtube(&self, id: &str) -> AppResult<Option<String>> {
+        let mut failure = None;
+        for &lang in YOUTUBE_LANGUAGES {
+            let request = async {
+                self.client
+                    .get("https://www.youtube.com/api/timedtext")
+                    .query(&[("v", id), ("lang", lang), ("fmt", "vtt")])
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .text()
+                    .await
+            };
+            match request.await {
+                // Missing tracks answer with an empty body
+                Ok(vtt) => {
+                    if let Ok(lrc) = to_lrc(&vtt, SubtitleFormat::Vtt) {
+                        return Ok(Some(lrc));
+                    }
+                }
+                Err(e) => {
+                    warn!("YouTube captions request for '{lang}' failed: {e}");
+                    failure.get_or_insert(AppError::network("YouTube captions request failed", e));
+                }
+            }
+        }
+        failure.map_or(Ok(None), Err)
+    }
+}
+
+impl LyricsProvider for Captions {
+    fn name(&self) -> &'static str {
+        "captions"
+    }
+
+    async fn fetch(&self, audio: &LocalAudio, _app_dir: &Path) -> AppResult<Option<String>> {
+        let sources = [audio.audio.id.as_str(), audio.audio.download_url.as_str()];
+        let lrc = match audio.audio.platform {
+            Platform::Bilibili => match sources.iter().find_map(|s| BVID.find(s)) {
+                Some(bvid) => self.bilibili(bvid.as_str(), audio).await?,
+                None => None,
+            },
+            Platform::Youtube => match sources.iter().find_map(|s| YOUTUBE_ID.captures(s)) {
+                Some(caps) => self.youtube(&caps[1]).await?,
+                None => None,
+            },
+            _ => None,
+        };
+        if lrc.is_some() {
+            info!("Found captions for '{}'", audio.audio.title);
+        }
+        Ok(lrc)
+    }
+}
@@ -1,7 +1,37 @@
-use crate::error::SyncError;
+use crate::{
+    core::{Config, LocalPlaylist},
+    error::SyncError,
+};
 use base64::{Engine as _, engine::general_purpose};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Component, Path, PathBuf},
+};
+use tokio::io::AsyncWriteExt;
+use tracing::{error, warn};
+
+/// Manifest in the sync repo describing every synced asset.
+pub const ASSET_MANIFEST_FILE: &str = "assets.json";
+
+/// Directory in the sync repo holding content-addressed asset objects.
+const ASSET_OBJECTS_DIR: &str = "objects";
+
+/// Tag of the release used to store assets too large for the contents API.
+const ASSET_RELEASE_TAG: &str = "musicfree-assets";
+
+/// The contents API takes whole base64 encoded files in a JSON body, so only
+/// small files such as covers and lyrics go through it. Anything larger is
+/// streamed from disk as a release asset instead.
+const CONTENTS_API_MAX_SIZE: u64 = 1024 * 1024;
+
+/// Default per-file size cap for asset sync. Larger files are left out.
+pub const DEFAULT_ASSET_SIZE_CAP: u64 = 200 * 1024 * 1024;
+
+/// Commit trailer recording which device pushed a change.
+const DEVICE_TRAILER: &str = "Device:";
 
 /// Response structure for GitHub repository file content
 #[derive(Debug, Deserialize)]
@@ -24,6 +54,40 @@ pub struct FileInfo {
     pub size: u64,
 }
 
+/// Entry of the GitHub "list commits" response
+#[derive(Debug, Deserialize)]
+struct CommitResponse {
+    sha: String,
+    commit: CommitDetail,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitDetail {
+    message: String,
+    author: Option<CommitAuthor>,
+    committer: Option<CommitAuthor>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitAuthor {
+    name: String,
+    date: String,
+}
+
+/// A commit that touched the sync file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncCommit {
+    pub sha: String,
+    /// ISO 8601 commit date
+    pub date: String,
+    /// First line of the commit message
+    pub message: String,
+    /// Device from the `Device:` trailer, or the commit author for commits
+    /// made outside the app
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+}
+
 /// Payload for creating/updating files in GitHub repo
 #[derive(Serialize)]
 struct UpdateFilePayload {
@@ -73,6 +137,7 @@ fn parse_repo_url(repo_url: &str) -> Result<(String, String), SyncError> {
 /// 2. A change-detection probe (compare SHA with cached value)
 ///
 /// A single lightweight GET request.
+#[tracing::instrument(skip(token))]
 pub async fn get_file_info(
     token: &str,
     repo_url: &str,
@@ -100,14 +165,7 @@ pub async fn get_file_info(
         return Ok(None); // File does not exist yet
     }
     if !status.is_success() {
-        let error_text = response
-            .text()
-            .await
-            .unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(SyncError::GitHubApi(format!(
-            "Status {}: {}",
-            status, error_text
-        )));
+        return Err(status_error(response, None).await);
     }
 
     let repo_file: RepoFileResponse = response.json().await?;
@@ -122,14 +180,40 @@ pub async fn download(
     token: &str,
     repo_url: &str,
     file_path: Option<&str>,
+) -> Result<Vec<u8>, SyncError> {
+    download_ref(token, repo_url, file_path, None).await
+}
+
+/// Download a file as it was at the given commit.
+pub async fn download_at(
+    token: &str,
+    repo_url: &str,
+    file_path: Option<&str>,
+    sha: &str,
+) -> Result<Vec<u8>, SyncError> {
+    if sha.len() != 40 || !sha.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(SyncError::InvalidSha(sha.to_string()));
+    }
+    download_ref(token, repo_url, file_path, Some(sha)).await
+}
+
+#[tracing::instrument(skip(token))]
+async fn download_ref(
+    token: &str,
+    repo_url: &str,
+    file_path: Option<&str>,
+    git_ref: Option<&str>,
 ) -> Result<Vec<u8>, SyncError> {
     let (owner, repo) = parse_repo_url(repo_url)?;
     let client = Client::new();
     let file_name = file_path.unwrap_or(crate::core::CONFIG_FILE);
-    let url = format!(
+    let mut url = format!(
         "https://api.github.com/repos/{}/{}/contents/{}",
         owner, repo, file_name
     );
+    if let Some(git_ref) = git_ref {
+        url.push_str(&format!("?ref={}", git_ref));
+    }
 
     // Use raw format to get binary data directly without base64 encoding
     let response = client
@@ -148,14 +232,7 @@ pub async fn download(
     }
 
     if !status.is_success() {
-        let error_text = response
-            .text()
-            .await
-            .unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(SyncError::GitHubApi(format!(
-            "Status {}: {}",
-            status, error_text
-        )));
+        return Err(status_error(response, None).await);
     }
 
     // Get raw binary data
@@ -165,12 +242,14 @@ pub async fn download(
 
 /// Upload/update file content to GitHub repository (binary format).
 /// Note: GitHub API requires base64 encoding for the content field.
+#[tracing::instrument(skip(token, content), fields(size = content.len()))]
 pub async fn update(
     token: &str,
     repo_url: &str,
     content: Vec<u8>,
     file_path: Option<&str>,
     message: Option<&str>,
+    device: Option<&str>,
 ) -> Result<(), SyncError> {
     let (owner, repo) = parse_repo_url(repo_url)?;
     let client = Client::new();
@@ -188,7 +267,7 @@ pub async fn update(
 
     let default_message = format!("Update {}", file_name);
     let payload = UpdateFilePayload {
-        message: message.unwrap_or(&default_message).to_string(),
+        message: with_device_trailer(message.unwrap_or(&default_message), device),
         content: encoded_content,
         sha,
         branch: Some("main".to_string()),
@@ -204,19 +283,38 @@ pub async fn update(
         .await?;
 
     if !response.status().is_success() {
-        let error_text = response
-            .text()
-            .await
-            .unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(SyncError::GitHubApi(format!(
-            "Failed to update {}: {}",
-            file_name, error_text
-        )));
+        return Err(status_error(response, Some(&format!("Failed to update {}", file_name))).await);
     }
 
     Ok(())
 }
 
+/// Turn a failed GitHub response into [`SyncError::Status`], keeping the
+/// status code so callers can tell an invalid token from a transient failure.
+async fn status_error(response: reqwest::Response, context: Option<&str>) -> SyncError {
+    let status = response.status().as_u16();
+    let text = response
+        .text()
+        .await
+        .unwrap_or_else(|_| "Unknown error".to_string());
+    let message = match context {
+        Some(context) => format!("{}: {}", context, text),
+        None => text,
+    };
+    SyncError::Status { status, message }
+}
+
+/// Append a `Device:` trailer with the app's device id (`_deviceId` of the
+/// config) so `history` can tell which device made a sync.
+fn with_device_trailer(message: &str, device: Option<&str>) -> String {
+    match device {
+        Some(device) if !message.lines().any(|l| l.starts_with(DEVICE_TRAILER)) => {
+            format!("{}\n\n{} {}", message, DEVICE_TRAILER, device)
+        }
+        _ => message.to_string(),
+    }
+}
+
 /// Helper function to get file SHA (needed for updates)
 async fn get_file_sha(
     client: &Client,
@@ -246,3 +344,661 @@ async fn get_file_sha(
     let repo_file: RepoFileResponse = response.json().await.ok()?;
     Some(repo_file.sha)
 }
+
+/// List the most recent commits that touched the sync file, newest first.
+#[tracing::instrument(skip(token))]
+pub async fn history(
+    token: &str,
+    repo_url: &str,
+    file_path: Option<&str>,
+    limit: usize,
+) -> Result<Vec<SyncCommit>, SyncError> {
+    let (owner, repo) = parse_repo_url(repo_url)?;
+    let client = Client::new();
+    let file_name = file_path.unwrap_or(crate::core::CONFIG_FILE);
+    let url = format!(
+        "https://api.github.com/repos/{}/{}/commits?path={}&per_page={}",
+        owner,
+        repo,
+        file_name,
+        limit.clamp(1, 100)
+    );
+
+    let response = client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .header("User-Agent", "musicfree-tauri")
+        .header("Accept", "application/vnd.github.v3+json")
+        .send()
+        .await?;
+
+    let status = response.status();
+    // An empty repository has no history yet
+    if status.as_u16() == 404 || status.as_u16() == 409 {
+        return Ok(Vec::new());
+    }
+    if !status.is_success() {
+        return Err(status_error(response, None).await);
+    }
+
+    let commits: Vec<CommitResponse> = response.json().await?;
+    Ok(commits
+        .into_iter()
+        .map(|c| {
+            let device = c
+                .commit
+                .message
+                .lines()
+                .find_map(|l| l.strip_prefix(DEVICE_TRAILER))
+                .map(|d| d.trim().to_string())
+                .or_else(|| c.commit.author.as_ref().map(|a| a.name.clone()));
+            let date = c
+                .commit
+                .committer
+                .or(c.commit.author)
+                .map(|a| a.date)
+                .unwrap_or_default();
+            SyncCommit {
+                sha: c.sha,
+                date,
+                message: c
+                    .commit
+                    .message
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+                device,
+            }
+        })
+        .collect())
+}
+
+/// Playlist present on both sides whose tracks differ.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistChange {
+    pub title: String,
+    /// Tracks the restored revision has that the local config lacks
+    pub added: usize,
+    /// Local tracks missing from the restored revision
+    pub removed: usize,
+}
+
+/// What restoring a revision would change, relative to the local config.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConfigDiff {
+    pub added_playlists: Vec<String>,
+    pub removed_playlists: Vec<String>,
+    pub changed_playlists: Vec<PlaylistChange>,
+}
+
+/// A past revision of the sync file, ready to be applied by the frontend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncRestore {
+    pub sha: String,
+    /// The revision as stored, including the `_updatedAt` and `_deviceId`
+    /// the Rust [`Config`] drops. The frontend stamps it again when applied.
+    pub config: serde_json::Value,
+    pub diff: ConfigDiff,
+}
+
+fn playlist_key(playlist: &LocalPlaylist) -> String {
+    playlist
+        .id
+        .clone()
+        .or_else(|| playlist.download_url.clone())
+        .or_else(|| playlist.title.clone())
+        .unwrap_or_default()
+}
+
+fn playlist_title(playlist: &LocalPlaylist) -> String {
+    playlist
+        .title
+        .clone()
+        .or_else(|| playlist.id.clone())
+        .unwrap_or_else(|| "Unknown".to_string())
+}
+
+/// Compare the local config with a restored one, playlist by playlist.
+/// Tracks are matched by `(platform, id)`.
+pub fn diff_config(current: &Config, restored: &Config) -> ConfigDiff {
+    let audio_keys = |p: &LocalPlaylist| -> HashSet<String> {
+        p.audios
+            .iter()
+            .map(|a| format!("{:?}:{}", a.audio.platform, a.audio.id))
+            .collect()
+    };
+    let current_by_key: HashMap<String, &LocalPlaylist> = current
+        .playlists
+        .iter()
+        .map(|p| (playlist_key(p), p))
+        .collect();
+    let restored_keys: HashSet<String> = restored.playlists.iter().map(playlist_key).collect();
+
+    let mut diff = ConfigDiff::default();
+    for playlist in &restored.playlists {
+        match current_by_key.get(&playlist_key(playlist)) {
+            None => diff.added_playlists.push(playlist_title(playlist)),
+            Some(local) => {
+                let restored_audios = audio_keys(playlist);
+                let local_audios = audio_keys(local);
+                let added = restored_audios.difference(&local_audios).count();
+                let removed = local_audios.difference(&restored_audios).count();
+                if added > 0 || removed > 0 {
+                    diff.changed_playlists.push(PlaylistChange {
+                        title: playlist_title(playlist),
+                        added,
+                        removed,
+                    });
+                }
+            }
+        }
+    }
+    for playlist in &current.playlists {
+        if !restored_keys.contains(&playlist_key(playlist)) {
+            diff.removed_playlists.push(playlist_title(playlist));
+        }
+    }
+    diff
+}
+
+/// Fetch the sync file at `sha` and compare it with the local config.
+/// Nothing is written; the caller decides whether to apply it.
+pub async fn restore(
+    token: &str,
+    repo_url: &str,
+    file_path: Option<&str>,
+    sha: &str,
+    current: &Config,
+) -> Result<SyncRestore, SyncError> {
+    let bytes = download_at(token, repo_url, file_path, sha).await?;
+    if bytes.is_empty() {
+        return Err(SyncError::FileNotFound(format!(
+            "{}@{}",
+            file_path.unwrap_or(crate::core::CONFIG_FILE),
+            sha
+        )));
+    }
+    let config: serde_json::Value = serde_json::from_slice(&bytes)?;
+    let diff = diff_config(current, &serde_json::from_value(config.clone())?);
+
+    Ok(SyncRestore {
+        sha: sha.to_string(),
+        config,
+        diff,
+    })
+}
+
+/// Where an asset object lives in the sync repo.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetStorage {
+    /// `objects/{hash[..2]}/{hash}` committed through the contents API
+    #[default]
+    Repo,
+    /// Asset named `{hash}` on the `musicfree-assets` release, for files
+    /// the contents API cannot take. Git LFS is not used: objects would need
+    /// the LFS batch API on top of the contents API, while release assets
+    /// take large files with the same token.
+    Release,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetEntry {
+    /// SHA-256 of the file content
+    pub hash: String,
+    pub size: u64,
+    #[serde(default)]
+    pub storage: AssetStorage,
+}
+
+/// Maps local asset paths (e.g. `assets/Bilibili/audios/x.mp4`) to the
+/// content-addressed objects stored in the sync repo.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AssetManifest {
+    #[serde(default)]
+    pub files: BTreeMap<String, AssetEntry>,
+}
+
+/// Outcome of an asset upload or download, grouped by local path.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AssetSyncResult {
+    /// Files uploaded or downloaded in this run
+    pub transferred: Vec<String>,
+    /// Files already up to date on the other side
+    pub skipped: Vec<String>,
+    /// Files larger than the size cap
+    pub oversized: Vec<String>,
+    /// Files not present on the source side
+    pub missing: Vec<String>,
+    /// Files that failed to transfer
+    pub failed: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    id: u64,
+    #[serde(default)]
+    assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseAsset {
+    id: u64,
+    name: String,
+}
+
+#[derive(Serialize)]
+struct CreateReleasePayload<'a> {
+    tag_name: &'a str,
+    name: &'a str,
+    body: &'a str,
+}
+
+fn object_path(hash: &str) -> String {
+    format!(
+        "{}/{}/{}",
+        ASSET_OBJECTS_DIR,
+        &hash[..2.min(hash.len())],
+        hash
+    )
+}
+
+/// Download the asset manifest. A missing manifest yields an empty one.
+pub async fn download_manifest(token: &str, repo_url: &str) -> Result<AssetManifest, SyncError> {
+    let bytes = download(token, repo_url, Some(ASSET_MANIFEST_FILE)).await?;
+    if bytes.is_empty() {
+        return Ok(AssetManifest::default());
+    }
+    serde_json::from_slice(&bytes)
+        .map_err(|e| SyncError::GitHubApi(format!("Invalid asset manifest: {}", e)))
+}
+
+/// Upload the given local asset paths (relative to `app_dir`).
+///
+/// Only files whose hash differs from the remote manifest are sent, and an
+/// object already stored under another path is reused instead of uploaded
+/// again. The manifest is rewritten once at the end if anything changed.
+#[tracing::instrument(skip(token, paths), fields(count = paths.len()))]
+pub async fn upload_assets(
+    token: &str,
+    repo_url: &str,
+    app_dir: &Path,
+    paths: &HashSet<String>,
+    size_cap: u64,
+) -> Result<AssetSyncResult, SyncError> {
+    let (owner, repo) = parse_repo_url(repo_url)?;
+    let client = Client::new();
+    let mut manifest = download_manifest(token, repo_url).await?;
+    let mut known: HashMap<String, AssetStorage> = manifest
+        .files
+        .values()
+        .map(|e| (e.hash.clone(), e.storage))
+        .collect();
+    let mut release: Option<Release> = None;
+    let mut result = AssetSyncResult::default();
+
+    let mut paths: Vec<&String> = paths.iter().collect();
+    paths.sort();
+
+    for path in paths {
+        let full_path = app_dir.join(path);
+        let size = match tokio::fs::metadata(&full_path).await {
+            Ok(metadata) => metadata.len(),
+            Err(_) => {
+                result.missing.push(path.clone());
+                continue;
+            }
+        };
+        if size > size_cap {
+            result.oversized.push(path.clone());
+            continue;
+        }
+
+        let hash = {
+            let full_path = full_path.clone();
+            tokio::task::spawn_blocking(move || crate::api::hash_file(full_path))
+                .await
+                .map_err(std::io::Error::other)??
+        };
+
+        if manifest.files.get(path).is_some_and(|e| e.hash == hash) {
+            result.skipped.push(path.clone());
+            continue;
+        }
+
+        let storage = match known.get(&hash) {
+            Some(storage) => *storage,
+            None => {
+                let uploaded = if size <= CONTENTS_API_MAX_SIZE {
+                    let content = tokio::fs::read(&full_path).await?;
+                    update(
+                        token,
+                        repo_url,
+                        content,
+                        Some(&object_path(&hash)),
+                        Some(&format!("Add asset {}", path)),
+                        None,
+                    )
+                    .await
+                    .map(|_| AssetStorage::Repo)
+                } else {
+                    let current = match release.take() {
+                        Some(r) => r,
+                        None => get_or_create_release(&client, token, &owner, &repo).await?,
+                    };
+                    let uploaded = upload_release_asset(
+                        &client, token, &owner, &repo, &current, &hash, &full_path,
+                    )
+                    .await
+                    .map(|_| AssetStorage::Release);
+                    release = Some(current);
+                    uploaded
+                };
+                match uploaded {
+                    Ok(storage) => storage,
+                    Err(e) => {
+                        error!("Failed to upload asset {}: {}", path, e);
+                        result.failed.push(path.clone());
+                        continue;
+                    }
+                }
+            }
+        };
+
+        known.insert(hash.clone(), storage);
+        manifest.files.insert(
+            path.clone(),
+            AssetEntry {
+                hash,
+                size,
+                storage,
+            },
+        );
+        result.transferred.push(path.clone());
+    }
+
+    if !result.transferred.is_empty() {
+        let content = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| SyncError::GitHubApi(e.to_string()))?;
+        update(
+            token,
+            repo_url,
+            content,
+            Some(ASSET_MANIFEST_FILE),
+            Some(&format!("Sync {} assets", result.transferred.len())),
+            None,
+        )
+        .await?;
+    }
+
+    Ok(result)
+}
+
+/// Whether `path` stays inside the directory it is joined to.
+fn is_app_path(path: &str) -> bool {
+    Path::new(path)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Download the given local asset paths (relative to `app_dir`) that are
+/// missing on disk but listed in the remote manifest. Every object is
+/// verified against its hash before it is written, and never outside
+/// `app_dir`.
+#[tracing::instrument(skip(token, paths), fields(count = paths.len()))]
+pub async fn download_assets(
+    token: &str,
+    repo_url: &str,
+    app_dir: &Path,
+    paths: &HashSet<String>,
+    size_cap: u64,
+) -> Result<AssetSyncResult, SyncError> {
+    let (owner, repo) = parse_repo_url(repo_url)?;
+    let client = Client::new();
+    let manifest = download_manifest(token, repo_url).await?;
+    let mut release: Option<Option<Release>> = None;
+    let mut result = AssetSyncResult::default();
+
+    let mut paths: Vec<&String> = paths.iter().collect();
+    paths.sort();
+
+    for path in paths {
+        if !is_app_path(path) {
+            warn!("Not downloading {} outside the app directory", path);
+            result.skipped.push(path.clone());
+            continue;
+        }
+        let full_path = app_dir.join(path);
+        if tokio::fs::try_exists(&full_path).await.unwrap_or(false) {
+            result.skipped.push(path.clone());
+            continue;
+        }
+        let Some(entry) = manifest.files.get(path) else {
+            result.missing.push(path.clone());
+            continue;
+        };
+        if entry.size > size_cap {
+            result.oversized.push(path.clone());
+            continue;
+        }
+
+        // Written next to the target and renamed once verified
+        let mut part = full_path.clone().into_os_string();
+        part.push(".part");
+        let part = PathBuf::from(part);
+
+        let downloaded: Result<Option<String>, SyncError> = async {
+            if let Some(parent) = full_path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            match entry.storage {
+                AssetStorage::Repo => {
+                    let bytes = download(token, repo_url, Some(&object_path(&entry.hash))).await?;
+                    if bytes.is_empty() {
+                        return Ok(None);
+                    }
+                    let hash = format!("{:x}", Sha256::digest(&bytes));
+                    tokio::fs::write(&part, bytes).await?;
+                    Ok(Some(hash))
+                }
+                AssetStorage::Release => {
+                    // A failed lookup is retried by the next release asset
+                    if release.is_none() {
+                        release = Some(get_release(&client, token, &owner, &repo).await?);
+                    }
+                    match release
+                        .as_ref()
+                        .and_then(|r| r.as_ref())
+                        .and_then(|r| r.assets.iter().find(|a| a.name == entry.hash))
+                    {
+                        Some(asset) => {
+                            download_release_asset(&client, token, &owner, &repo, asset.id, &part)
+                                .await
+                                .map(Some)
+                        }
+                        None => Err(SyncError::FileNotFound(entry.hash.clone())),
+                    }
+                }
+            }
+        }
+        .await;
+
+        let hash = match downloaded {
+            Ok(Some(hash)) => hash,
+            Ok(None) => {
+                result.missing.push(path.clone());
+                continue;
+            }
+            Err(e) => {
+                error!("Failed to download asset {}: {}", path, e);
+                let _ = tokio::fs::remove_file(&part).await;
+                result.failed.push(path.clone());
+                continue;
+            }
+        };
+        if hash != entry.hash {
+            error!("{}", SyncError::HashMismatch(path.clone()));
+            let _ = tokio::fs::remove_file(&part).await;
+            result.failed.push(path.clone());
+            continue;
+        }
+
+        if let Err(e) = tokio::fs::rename(&part, &full_path).await {
+            error!("Failed to move asset {} into place: {}", path, e);
+            let _ = tokio::fs::remove_file(&part).await;
+            result.failed.push(path.clone());
+            continue;
+        }
+        result.transferred.push(path.clone());
+    }
+
+    Ok(result)
+}
+
+/// Look up the asset release, returning `None` if it does not exist yet.
+async fn get_release(
+    client: &Client,
+    token: &str,
+    owner: &str,
+    repo: &str,
+) -> Result<Option<Release>, SyncError> {
+    let url = format!(
+        "https://api.github.com/repos/{}/{}/releases/tags/{}",
+        owner, repo, ASSET_RELEASE_TAG
+    );
+
+    let response = client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .header("User-Agent", "musicfree-tauri")
+        .header("Accept", "application/vnd.github.v3+json")
+        .send()
+        .await?;
+
+    let status = response.status();
+    if status.as_u16() == 404 {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        return Err(status_error(response, None).await);
+    }
+
+    Ok(Some(response.json().await?))
+}
+
+async fn get_or_create_release(
+    client: &Client,
+    token: &str,
+    owner: &str,
+    repo: &str,
+) -> Result<Release, SyncError> {
+    if let Some(release) = get_release(client, token, owner, repo).await? {
+        return Ok(release);
+    }
+
+    let url = format!("https://api.github.com/repos/{}/{}/releases", owner, repo);
+    let payload = CreateReleasePayload {
+        tag_name: ASSET_RELEASE_TAG,
+        name: ASSET_RELEASE_TAG,
+        body: "Large audio files synced by MusicFree",
+    };
+
+    let response = client
+        .post(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .header("User-Agent", "musicfree-tauri")
+        .header("Accept", "application/vnd.github.v3+json")
+        .json(&payload)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(status_error(response, Some("Failed to create release")).await);
+    }
+
+    Ok(response.json().await?)
+}
+
+async fn upload_release_asset(
+    client: &Client,
+    token: &str,
+    owner: &str,
+    repo: &str,
+    release: &Release,
+    name: &str,
+    path: &Path,
+) -> Result<(), SyncError> {
+    // A previous run may have uploaded the object without updating the manifest
+    if release.assets.iter().any(|a| a.name == name) {
+        return Ok(());
+    }
+
+    let url = format!(
+        "https://uploads.github.com/repos/{}/{}/releases/{}/assets?name={}",
+        owner, repo, release.id, name
+    );
+
+    // Streamed from disk rather than read into memory
+    let file = tokio::fs::File::open(path).await?;
+    let size = file.metadata().await?.len();
+    let response = client
+        .post(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .header("User-Agent", "musicfree-tauri")
+        .header("Accept", "application/vnd.github.v3+json")
+        .header("Content-Type", "application/octet-stream")
+        .header("Content-Length", size)
+        .body(reqwest::Body::from(file))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(status_error(
+            response,
+            Some(&format!("Failed to upload release asset {}", name)),
+        )
+        .await);
+    }
+
+    Ok(())
+}
+
+async fn download_release_asset(
+    client: &Client,
+    token: &str,
+    owner: &str,
+    repo: &str,
+    asset_id: u64,
+    target: &Path,
+) -> Result<String, SyncError> {
+    let url = format!(
+        "https://api.github.com/repos/{}/{}/releases/assets/{}",
+        owner, repo, asset_id
+    );
+
+    // GitHub redirects to object storage; reqwest drops the token on the
+    // cross-host redirect.
+    let mut response = client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .header("User-Agent", "musicfree-tauri")
+        .header("Accept", "application/octet-stream")
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(status_error(response, None).await);
+    }
+
+    // Streamed to disk, hashing along the way, to keep large files out of memory
+    let mut file = tokio::fs::File::create(target).await?;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = response.chunk().await? {
+        hasher.update(&chunk);
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
@@ -0,0 +1,145 @@
+use crate::{
+    decode::{Spec, decode},
+    error::{AppError, AppResult},
+};
+use std::path::Path;
+use trackex::format::OutputFormat;
+
+pub fn output_format(format: &str) -> AppResult<OutputFormat> {
+    match format {
+        "mp3" => Ok(OutputFormat::Mp3),
+        "ogg" => Ok(OutputFormat::Ogg),
+        "wav" => Ok(OutputFormat::Wav),
+        _ => Err(AppError::Transcode {
+            message: format!("Unsupported format: {format}"),
+            source: None,
+        }),
+    }
+}
+
+/// Run encoded audio through trackex into `format`.
+/// Blocking; run it on a blocking thread.
+pub fn encode(input_data: Vec<u8>, format: &str) -> AppResult<Vec<u8>> {
+    let config = trackex::config::AudioConfig {
+        input_data,
+        format: output_format(format)?,
+        sample_rate: None,
+        channels: None,
+    };
+    trackex::extract_audio(&config).map_err(|e| AppError::transcode("Transcode failed", e))
+}
+
+fn to_i16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+}
+
+/// 16-bit PCM WAV of interleaved samples.
+pub fn to_wav(spec: Spec, samples: &[f32]) -> Vec<u8> {
+    let samples: Vec<i16> = samples.iter().copied().map(to_i16).collect();
+    wav(spec, &samples)
+}
+
+fn wav(spec: Spec, samples: &[i16]) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let block_align = spec.channels as u16 * 2;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&(spec.channels as u16).to_le_bytes());
+    wav.extend_from_slice(&spec.sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(spec.sample_rate * block_align as u32).to_le_bytes());
+    wav.extend_from_slice(&block_align.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+fn no_audio(path: &Path) -> AppError {
+    AppError::Transcode {
+        message: format!("No audio decoded from {}", path.display()),
+        source: None,
+    }
+}
+
+fn nothing_between(start_ms: u64, end_ms: Option<u64>) -> AppError {
+    let end = end_ms.map_or("the end".to_string(), |ms| format!("{ms}ms"));
+    AppError::Transcode {
+        message: format!("Nothing between {start_ms}ms and {end}"),
+        source: None,
+    }
+}
+
+/// A whole file decoded to 16-bit PCM, to cut several parts out of a
+/// single decode.
+pub struct Pcm {
+    spec: Spec,
+    samples: Vec<i16>,
+}
+
+impl Pcm {
+    /// Blocking; run it on a blocking thread.
+    pub fn decode(path: &Path) -> AppResult<Self> {
+        let mut spec = None;
+        let mut samples = Vec::new();
+        decode(path, |packet_spec, packet| {
+            spec.get_or_insert(packet_spec);
+            samples.extend(packet.iter().copied().map(to_i16));
+            Ok(())
+        })?;
+        let spec = spec.ok_or_else(|| no_audio(path))?;
+        Ok(Self { spec, samples })
+    }
+
+    /// The part between `start_ms` and `end_ms` (the end when `None`) as WAV.
+    pub fn cut(&self, start_ms: u64, end_ms: Option<u64>) -> AppResult<Vec<u8>> {
+        let channels = self.spec.channels;
+        let frames = self.samples.len() / channels;
+        let frame = |ms: u64| ((ms * self.spec.sample_rate as u64 / 1000) as usize).min(frames);
+        let (from, to) = (frame(start_ms), end_ms.map_or(frames, frame));
+        if from >= to {
+            return Err(nothing_between(start_ms, end_ms));
+        }
+        Ok(wav(
+            self.spec,
+            &self.samples[from * channels..to * channels],
+        ))
+    }
+}
+
+/// Decode the part of a file between `start_ms` and `end_ms` (the end of
+/// the file when `None`) as WAV.
+/// Blocking; run it on a blocking thread.
+pub fn cut(path: &Path, start_ms: u64, end_ms: Option<u64>) -> AppResult<Vec<u8>> {
+    let mut spec = None;
+    let mut samples = Vec::new();
+    // Position in frames, counted per channel
+    let mut position: u64 = 0;
+    decode(path, |packet_spec, packet| {
+        let spec = *spec.get_or_insert(packet_spec);
+        let rate = spec.sample_rate as u64;
+        let (start, end) = (
+            start_ms * rate / 1000,
+            end_ms.map_or(u64::MAX, |ms| ms * rate / 1000),
+        );
+        let frames = (packet.len() / spec.channels) as u64;
+        let from = start.clamp(position, position + frames) - position;
+        let to = end.clamp(position, position + frames) - position;
+        samples
+            .extend_from_slice(&packet[from as usize * spec.channels..to as usize * spec.channels]);
+        position += frames;
+        Ok(())
+    })?;
+
+    let spec = spec.ok_or_else(|| no_audio(path))?;
+    if samples.is_empty() {
+        return Err(nothing_between(start_ms, end_ms));
+    }
+    Ok(to_wav(spec, &samples))
+}
@@ -9,7 +9,7 @@ import "@fontsource/roboto/300.css"
 import "@fontsource/roboto/400.css"
 import "@fontsource/roboto/500.css"
 import "@fontsource/roboto/700.css"
-import { is_android } from "./api"
+import { is_android, get_error_message } from "./api"
 import { invoke } from "@tauri-apps/api/core"
 import { useMessage } from "./contexts/MessageContext"
 
@@ -29,7 +29,7 @@ const AppInitializer: FC = () => {
           }
         })
         .catch((err) => {
-          message.error(`Storage permission request failed: ${String(err)}`, 5000)
+          message.error(`Storage permission request failed: ${get_error_message(err)}`, 5000)
         })
     }
   }, [message])
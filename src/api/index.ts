@@ -1,4 +1,5 @@
 import { invoke, convertFileSrc } from "@tauri-apps/api/core"
+import { listen, UnlistenFn } from "@tauri-apps/api/event"
 import { join } from "@tauri-apps/api/path"
 import { platform, hostname } from "@tauri-apps/plugin-os"
 import { getWavUrl, isAudio, isVideo } from "./audio"
@@ -28,10 +29,48 @@ export type Audio = {
   format?: string
 }
 
+/** Stream properties read from the downloaded file */
+export type AudioInfo = {
+  /** Seconds */
+  duration?: number
+  /** e.g. mp3, aac, opus or pcm */
+  codec: string
+  /** e.g. mpeg, mp4, ogg or wav */
+  container: string
+  /** Kbps */
+  bitrate?: number
+  /** Hz */
+  sample_rate?: number
+  channels?: number
+}
+
+/** EBU R128 measurements, gains in dB and peaks linear */
+export type Loudness = {
+  /** LUFS */
+  integrated: number
+  true_peak: number
+  track_gain: number
+  album_gain?: number
+  album_peak?: number
+}
+
 export type LocalAudio = {
   audio: Audio
+  /** Relative to the app directory, and the key of the audio in reports */
   path: string
+  /** Absolute path of a file referenced in place, never copied, synced or deleted */
+  external_path?: string
   cover_path: string | null
+  info?: AudioInfo
+  loudness?: Loudness
+  /** Where the audible part starts, in ms */
+  start_ms?: number
+  /** Where the audible part ends, in ms */
+  end_ms?: number
+  /** Stored LRC file, relative to the app directory */
+  lyrics_path?: string
+  /** Acoustic fingerprint, base64 */
+  fingerprint?: string
 }
 
 export type SyncedLocalAudio = LocalAudio & {
@@ -78,10 +117,12 @@ export type Config = {
 
 export type GistConfig = {
   repoUrl: string // GitHub repository URL (e.g., "https://github.com/owner/repo" or "owner/repo")
-  githubToken: string
+  /** @deprecated The token lives in the Rust credential store; only read to migrate old configs */
+  githubToken?: string
   syncInterval: number // in minutes
   lastSyncTime?: number
   lastRemoteSha?: string // SHA of the remote file at last sync, used for change detection
+  syncHistory?: boolean // also merge and upload the play history
 }
 
 export type FileInfo = {
@@ -89,10 +130,75 @@ export type FileInfo = {
   size: number
 }
 
+/** How an imported backup is combined with the local library */
+export type ImportMode = "merge" | "replace" | "add_missing"
+
 export type ImportResult = {
+  /** Playlists to apply, already narrowed to what `mode` adds */
   config: Config
   filename: string
   paths: string[]
+  mode: ImportMode
+}
+
+export type BackupInfo = {
+  path: string
+  filename: string
+  created_at: string
+  size: number
+  playlist_count: number
+  track_count: number
+  kind?: "full" | "incremental"
+  scope?: BackupScope
+}
+
+export type BackupPreview = {
+  info: BackupInfo
+  config: Config
+}
+
+export type AppErrorKind =
+  | "io"
+  | "serialization"
+  | "path"
+  | "network"
+  | "timeout"
+  | "not_found"
+  | "permission"
+  | "extractor"
+  | "transcode"
+  | "archive"
+  | "conflict"
+  | "cancelled"
+  | "invalid"
+  | "parse"
+  | "unknown"
+
+/** Error rejected by every Rust command */
+export type AppError = {
+  kind: AppErrorKind
+  message: string
+  /** The same call may succeed later without user action */
+  retryable: boolean
+  /** Messages of the underlying causes, outermost first */
+  context: string[]
+}
+
+export function is_app_error(error: unknown): error is AppError {
+  return (
+    typeof error === "object" &&
+    error !== null &&
+    "kind" in error &&
+    "message" in error &&
+    "retryable" in error
+  )
+}
+
+export function get_error_message(error: unknown): string {
+  if (is_app_error(error) || error instanceof Error) {
+    return error.message
+  }
+  return String(error)
 }
 
 export function get_default_config(deviceId?: string): Config {
@@ -109,8 +215,8 @@ export function extract_audios(
   return invoke("extract_audios", { url })
 }
 
-export function download_audio(audio: Audio): Promise<LocalAudio> {
-  return invoke("download_audio", { audio })
+export function download_audio(audio: Audio, extractAudio?: boolean): Promise<LocalAudio> {
+  return invoke("download_audio", { audio, extractAudio })
 }
 
 export function app_dir(): Promise<string> {
@@ -222,6 +328,11 @@ export function get_web_url(path: string): Promise<string> {
   return is_android() ? get_musicfree_url(path) : get_convert_url(path)
 }
 
+/** Playable URL of an audio, files referenced in place go through the asset protocol */
+export async function get_audio_url(audio: LocalAudio): Promise<string> {
+  return audio.external_path ? convertFileSrc(audio.external_path) : get_web_url(audio.path)
+}
+
 export const DEFAULT_COVER_URL = "/icon.png"
 
 export function get_config(): Promise<Config> {
@@ -259,40 +370,226 @@ export function exists_cover(url: string, platform: Platform): Promise<string |
   return invoke("exists_cover", { url, platform })
 }
 
-export function export_data(): Promise<string> {
-  return invoke("export_data")
+export type BackupScope = "full" | "config" | "playlists"
+export type BackupCompression = "stored" | "deflate" | "zstd"
+
+export type ExportOptions = {
+  scope?: BackupScope
+  /** Playlists to include when scope is "playlists" */
+  playlist_ids?: string[]
+  /** Store only assets missing from the latest backup */
+  incremental?: boolean
+  /** Compression for JSON entries; audio is always stored */
+  compression?: BackupCompression
+  /** Store the play history next to the config */
+  include_history?: boolean
+}
+
+export function export_data(options?: ExportOptions, taskId?: string): Promise<string> {
+  return invoke("export_data", { options, taskId })
+}
+
+/** Backups in the download directory, newest first */
+export function list_backups(): Promise<BackupInfo[]> {
+  return invoke("list_backups")
+}
+
+/** Read a backup's playlists without importing anything */
+export function inspect_backup(path: string): Promise<BackupPreview> {
+  return invoke("inspect_backup", { path })
+}
+
+/** Import a backup; the newest one in the download directory when `path` is omitted */
+export function import_data(
+  path?: string,
+  mode: ImportMode = "merge",
+  taskId?: string,
+): Promise<ImportResult> {
+  return invoke("import_data", { path, mode, taskId })
+}
+
+/** Progress of a long-running task, in bytes */
+export type TaskProgress = {
+  id: string
+  task: string
+  done: number
+  total: number
+  current?: string
+  finished: boolean
+}
+
+/** Follow the progress of the task started with `taskId` */
+export function listen_task_progress(
+  taskId: string,
+  onProgress: (progress: TaskProgress) => void,
+): Promise<UnlistenFn> {
+  return listen<TaskProgress>("task-progress", (event) => {
+    if (event.payload.id === taskId) onProgress(event.payload)
+  })
+}
+
+export type PlaylistFormat = "m3u" | "xspf" | "pls" | "musicfree" | "json"
+
+export type PlaylistImport = {
+  format: PlaylistFormat
+  playlists: LocalPlaylist[]
+  /** Entries that could not be resolved, as `title (location)` */
+  failed: string[]
+}
+
+/** Import an M3U/M3U8, XSPF or PLS playlist, or a MusicFree mobile app backup */
+export function import_playlist_file(path: string, taskId?: string): Promise<PlaylistImport> {
+  return invoke("import_playlist_file", { path, taskId })
+}
+
+export type ScanResult = {
+  /** One playlist per scanned folder */
+  playlists: LocalPlaylist[]
+  /** Files that could not be read */
+  failed: string[]
+}
+
+/**
+ * Scan folders for audio files, one playlist per folder.
+ * Files are referenced in place unless `copy` is set (always copied on Android).
+ */
+export function scan_local_folders(
+  paths: string[],
+  copy = false,
+  taskId?: string,
+): Promise<ScanResult> {
+  return invoke("scan_local_folders", { paths, copy, taskId })
+}
+
+/** Audio files added to, changed in or removed from a watched folder */
+export type LibraryChange = {
+  playlist_id: string
+  added: LocalAudio[]
+  /** Audios of the playlist whose file changed */
+  updated: LocalAudio[]
+  /** Ids of the removed audios */
+  removed: string[]
+}
+
+/** Watch folders for added and removed audio files; resolves every watched folder */
+export function watch_local_folders(paths: string[], copy = false): Promise<string[]> {
+  return invoke("watch_local_folders", { paths, copy })
+}
+
+/** Stop watching `paths`, or every folder when omitted */
+export function unwatch_local_folders(paths?: string[]): Promise<void> {
+  return invoke("unwatch_local_folders", { paths })
+}
+
+export function listen_library_changes(
+  onChange: (change: LibraryChange) => void,
+): Promise<UnlistenFn> {
+  return listen<LibraryChange>("library-changed", (event) => onChange(event.payload))
 }
 
-export function import_data(): Promise<ImportResult> {
-  return invoke("import_data")
+/** Cancel a running export or import; resolves `false` if it already finished */
+export function cancel_task(id: string): Promise<boolean> {
+  return invoke("cancel_task", { id })
+}
+
+/** Write a diagnostics zip (logs, system info, redacted config) and return its path */
+export function export_diagnostics(): Promise<string> {
+  return invoke("export_diagnostics")
 }
 
 export function remove_file(path: string): Promise<void> {
   return invoke("remove_file", { path })
 }
 
-export function sync_download(token: string, repo: string, path?: string): Promise<Uint8Array> {
-  return invoke<number[]>("sync_download", { token, repo, path }).then(
-    (data) => new Uint8Array(data),
-  )
+export type SyncStatus = {
+  configured: boolean
+  repo?: string
+  backend?: "keyring" | "file"
 }
 
+/** Store the GitHub token and repository on the Rust side */
+export function set_sync_credentials(token: string, repo: string): Promise<SyncStatus> {
+  return invoke("set_sync_credentials", { token, repo })
+}
+
+export function clear_sync_credentials(): Promise<void> {
+  return invoke("clear_sync_credentials")
+}
+
+export function sync_status(): Promise<SyncStatus> {
+  return invoke("sync_status")
+}
+
+export function sync_download(path?: string): Promise<Uint8Array> {
+  return invoke<number[]>("sync_download", { path }).then((data) => new Uint8Array(data))
+}
+
+/** Upload a file to the sync repo, tagging the commit with `deviceId` (the config's `_deviceId`) */
 export function sync_update(
-  token: string,
-  repo: string,
   content: Uint8Array,
   path?: string,
   message?: string,
+  deviceId?: string,
 ): Promise<void> {
-  return invoke("sync_update", { token, repo, content, path, message })
+  return invoke("sync_update", { content, path, message, deviceId })
 }
 
-export function sync_file_info(
-  token: string,
-  repo: string,
-  path?: string,
-): Promise<FileInfo | null> {
-  return invoke("sync_file_info", { token, repo, path })
+export function sync_file_info(path?: string): Promise<FileInfo | null> {
+  return invoke("sync_file_info", { path })
+}
+
+export type SyncCommit = {
+  sha: string
+  date: string
+  message: string
+  device?: string
+}
+
+export type PlaylistChange = {
+  title: string
+  added: number
+  removed: number
+}
+
+export type ConfigDiff = {
+  added_playlists: string[]
+  removed_playlists: string[]
+  changed_playlists: PlaylistChange[]
+}
+
+export type SyncRestore = {
+  sha: string
+  /** The revision as stored, older ones may lack the LWW stamps; saving renews them */
+  config: Omit<Config, "_updatedAt" | "_deviceId"> & Partial<Config>
+  diff: ConfigDiff
+}
+
+/** List commits that touched the sync file, newest first */
+export function sync_history(path?: string, limit?: number): Promise<SyncCommit[]> {
+  return invoke("sync_history", { path, limit })
+}
+
+/** Fetch the sync file at a past commit without applying it */
+export function sync_restore(sha: string, path?: string): Promise<SyncRestore> {
+  return invoke("sync_restore", { sha, path })
+}
+
+export type AssetSyncResult = {
+  transferred: string[]
+  skipped: string[]
+  oversized: string[]
+  missing: string[]
+  failed: string[]
+}
+
+/** Upload used audio/cover files that changed since the last asset sync */
+export function sync_upload_assets(sizeCap?: number): Promise<AssetSyncResult> {
+  return invoke("sync_upload_assets", { sizeCap })
+}
+
+/** Download used audio/cover files that are missing locally */
+export function sync_download_assets(sizeCap?: number): Promise<AssetSyncResult> {
+  return invoke("sync_download_assets", { sizeCap })
 }
 
 // ============================================
@@ -313,6 +610,13 @@ export async function get_device_id(): Promise<string> {
 
 export { syncConfig, SyncError } from "./sync"
 
+/** Folders of the local library, kept across restarts */
+export type LocalFolders = {
+  paths: string[]
+  watch: boolean
+  copy: boolean
+}
+
 // ============================================
 // LocalStorage Keys
 // ============================================
@@ -323,6 +627,10 @@ const STORAGE_KEYS = {
   THEME: "musicfree_theme",
   GIST_CONFIG: "musicfree_repo_sync_config",
   TRANSCODE_FORMAT: "musicfree_transcode_format",
+  LOCAL_FOLDERS: "musicfree_local_folders",
+  REPLAY_GAIN: "musicfree_replay_gain",
+  SKIP_SILENCE: "musicfree_skip_silence",
+  EXTRACT_AUDIO: "musicfree_extract_audio",
 } as const
 
 // ============================================
@@ -387,6 +695,35 @@ export const storage = {
   getTranscodeFormat: (): TranscodeFormat => {
     return (localStorage.getItem(STORAGE_KEYS.TRANSCODE_FORMAT) as TranscodeFormat) || "original"
   },
+  setReplayGain: (mode: ReplayGainMode) => {
+    localStorage.setItem(STORAGE_KEYS.REPLAY_GAIN, mode)
+  },
+  getReplayGain: (): ReplayGainMode => {
+    return (localStorage.getItem(STORAGE_KEYS.REPLAY_GAIN) as ReplayGainMode) || "off"
+  },
+  setSkipSilence: (skip: boolean) => {
+    localStorage.setItem(STORAGE_KEYS.SKIP_SILENCE, String(skip))
+  },
+  getSkipSilence: (): boolean => {
+    return localStorage.getItem(STORAGE_KEYS.SKIP_SILENCE) === "true"
+  },
+  setExtractAudio: (extract: boolean) => {
+    localStorage.setItem(STORAGE_KEYS.EXTRACT_AUDIO, String(extract))
+  },
+  getExtractAudio: (): boolean => {
+    return localStorage.getItem(STORAGE_KEYS.EXTRACT_AUDIO) === "true"
+  },
+  setLocalFolders: (folders: LocalFolders) => {
+    localStorage.setItem(STORAGE_KEYS.LOCAL_FOLDERS, JSON.stringify(folders))
+  },
+  getLocalFolders: (): LocalFolders => {
+    try {
+      const data = localStorage.getItem(STORAGE_KEYS.LOCAL_FOLDERS)
+      return data ? JSON.parse(data) : { paths: [], watch: false, copy: false }
+    } catch {
+      return { paths: [], watch: false, copy: false }
+    }
+  },
 }
 
 // Log API
@@ -406,12 +743,56 @@ export function get_log_size(): Promise<number> {
   return invoke("get_log_size")
 }
 
+/** Tail of the active log file (at most 1 MB) */
 export function read_log(): Promise<string> {
   return invoke("read_log")
 }
 
-export function save_audio(playlistId: string, audioId?: string): Promise<string[]> {
-  return invoke("save_audio", { playlistId, audioId })
+export type LogLevel = "trace" | "debug" | "info" | "warn" | "error"
+
+export type LogEntry = {
+  timestamp: string
+  level: string
+  module: string
+  message: string
+  fields?: Record<string, unknown>
+  span?: Record<string, unknown>
+}
+
+/**
+ * Query the structured log, newest entries last.
+ * @param level minimum level
+ * @param module substring of the module or Rust target
+ * @param since only entries at or after this time (Date.now() millis)
+ */
+export function query_logs(
+  level?: LogLevel,
+  module?: string,
+  since?: number,
+  limit?: number,
+): Promise<LogEntry[]> {
+  return invoke("query_logs", { level, module, since, limit })
+}
+
+/** Change the Rust log filter, e.g. "debug" or "info,musicfree_tauri_lib::sync=trace" */
+export function set_log_level(level: string): Promise<void> {
+  return invoke("set_log_level", { level })
+}
+
+export function save_audio(
+  playlistId: string,
+  audioId?: string,
+  replayGain = false,
+): Promise<string[]> {
+  return invoke("save_audio", { playlistId, audioId, replayGain })
+}
+
+/**
+ * Save a playlist's audios like `save_audio` and write a playlist file with
+ * relative paths next to them. Resolves to the playlist file path.
+ */
+export function export_playlist(playlistId: string, format: PlaylistFormat): Promise<string> {
+  return invoke("export_playlist", { playlistId, format })
 }
 
 // ============================================
@@ -420,6 +801,9 @@ export function save_audio(playlistId: string, audioId?: string): Promise<string
 
 export type TranscodeFormat = "original" | "mp3" | "ogg" | "wav"
 
+/** Loudness normalization during playback */
+export type ReplayGainMode = "off" | "track" | "album"
+
 /** Map TranscodeFormat to the format string stored in Audio.format */
 export function transcode_format_to_audio_format(fmt: TranscodeFormat): string {
   switch (fmt) {
@@ -434,6 +818,257 @@ export function transcode_format_to_audio_format(fmt: TranscodeFormat): string {
   }
 }
 
+/** Read the stream properties of a file in the app directory */
+export function probe_audio(path: string): Promise<AudioInfo> {
+  return invoke("probe_audio", { path })
+}
+
+export type LoudnessReport = {
+  /** Newly measured files, by audio path */
+  tracks: Record<string, Loudness>
+  /** [album gain, album peak] by playlist id */
+  albums: Record<string, [number, number]>
+}
+
+/** Measure the loudness of every audio not analyzed yet and the album gain of every playlist */
+export function analyze_loudness(taskId?: string): Promise<LoudnessReport> {
+  return invoke("analyze_loudness", { taskId })
+}
+
+/** Playback gain in dB, `null` until the audio was analyzed */
+export function replay_gain(
+  playlistId: string,
+  audioId: string,
+  album = false,
+): Promise<number | null> {
+  return invoke("replay_gain", { playlistId, audioId, album })
+}
+
+export type SilenceBounds = {
+  start_ms: number
+  end_ms: number
+}
+
+/** Detect the leading and trailing silence of every audio not analyzed yet, by audio path */
+export function detect_silence(taskId?: string): Promise<Record<string, SilenceBounds>> {
+  return invoke("detect_silence", { taskId })
+}
+
+/** Fingerprint every audio without a fingerprint yet, by audio path */
+export function fingerprint_audios(taskId?: string): Promise<Record<string, string>> {
+  return invoke("fingerprint_audios", { taskId })
+}
+
+export type TrackMatch = {
+  /** The same recording, highest bitrate first */
+  audios: LocalAudio[]
+  similarity: number
+}
+
+/** Find audios holding the same recording, only across platforms unless `crossPlatform` is false */
+export function match_tracks(crossPlatform?: boolean): Promise<TrackMatch[]> {
+  return invoke("match_tracks", { crossPlatform })
+}
+
+export type PlayRecord = {
+  audio_id: string
+  playlist_id: string
+  platform?: Platform
+  /** Seconds into the audio when playback stopped */
+  position: number
+  duration: number
+  /** Unix milliseconds */
+  played_at: number
+}
+
+export type TrackStats = {
+  audio_id: string
+  platform?: Platform
+  plays: number
+  listen_secs: number
+  /** Unix milliseconds */
+  last_played: number
+}
+
+export type GroupStats = {
+  /** Playlist id, platform, `2024-05-01` or ISO week `2024-W18` */
+  key: string
+  plays: number
+  listen_secs: number
+}
+
+export type HistoryStats = {
+  plays: number
+  listen_secs: number
+  /** Most played first */
+  tracks: TrackStats[]
+  /** Most listened first */
+  playlists: GroupStats[]
+  platforms: GroupStats[]
+  /** Oldest first */
+  daily: GroupStats[]
+  weekly: GroupStats[]
+}
+
+/** Append a playback to the play history, `position` being where it stopped in seconds */
+export function record_play(
+  audioId: string,
+  platform: Platform,
+  playlistId: string,
+  position: number,
+  duration: number,
+): Promise<void> {
+  return invoke("record_play", { audioId, platform, playlistId, position, duration })
+}
+
+/** The last play of each recently played audio, most recent first */
+export function recent_plays(limit?: number): Promise<PlayRecord[]> {
+  return invoke("recent_plays", { limit })
+}
+
+/** Plays and listening time over the last `days`, or all time */
+export function play_stats(days?: number, limit?: number): Promise<HistoryStats> {
+  return invoke("play_stats", { days, limit })
+}
+
+/** Merge the play history with the synced copy and upload it, returning the record count */
+export function sync_play_history(deviceId?: string): Promise<number> {
+  return invoke("sync_play_history", { deviceId })
+}
+
+/** Write the part of an audio between `startMs` and `endMs` to a new file, returning its path */
+export function trim_audio(
+  inputPath: string,
+  startMs: number,
+  endMs?: number,
+  format?: TranscodeFormat,
+): Promise<string> {
+  return invoke("trim_audio", { inputPath, startMs, endMs, format })
+}
+
+/** A part of a track to cut out, in ms; `end_ms` omitted for the end of the file */
+export type Segment = {
+  title: string
+  start_ms: number
+  end_ms?: number
+}
+
+/** Read a pasted `mm:ss Title` list, each segment ending where the next starts */
+export function parse_timestamps(text: string): Promise<Segment[]> {
+  return invoke("parse_timestamps", { text })
+}
+
+/** Cut a downloaded audio into one track per segment, returned as a new playlist */
+export function split_audio(
+  audioId: string,
+  segments: Segment[],
+  taskId?: string,
+): Promise<LocalPlaylist> {
+  return invoke("split_audio", { audioId, segments, taskId })
+}
+
 export function transcode_audio(inputPath: string, format: TranscodeFormat): Promise<string> {
   return invoke("transcode_audio", { inputPath, format })
 }
+
+/** A word of an enhanced LRC line */
+export type LyricWord = {
+  time_ms: number
+  text: string
+}
+
+export type LyricLine = {
+  /** 0 for every line of unsynced lyrics */
+  time_ms: number
+  text: string
+  words?: LyricWord[]
+}
+
+export type Lyrics = {
+  lines: LyricLine[]
+  /** The `[offset:]` tag, already applied to the lines */
+  offset_ms: number
+  synced: boolean
+  title?: string
+  artist?: string
+}
+
+/**
+ * Copy an .lrc file into the lyrics store, returning the audio pointing at it.
+ * SRT, WebVTT and Bilibili JSON subtitles are converted to LRC.
+ */
+export function import_lyrics(audio: LocalAudio, path: string): Promise<LocalAudio> {
+  return invoke("import_lyrics", { audio, path })
+}
+
+/** Look lyrics up in the file tags, then online; null when none were found */
+export function attach_lyrics(audio: LocalAudio): Promise<LocalAudio | null> {
+  return invoke("attach_lyrics", { audio })
+}
+
+/** Stored or embedded lyrics of an audio, with the offset applied */
+export function get_lyrics(audio: LocalAudio): Promise<Lyrics | null> {
+  return invoke("get_lyrics", { audio })
+}
+
+/** A library search result; `playlist_id` is the first playlist holding the audio */
+export type LibraryHit = {
+  audio: LocalAudio
+  playlist_id?: string
+  score: number
+}
+
+/** Ranked library audios matching a query in titles, playlists, platform or lyrics */
+export function search_library(query: string, limit?: number): Promise<LibraryHit[]> {
+  return invoke("search_library", { query, limit })
+}
+
+/**
+ * Keyword search on every platform, or only `platform`, merged and deduplicated by title.
+ * Results are lightweight hits on the video page, `download_audio` extracts them when downloaded
+ */
+export function search_platform(
+  query: string,
+  platform?: Platform,
+  page?: number,
+): Promise<Audio[]> {
+  return invoke("search_platform", { platform, query, page })
+}
+
+export type DuplicateReason = "same_id" | "same_content" | "similar_title" | "same_recording"
+
+export type DuplicateEntry = {
+  audio: LocalAudio
+  /** Playlists referencing the file */
+  playlist_ids: string[]
+}
+
+export type DuplicateCluster = {
+  reasons: DuplicateReason[]
+  /** The suggested audio to keep comes first */
+  entries: DuplicateEntry[]
+}
+
+export type MergeResult = {
+  playlists: LocalPlaylist[]
+  /** Files of the duplicates no longer referenced, to delete once the playlists are saved */
+  removed: string[]
+}
+
+/** Group library audios holding the same song, by id, by content or by similar title and duration */
+export function find_duplicates(): Promise<DuplicateCluster[]> {
+  return invoke("find_duplicates")
+}
+
+/** Replace `duplicates` with `keep` in every playlist, the playlists still need saving and the removed files deleting */
+export function merge_duplicates(keep: LocalAudio, duplicates: LocalAudio[]): Promise<MergeResult> {
+  return invoke("merge_duplicates", { keep, duplicates })
+}
+
+/** Store a new offset for the lyrics of an audio */
+export function set_lyrics_offset(
+  audio: LocalAudio,
+  offsetMs: number,
+): Promise<[audio: LocalAudio, lyrics: Lyrics]> {
+  return invoke("set_lyrics_offset", { audio, offsetMs })
+}
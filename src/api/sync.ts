@@ -5,6 +5,7 @@ import {
   sync_update,
   sync_file_info,
   SYNC_FILE_NAME,
+  is_app_error,
 } from "./index"
 import logger from "../utils/logger"
 
@@ -51,15 +52,18 @@ export async function syncConfig(
   changed: boolean
 }> {
   log.info("[Sync] ========== Starting LWW sync ==========")
-  const { githubToken, repoUrl } = gistConfig
 
   // -------------------------------------------------------
   // Step 0: Probe remote — reachability + SHA check (1 API call)
   // -------------------------------------------------------
   let remoteInfo
   try {
-    remoteInfo = await sync_file_info(githubToken, repoUrl, SYNC_FILE_NAME)
+    remoteInfo = await sync_file_info(SYNC_FILE_NAME)
   } catch (e) {
+    // An invalid token or repository is not an outage; surface it as-is
+    if (is_app_error(e) && !e.retryable && e.kind !== "network") {
+      throw new Error(e.message)
+    }
     throw new SyncError(e)
   }
 
@@ -97,9 +101,9 @@ export async function syncConfig(
   if (forcePush) {
     log.info("[Sync] Force push – uploading local config")
     const encoded = new TextEncoder().encode(JSON.stringify(localConfig, null, 2))
-    await sync_update(githubToken, repoUrl, encoded, SYNC_FILE_NAME, undefined)
+    await sync_update(encoded, SYNC_FILE_NAME, undefined, localConfig._deviceId)
 
-    const newSha = await fetchRemoteSha(githubToken, repoUrl, SYNC_FILE_NAME)
+    const newSha = await fetchRemoteSha(SYNC_FILE_NAME)
     log.info("[Sync] ========== Force push done ==========")
     return {
       updatedConfig: localConfig,
@@ -115,15 +119,15 @@ export async function syncConfig(
   // -------------------------------------------------------
   // Step 1: Download remote JSON
   // -------------------------------------------------------
-  const remoteBytes = await sync_download(githubToken, repoUrl, SYNC_FILE_NAME)
+  const remoteBytes = await sync_download(SYNC_FILE_NAME)
 
   if (remoteBytes.length === 0) {
     // No remote file — upload local
     log.info("[Sync] No remote file – uploading local config")
     const encoded = new TextEncoder().encode(JSON.stringify(localConfig, null, 2))
-    await sync_update(githubToken, repoUrl, encoded, SYNC_FILE_NAME, undefined)
+    await sync_update(encoded, SYNC_FILE_NAME, undefined, localConfig._deviceId)
 
-    const newSha = await fetchRemoteSha(githubToken, repoUrl, SYNC_FILE_NAME)
+    const newSha = await fetchRemoteSha(SYNC_FILE_NAME)
     log.info("[Sync] ========== Upload done ==========")
     return {
       updatedConfig: localConfig,
@@ -181,9 +185,9 @@ export async function syncConfig(
     // Local is newer or equal — upload local
     log.info("[Sync] Local is newer – uploading")
     const encoded = new TextEncoder().encode(JSON.stringify(localConfig, null, 2))
-    await sync_update(githubToken, repoUrl, encoded, SYNC_FILE_NAME, undefined)
+    await sync_update(encoded, SYNC_FILE_NAME, undefined, localConfig._deviceId)
 
-    const newSha = await fetchRemoteSha(githubToken, repoUrl, SYNC_FILE_NAME)
+    const newSha = await fetchRemoteSha(SYNC_FILE_NAME)
     log.info("[Sync] ========== Upload done (local newer) ==========")
     return {
       updatedConfig: localConfig,
@@ -220,13 +224,9 @@ function isConfigEmpty(config: Config): boolean {
   return false
 }
 
-async function fetchRemoteSha(
-  token: string,
-  repo: string,
-  path: string,
-): Promise<string | undefined> {
+async function fetchRemoteSha(path: string): Promise<string | undefined> {
   try {
-    const info = await sync_file_info(token, repo, path)
+    const info = await sync_file_info(path)
     return info?.sha
   } catch {
     return undefined
@@ -1,4 +1,4 @@
-import { FC, useState, useCallback } from "react"
+import { FC, useState, useCallback, useEffect } from "react"
 import {
   Menu,
   MenuItem,
@@ -10,12 +10,31 @@ import {
   DialogContent,
   DialogActions,
   TextField,
+  Select,
+  Typography,
 } from "@mui/material"
 import { MoreVert, ContentCopy, Delete, Source, DriveFileRenameOutline } from "@mui/icons-material"
 import SaveIcon from "@mui/icons-material/Save"
+import QueueMusicIcon from "@mui/icons-material/QueueMusic"
+import ContentCutIcon from "@mui/icons-material/ContentCut"
+import CallSplitIcon from "@mui/icons-material/CallSplit"
+import LyricsIcon from "@mui/icons-material/Lyrics"
 import { openUrl, revealItemInDir } from "@tauri-apps/plugin-opener"
 import { join } from "@tauri-apps/api/path"
-import { CurrentPlatform, save_audio } from "../../api"
+import {
+  CurrentPlatform,
+  save_audio,
+  export_playlist,
+  get_error_message,
+  PlaylistFormat,
+  Segment,
+  parse_timestamps,
+  split_audio,
+  import_lyrics,
+  attach_lyrics,
+  get_lyrics,
+  set_lyrics_offset,
+} from "../../api"
 import { copyToClipboard } from "../../utils"
 import { useAdaptiveSize, AdaptiveSize } from "../../hooks"
 import { useAppStore } from "../../store"
@@ -24,6 +43,7 @@ import OpenInNewIcon from "@mui/icons-material/OpenInNew"
 
 export interface MoreActionsDropdownProps {
   url?: string
+  /** Relative to the app directory, or absolute for files referenced in place */
   filePath?: string
   onDelete?: () => void
   onRename?: (newName: string) => void
@@ -52,11 +72,41 @@ export const MoreActionsDropdown: FC<MoreActionsDropdownProps> = ({
   const [anchorEl, setAnchorEl] = useState<null | HTMLElement>(null)
   const [renameOpen, setRenameOpen] = useState(false)
   const [renameValue, setRenameValue] = useState("")
+  const [exportOpen, setExportOpen] = useState(false)
+  const [exportFormat, setExportFormat] = useState<PlaylistFormat>("m3u")
+  const [exporting, setExporting] = useState(false)
+  const [splitOpen, setSplitOpen] = useState(false)
+  const [splitText, setSplitText] = useState("")
+  const [segments, setSegments] = useState<Segment[]>([])
+  const [splitting, setSplitting] = useState(false)
+  const [lyricsOpen, setLyricsOpen] = useState(false)
+  const [lyricsPath, setLyricsPath] = useState("")
+  const [lyricsOffset, setLyricsOffset] = useState(0)
+  const [savedOffset, setSavedOffset] = useState(0)
+  const [lyricsBusy, setLyricsBusy] = useState(false)
   const open = Boolean(anchorEl)
 
   const message = useMessage()
 
   const appDir = useAppStore((state) => state.app_dir)
+  const replayGain = useAppStore((state) => state.replayGain)
+  const trimSilence = useAppStore((state) => state.trimSilence)
+  const addPlaylistToConfig = useAppStore((state) => state.addPlaylistToConfig)
+  const setAudioLyrics = useAppStore((state) => state.setAudioLyrics)
+  const localAudio = useAppStore((state) =>
+    audioId
+      ? state.config.playlists.flatMap((p) => p.audios).find((a) => a.audio.id === audioId)
+      : undefined,
+  )
+  // Offer trimming only once silence was found at either end
+  const canTrim = useAppStore((state) => {
+    const audio = audioId
+      ? state.config.playlists.flatMap((p) => p.audios).find((a) => a.audio.id === audioId)
+      : undefined
+    if (audio?.end_ms == null) return false
+    const durationMs = (audio.info?.duration ?? audio.audio.duration ?? 0) * 1000
+    return (audio.start_ms ?? 0) > 0 || audio.end_ms < durationMs - 1000
+  })
   const isAndroid = CurrentPlatform === "android"
   const showFileOption = !isAndroid && !!filePath && !!appDir
 
@@ -115,7 +165,7 @@ export const MoreActionsDropdown: FC<MoreActionsDropdownProps> = ({
       if (!appDir || !filePath) return
 
       try {
-        // Convert relative path to absolute path
+        // Resolve a relative path, an absolute one is kept as is
         const fullPath = await join(appDir, filePath)
 
         // Use revealItemInDir to open the file location
@@ -145,7 +195,7 @@ export const MoreActionsDropdown: FC<MoreActionsDropdownProps> = ({
       if (!playlistId) return
 
       try {
-        const savedPaths = await save_audio(playlistId, audioId)
+        const savedPaths = await save_audio(playlistId, audioId, replayGain !== "off")
         if (savedPaths.length === 1) {
           message.success(`Saved to\n${savedPaths[0]}`)
           copyToClipboard(savedPaths[0]).catch((e) =>
@@ -160,11 +210,143 @@ export const MoreActionsDropdown: FC<MoreActionsDropdownProps> = ({
         }
       } catch (error) {
         console.error("Failed to save audio:", error)
-        message.error(`Failed to save: ${error}`)
+        message.error(`Failed to save: ${get_error_message(error)}`)
+      }
+      handleClose()
+    },
+    [playlistId, audioId, replayGain, message],
+  )
+
+  const handleExportClick = useCallback((e: React.MouseEvent) => {
+    e.stopPropagation()
+    setExportOpen(true)
+    handleClose()
+  }, [])
+
+  const handleExportConfirm = useCallback(async () => {
+    if (!playlistId) return
+    setExporting(true)
+    try {
+      const path = await export_playlist(playlistId, exportFormat)
+      message.success(`Playlist exported to\n${path}`)
+      revealItemInDir(path)
+      setExportOpen(false)
+    } catch (error) {
+      console.error("Failed to export playlist:", error)
+      message.error(`Failed to export: ${get_error_message(error)}`)
+    } finally {
+      setExporting(false)
+    }
+  }, [playlistId, exportFormat, message])
+
+  const handleSplitClick = useCallback((e: React.MouseEvent) => {
+    e.stopPropagation()
+    setSplitText("")
+    setSplitOpen(true)
+    handleClose()
+  }, [])
+
+  // Preview the segments while the list is being pasted
+  useEffect(() => {
+    if (!splitOpen) return
+    parse_timestamps(splitText)
+      .then(setSegments)
+      .catch(() => setSegments([]))
+  }, [splitOpen, splitText])
+
+  const handleSplitConfirm = useCallback(async () => {
+    if (!audioId || segments.length === 0) return
+    setSplitting(true)
+    try {
+      const playlist = await split_audio(audioId, segments)
+      await addPlaylistToConfig(playlist)
+      message.success(`Split into ${playlist.audios.length} tracks`)
+      setSplitOpen(false)
+    } catch (error) {
+      console.error("Failed to split audio:", error)
+      message.error(`Failed to split: ${get_error_message(error)}`)
+    } finally {
+      setSplitting(false)
+    }
+  }, [audioId, segments, addPlaylistToConfig, message])
+
+  const handleLyricsClick = useCallback(
+    (e: React.MouseEvent) => {
+      e.stopPropagation()
+      handleClose()
+      if (!localAudio) return
+      setLyricsPath("")
+      setLyricsOpen(true)
+      get_lyrics(localAudio)
+        .then((l) => {
+          setLyricsOffset(l?.offset_ms ?? 0)
+          setSavedOffset(l?.offset_ms ?? 0)
+        })
+        .catch(() => {
+          setLyricsOffset(0)
+          setSavedOffset(0)
+        })
+    },
+    [localAudio],
+  )
+
+  const handleLyricsFind = useCallback(async () => {
+    if (!localAudio) return
+    setLyricsBusy(true)
+    try {
+      const updated = await attach_lyrics(localAudio)
+      if (updated) {
+        await setAudioLyrics(updated)
+        message.success("Lyrics found")
+        setLyricsOpen(false)
+      } else {
+        message.info("No lyrics found")
+      }
+    } catch (error) {
+      console.error("Failed to find lyrics:", error)
+      message.error(`Failed to find lyrics: ${get_error_message(error)}`)
+    } finally {
+      setLyricsBusy(false)
+    }
+  }, [localAudio, setAudioLyrics, message])
+
+  const handleLyricsSave = useCallback(async () => {
+    if (!localAudio) return
+    setLyricsBusy(true)
+    try {
+      let updated = localAudio
+      if (lyricsPath.trim()) {
+        updated = await import_lyrics(updated, lyricsPath.trim())
       }
+      if (lyricsOffset !== savedOffset) {
+        updated = (await set_lyrics_offset(updated, lyricsOffset))[0]
+      }
+      await setAudioLyrics(updated)
+      message.success("Lyrics saved")
+      setLyricsOpen(false)
+    } catch (error) {
+      console.error("Failed to save lyrics:", error)
+      message.error(`Failed to save lyrics: ${get_error_message(error)}`)
+    } finally {
+      setLyricsBusy(false)
+    }
+  }, [localAudio, lyricsPath, lyricsOffset, savedOffset, setAudioLyrics, message])
+
+  const handleTrim = useCallback(
+    async (e: React.MouseEvent) => {
+      e.stopPropagation()
       handleClose()
+      if (!audioId) return
+
+      try {
+        await trimSilence(audioId)
+        message.success("Silence trimmed")
+      } catch (error) {
+        console.error("Failed to trim audio:", error)
+        message.error(`Failed to trim: ${get_error_message(error)}`)
+      }
     },
-    [playlistId, audioId, message],
+    [audioId, trimSilence, message],
   )
 
   const handleRenameClick = useCallback(
@@ -246,6 +428,38 @@ export const MoreActionsDropdown: FC<MoreActionsDropdownProps> = ({
             <ListItemText>Save</ListItemText>
           </MenuItem>
         )}
+        {showSave && !audioId && (
+          <MenuItem onClick={handleExportClick} disabled={!playlistId}>
+            <ListItemIcon>
+              <QueueMusicIcon />
+            </ListItemIcon>
+            <ListItemText>Export</ListItemText>
+          </MenuItem>
+        )}
+        {showSave && audioId && (
+          <MenuItem onClick={handleSplitClick}>
+            <ListItemIcon>
+              <CallSplitIcon />
+            </ListItemIcon>
+            <ListItemText>Split</ListItemText>
+          </MenuItem>
+        )}
+        {showSave && localAudio && (
+          <MenuItem onClick={handleLyricsClick}>
+            <ListItemIcon>
+              <LyricsIcon />
+            </ListItemIcon>
+            <ListItemText>Lyrics</ListItemText>
+          </MenuItem>
+        )}
+        {showSave && audioId && canTrim && (
+          <MenuItem onClick={handleTrim}>
+            <ListItemIcon>
+              <ContentCutIcon />
+            </ListItemIcon>
+            <ListItemText>Trim</ListItemText>
+          </MenuItem>
+        )}
         {onRename && (
           <MenuItem onClick={handleRenameClick}>
             <ListItemIcon>
@@ -287,6 +501,105 @@ export const MoreActionsDropdown: FC<MoreActionsDropdownProps> = ({
           </Button>
         </DialogActions>
       </Dialog>
+      <Dialog open={splitOpen} onClose={() => setSplitOpen(false)} fullWidth maxWidth="xs">
+        <DialogTitle>Split into Tracks</DialogTitle>
+        <DialogContent>
+          <TextField
+            autoFocus
+            fullWidth
+            multiline
+            minRows={4}
+            maxRows={12}
+            size="small"
+            value={splitText}
+            onChange={(e) => setSplitText(e.target.value)}
+            placeholder={"00:00 Intro\n03:25 Second Track\n1:02:10 Last Track"}
+            sx={{ mt: 0.5 }}
+          />
+          <Typography color="text.secondary" variant="body2" sx={{ mt: 1 }}>
+            {segments.length > 0
+              ? `${segments.length} tracks: ${segments.map((s) => s.title).join(", ")}`
+              : "Paste timestamps from the description"}
+          </Typography>
+        </DialogContent>
+        <DialogActions>
+          <Button onClick={() => setSplitOpen(false)} color="inherit">
+            Cancel
+          </Button>
+          <Button
+            onClick={handleSplitConfirm}
+            variant="contained"
+            disabled={splitting || segments.length === 0}
+          >
+            Split
+          </Button>
+        </DialogActions>
+      </Dialog>
+      <Dialog open={lyricsOpen} onClose={() => setLyricsOpen(false)} fullWidth maxWidth="xs">
+        <DialogTitle>Lyrics</DialogTitle>
+        <DialogContent>
+          <TextField
+            autoFocus
+            fullWidth
+            size="small"
+            label="LRC or subtitle file"
+            value={lyricsPath}
+            onChange={(e) => setLyricsPath(e.target.value)}
+            placeholder="/path/to/lyrics.lrc, .srt or .vtt"
+            sx={{ mt: 1 }}
+          />
+          <TextField
+            fullWidth
+            size="small"
+            type="number"
+            label="Offset (ms)"
+            value={lyricsOffset}
+            onChange={(e) => setLyricsOffset(Math.round(Number(e.target.value) || 0))}
+            helperText="Positive values show lines earlier"
+            sx={{ mt: 2 }}
+          />
+        </DialogContent>
+        <DialogActions>
+          <Button onClick={() => setLyricsOpen(false)} color="inherit">
+            Cancel
+          </Button>
+          <Button onClick={handleLyricsFind} disabled={lyricsBusy}>
+            Find
+          </Button>
+          <Button
+            onClick={handleLyricsSave}
+            variant="contained"
+            disabled={lyricsBusy || (!lyricsPath.trim() && lyricsOffset === savedOffset)}
+          >
+            Save
+          </Button>
+        </DialogActions>
+      </Dialog>
+      <Dialog open={exportOpen} onClose={() => setExportOpen(false)} fullWidth maxWidth="xs">
+        <DialogTitle>Export Playlist</DialogTitle>
+        <DialogContent>
+          <Select
+            fullWidth
+            size="small"
+            value={exportFormat}
+            onChange={(e) => setExportFormat(e.target.value as PlaylistFormat)}
+            sx={{ mt: 0.5 }}
+          >
+            <MenuItem value="m3u">M3U8</MenuItem>
+            <MenuItem value="xspf">XSPF</MenuItem>
+            <MenuItem value="pls">PLS</MenuItem>
+            <MenuItem value="json">JSON</MenuItem>
+          </Select>
+        </DialogContent>
+        <DialogActions>
+          <Button onClick={() => setExportOpen(false)} color="inherit">
+            Cancel
+          </Button>
+          <Button onClick={handleExportConfirm} variant="contained" disabled={exporting}>
+            Export
+          </Button>
+        </DialogActions>
+      </Dialog>
     </>
   )
 }
@@ -83,7 +83,7 @@ export const MusicPage: FC = () => {
             <MoreActionsDropdown
               size="medium"
               url={audio.audio.download_url}
-              filePath={audio.path}
+              filePath={audio.external_path ?? audio.path}
               showSave={true}
               playlistId={AUDIO_PLAYLIST_ID}
               audioId={audio.audio.id}
@@ -1,9 +1,9 @@
-import { FC, useCallback } from "react"
+import { FC, useCallback, useEffect, useMemo, useState } from "react"
 import { useNavigate } from "react-router-dom"
 import { MusicNote } from "@mui/icons-material"
 import { Slider, Typography, Avatar, Stack, Box, useTheme, alpha } from "@mui/material"
 import { useAppStore, useCurrentTime, useDuration } from "../../store"
-import { DEFAULT_COVER_URL, AUDIO_PLAYLIST_ID } from "../../api"
+import { DEFAULT_COVER_URL, AUDIO_PLAYLIST_ID, Lyrics, get_lyrics } from "../../api"
 import { useCoverUrl, useConfirm } from "../../hooks"
 import { MoreActionsDropdown, PlayerControls, BackButton, PlatformIcon } from "../../components"
 
@@ -36,6 +36,26 @@ export const PlayerPage: FC = () => {
   const currentTime = useCurrentTime()
   const duration = useDuration()
 
+  // Reload lyrics whenever the track or its entry changes
+  const [lyrics, setLyrics] = useState<Lyrics | null>(null)
+  useEffect(() => {
+    setLyrics(null)
+    if (!currentAudio) return
+    let cancelled = false
+    get_lyrics(currentAudio)
+      .then((l) => !cancelled && setLyrics(l))
+      .catch((e) => console.error("Failed to load lyrics:", e))
+    return () => {
+      cancelled = true
+    }
+  }, [currentAudio])
+
+  const lyricLine = useMemo(() => {
+    if (!lyrics?.synced) return null
+    const ms = currentTime * 1000
+    return lyrics.lines.findLast((l) => l.time_ms <= ms)?.text ?? null
+  }, [lyrics, currentTime])
+
   // Commit seek operation when user releases slider
   const handleAfterChange = useCallback(
     (_event: Event | React.SyntheticEvent | undefined, value: number | number[]) => {
@@ -227,6 +247,11 @@ export const PlayerPage: FC = () => {
           <Stack direction="row" sx={{ alignItems: "center" }} spacing={1}>
             <PlatformIcon platform={currentAudio.audio.platform} size={36} />
           </Stack>
+          {lyricLine && (
+            <Typography color="text.secondary" sx={{ width: "100%", minHeight: "1.5em" }}>
+              {lyricLine}
+            </Typography>
+          )}
         </Stack>
       </Stack>
 
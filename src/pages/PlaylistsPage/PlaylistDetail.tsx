@@ -97,7 +97,7 @@ export const PlaylistDetail: FC = () => {
             <MoreActionsDropdown
               size="medium"
               url={audio.audio.download_url}
-              filePath={audio.path}
+              filePath={audio.external_path ?? audio.path}
               showSave={true}
               playlistId={playlist?.id}
               audioId={audio.audio.id}
@@ -12,7 +12,7 @@ import ArrowForwardIcon from "@mui/icons-material/ArrowForward"
 
 import { SearchBottomBar } from "./SearchBottomBar"
 import { useSearchLogic } from "./useSearchLogic"
-import { AUDIO_PLAYLIST_ID, Audio, LocalAudio } from "../../api"
+import { AUDIO_PLAYLIST_ID, Audio, LocalAudio, LibraryHit, search_library } from "../../api"
 
 import { useAppStore } from "../../store"
 import { useShallow } from "zustand/react/shallow"
@@ -93,6 +93,40 @@ export const SearchPage: FC = () => {
     })),
   )
 
+  // Library matches for text that is not a URL, shown until something is extracted
+  const [libraryHits, setLibraryHits] = useState<LibraryHit[]>([])
+  const playAudio = useAppStore((state) => state.playAudio)
+  useEffect(() => {
+    const query = searchText.trim()
+    if (!query || /^https?:\/\//i.test(query)) {
+      setLibraryHits([])
+      return
+    }
+    const timer = setTimeout(() => {
+      search_library(query, 20)
+        .then(setLibraryHits)
+        .catch((e) => console.error("Library search failed:", e))
+    }, 200)
+    return () => clearTimeout(timer)
+  }, [searchText])
+
+  const getHitId = useCallback((hit: LibraryHit) => hit.audio.audio.id, [])
+
+  const renderLibraryHit = useCallback(
+    (hit: LibraryHit) => (
+      <AudioCard
+        coverPath={hit.audio.cover_path}
+        coverUrl={hit.audio.audio.cover}
+        platform={hit.audio.audio.platform}
+        title={hit.audio.audio.title}
+        duration={hit.audio.audio.duration}
+        onClick={() => playAudio(hit.audio, hit.playlist_id ?? AUDIO_PLAYLIST_ID)}
+        active={false}
+      />
+    ),
+    [playAudio],
+  )
+
   // Clear on unmount
   useEffect(() => {
     return () => {
@@ -414,7 +448,7 @@ export const SearchPage: FC = () => {
       <Box sx={{ p: 1, pb: 0 }}>
         <TextField
           fullWidth
-          placeholder="Input audio/playlist ID/URL"
+          placeholder="Input audio/playlist ID/URL or keywords"
           value={searchText}
           onChange={handleSearchTextChange}
           disabled={searching || downloadingAll}
@@ -470,7 +504,13 @@ export const SearchPage: FC = () => {
         />
       </Box>
 
-      {(!playlist || playlist.audios.length === 0) && !searching && (
+      {(!playlist || playlist.audios.length === 0) && !searching && libraryHits.length > 0 && (
+        <Box sx={{ flex: 1, overflow: "hidden" }}>
+          <AudioList items={libraryHits} getItemId={getHitId} renderItem={renderLibraryHit} />
+        </Box>
+      )}
+
+      {(!playlist || playlist.audios.length === 0) && !searching && libraryHits.length === 0 && (
         <Box
           sx={{
             flex: 1,
@@ -11,6 +11,7 @@ import {
   Button,
   IconButton,
   Switch,
+  LinearProgress,
 } from "@mui/material"
 import GitHub from "@mui/icons-material/GitHub"
 import FolderOpen from "@mui/icons-material/FolderOpen"
@@ -40,6 +41,19 @@ import {
   is_builtin,
   export_data,
   import_data,
+  import_playlist_file,
+  scan_local_folders,
+  LocalFolders,
+  Config,
+  listen_task_progress,
+  cancel_task,
+  TaskProgress,
+  list_backups,
+  inspect_backup,
+  is_app_error,
+  BackupInfo,
+  BackupPreview,
+  ImportMode,
   CurrentPlatform,
   GistConfig,
   get_log_size,
@@ -47,6 +61,13 @@ import {
   get_log_path,
   read_log,
   TranscodeFormat,
+  ReplayGainMode,
+  set_sync_credentials,
+  sync_status,
+  get_error_message,
+  find_duplicates,
+  DuplicateCluster,
+  DuplicateReason,
 } from "../../api"
 import { useConfirm } from "../../hooks"
 import { useMessage } from "../../contexts/MessageContext"
@@ -87,6 +108,12 @@ export const SettingsPage: FC = () => {
   const [cacheSize, setCacheSize] = useState<number>(0)
   const [loadingCache, setLoadingCache] = useState(false)
   const [openSyncDialog, setOpenSyncDialog] = useState(false)
+  const [openImportDialog, setOpenImportDialog] = useState(false)
+  const [openPlaylistDialog, setOpenPlaylistDialog] = useState(false)
+  const [openFoldersDialog, setOpenFoldersDialog] = useState(false)
+  const [openDuplicatesDialog, setOpenDuplicatesDialog] = useState(false)
+  const [taskId, setTaskId] = useState<string | null>(null)
+  const [taskProgress, setTaskProgress] = useState<TaskProgress | null>(null)
   const [saveLogsToFile, setSaveLogsToFile] = useState(getSaveToFile())
   const [logSize, setLogSize] = useState<number>(0)
 
@@ -94,8 +121,16 @@ export const SettingsPage: FC = () => {
   const setGistConfig = useAppStore((state) => state.setGistConfig)
   const syncGithub = useAppStore((state) => state.syncGithub)
   const isSyncing = useAppStore((state) => state.isSyncing)
+  const localFolders = useAppStore((state) => state.localFolders)
+  const setLocalFolders = useAppStore((state) => state.setLocalFolders)
   const transcodeFormat = useAppStore((state) => state.transcodeFormat)
   const setTranscodeFormat = useAppStore((state) => state.setTranscodeFormat)
+  const extractAudio = useAppStore((state) => state.extractAudio)
+  const setExtractAudio = useAppStore((state) => state.setExtractAudio)
+  const replayGain = useAppStore((state) => state.replayGain)
+  const setReplayGain = useAppStore((state) => state.setReplayGain)
+  const skipSilence = useAppStore((state) => state.skipSilence)
+  const setSkipSilence = useAppStore((state) => state.setSkipSilence)
 
   // Load storage size
   const loadStorageSize = useCallback(async () => {
@@ -163,42 +198,137 @@ export const SettingsPage: FC = () => {
     })
   }, [showConfirm, message, loadStorageSize])
 
+  // Run a cancellable backup task while tracking its progress
+  const runTask = useCallback(async <T,>(run: (taskId: string) => Promise<T>): Promise<T> => {
+    const taskId = crypto.randomUUID()
+    setTaskId(taskId)
+    const unlisten = await listen_task_progress(taskId, setTaskProgress)
+    try {
+      return await run(taskId)
+    } finally {
+      unlisten()
+      setTaskId(null)
+      setTaskProgress(null)
+    }
+  }, [])
+
+  const handleCancelTask = useCallback(() => {
+    if (taskId) cancel_task(taskId)
+  }, [taskId])
+
   const handleExport = useCallback(async () => {
     setExporting(true)
     try {
-      const path = await export_data()
+      const path = await runTask((taskId) => export_data({ include_history: true }, taskId))
       message.success(`Data exported to\n${path}`)
       revealItemInDir(path)
     } catch (e) {
       console.error(e)
-      message.error(`Failed to export data ${e}`)
+      if (is_app_error(e) && e.kind === "cancelled") {
+        message.info("Export cancelled")
+      } else {
+        message.error(`Failed to export data ${get_error_message(e)}`)
+      }
     } finally {
       setExporting(false)
     }
-  }, [message])
+  }, [message, runTask])
 
   const importConfig = useAppStore((state) => state.importConfig)
 
-  const handleImport = useCallback(async () => {
-    setImporting(true)
-    try {
-      const { config: importedConfig, filename } = await import_data()
+  const handleImport = useCallback(
+    async (path: string, mode: ImportMode) => {
+      setImporting(true)
+      try {
+        const { config: importedConfig, filename } = await runTask((taskId) =>
+          import_data(path, mode, taskId),
+        )
 
-      await importConfig(importedConfig)
+        await importConfig(importedConfig, mode)
 
-      message.success(`Successfully imported data from ${filename}`)
-      await loadConfig()
-    } catch (e: unknown) {
-      console.error(e)
-      if (typeof e === "string" && e.includes("No backup")) {
-        message.warning("No backup file found in Downloads")
-      } else {
-        message.error(`Failed to import data: ${e}`)
+        message.success(`Successfully imported data from ${filename}`)
+        setOpenImportDialog(false)
+        await loadConfig()
+      } catch (e: unknown) {
+        console.error(e)
+        if (is_app_error(e) && e.kind === "not_found") {
+          message.warning(get_error_message(e))
+        } else if (is_app_error(e) && e.kind === "cancelled") {
+          message.info("Import cancelled")
+        } else {
+          message.error(`Failed to import data: ${get_error_message(e)}`)
+        }
+      } finally {
+        setImporting(false)
       }
-    } finally {
-      setImporting(false)
-    }
-  }, [message, loadConfig, importConfig])
+    },
+    [message, loadConfig, importConfig, runTask],
+  )
+
+  const handleImportPlaylist = useCallback(
+    async (path: string) => {
+      setImporting(true)
+      try {
+        const { playlists, failed } = await runTask((taskId) =>
+          import_playlist_file(path, taskId),
+        )
+        await importConfig({ playlists } as Config, "merge")
+        await loadConfig()
+
+        const count = playlists.reduce((n, p) => n + p.audios.length, 0)
+        if (failed.length > 0) {
+          console.warn("Unresolved playlist entries", failed)
+          message.warning(`Imported ${count} tracks, ${failed.length} could not be found`)
+        } else {
+          message.success(`Imported ${count} tracks`)
+        }
+        setOpenPlaylistDialog(false)
+      } catch (e: unknown) {
+        console.error(e)
+        if (is_app_error(e) && e.kind === "cancelled") {
+          message.info("Import cancelled")
+        } else {
+          message.error(`Failed to import playlist: ${get_error_message(e)}`)
+        }
+      } finally {
+        setImporting(false)
+      }
+    },
+    [message, loadConfig, importConfig, runTask],
+  )
+
+  const handleScanFolders = useCallback(
+    async (folders: LocalFolders) => {
+      setImporting(true)
+      try {
+        const { playlists, failed } = await runTask((taskId) =>
+          scan_local_folders(folders.paths, folders.copy, taskId),
+        )
+        await importConfig({ playlists } as Config, "merge")
+        await setLocalFolders(folders)
+        await loadConfig()
+
+        const count = playlists.reduce((n, p) => n + p.audios.length, 0)
+        if (failed.length > 0) {
+          console.warn("Unreadable local files", failed)
+          message.warning(`Found ${count} tracks, ${failed.length} could not be read`)
+        } else {
+          message.success(`Found ${count} tracks`)
+        }
+        setOpenFoldersDialog(false)
+      } catch (e: unknown) {
+        console.error(e)
+        if (is_app_error(e) && e.kind === "cancelled") {
+          message.info("Scan cancelled")
+        } else {
+          message.error(`Failed to scan folders: ${get_error_message(e)}`)
+        }
+      } finally {
+        setImporting(false)
+      }
+    },
+    [message, loadConfig, importConfig, setLocalFolders, runTask],
+  )
 
   const handleToggleSaveLogs = useCallback(
     (checked: boolean) => {
@@ -311,6 +441,45 @@ export const SettingsPage: FC = () => {
               <MenuItem value="wav">WAV</MenuItem>
             </Select>
           </Stack>
+          <Divider sx={{ my: muiTheme.custom.spacing.dividerSpacing }} />
+          <Stack direction="row" sx={rowSx}>
+            <Box>
+              <Typography>Extract Audio</Typography>
+              <Typography color="text.secondary" variant="body2">
+                Drop the video of MP4 and WebM downloads
+              </Typography>
+            </Box>
+            <Switch checked={extractAudio} onChange={(e) => setExtractAudio(e.target.checked)} />
+          </Stack>
+          <Divider sx={{ my: muiTheme.custom.spacing.dividerSpacing }} />
+          <Stack direction="row" sx={rowSx}>
+            <Box>
+              <Typography>Normalize Volume</Typography>
+              <Typography color="text.secondary" variant="body2">
+                ReplayGain, also written into saved files
+              </Typography>
+            </Box>
+            <Select
+              value={replayGain}
+              onChange={(e) => setReplayGain(e.target.value as ReplayGainMode)}
+              size="small"
+              sx={{ minWidth: muiTheme.custom.spacing.selectMinWidth }}
+            >
+              <MenuItem value="off">Off</MenuItem>
+              <MenuItem value="track">Track</MenuItem>
+              <MenuItem value="album">Playlist</MenuItem>
+            </Select>
+          </Stack>
+          <Divider sx={{ my: muiTheme.custom.spacing.dividerSpacing }} />
+          <Stack direction="row" sx={rowSx}>
+            <Box>
+              <Typography>Skip Silence</Typography>
+              <Typography color="text.secondary" variant="body2">
+                Skip silent intros and outros
+              </Typography>
+            </Box>
+            <Switch checked={skipSilence} onChange={(e) => setSkipSilence(e.target.checked)} />
+          </Stack>
         </Paper>
       </Stack>
 
@@ -375,11 +544,64 @@ export const SettingsPage: FC = () => {
               <Button variant="outlined" onClick={handleExport} disabled={exporting}>
                 Export
               </Button>
-              <Button variant="outlined" onClick={handleImport} disabled={importing}>
+              <Button
+                variant="outlined"
+                onClick={() => setOpenImportDialog(true)}
+                disabled={importing}
+              >
                 Import
               </Button>
             </Stack>
           </Stack>
+          {exporting && taskId && (
+            <TaskProgressBar progress={taskProgress} onCancel={handleCancelTask} />
+          )}
+          <Divider sx={{ my: 1 }} />
+          <Stack direction="row" sx={rowSx}>
+            <Box>
+              <Typography>Import Playlist</Typography>
+              <Typography color="text.secondary" variant="body2">
+                M3U, XSPF, PLS or MusicFree mobile backup
+              </Typography>
+            </Box>
+            <Button
+              variant="outlined"
+              onClick={() => setOpenPlaylistDialog(true)}
+              disabled={importing}
+            >
+              Import
+            </Button>
+          </Stack>
+          <Divider sx={{ my: 1 }} />
+          <Stack direction="row" sx={rowSx}>
+            <Box>
+              <Typography>Local Folders</Typography>
+              <Typography color="text.secondary" variant="body2">
+                {localFolders.paths.length > 0
+                  ? `${localFolders.paths.length} folders${localFolders.watch ? ", watched" : ""}`
+                  : "Add music files from your device"}
+              </Typography>
+            </Box>
+            <Button
+              variant="outlined"
+              onClick={() => setOpenFoldersDialog(true)}
+              disabled={importing}
+            >
+              Scan
+            </Button>
+          </Stack>
+          <Divider sx={{ my: 1 }} />
+          <Stack direction="row" sx={rowSx}>
+            <Box>
+              <Typography>Duplicates</Typography>
+              <Typography color="text.secondary" variant="body2">
+                Same songs across playlists and uploads
+              </Typography>
+            </Box>
+            <Button variant="outlined" onClick={() => setOpenDuplicatesDialog(true)}>
+              Find
+            </Button>
+          </Stack>
           <Divider sx={{ my: 1 }} />
           <Stack direction="row" sx={rowSx}>
             <Box>
@@ -509,6 +731,39 @@ export const SettingsPage: FC = () => {
           setOpenSyncDialog(false)
         }}
       />
+
+      <ImportDialog
+        open={openImportDialog}
+        onClose={() => setOpenImportDialog(false)}
+        importing={importing}
+        onImport={handleImport}
+        progress={taskProgress}
+        onCancel={handleCancelTask}
+      />
+
+      <PlaylistImportDialog
+        open={openPlaylistDialog}
+        onClose={() => setOpenPlaylistDialog(false)}
+        importing={importing}
+        onImport={handleImportPlaylist}
+        progress={taskProgress}
+        onCancel={handleCancelTask}
+      />
+
+      <LocalFoldersDialog
+        open={openFoldersDialog}
+        onClose={() => setOpenFoldersDialog(false)}
+        folders={localFolders}
+        scanning={importing}
+        onScan={handleScanFolders}
+        progress={taskProgress}
+        onCancel={handleCancelTask}
+      />
+
+      <DuplicatesDialog
+        open={openDuplicatesDialog}
+        onClose={() => setOpenDuplicatesDialog(false)}
+      />
     </Stack>
   )
 }
@@ -531,8 +786,11 @@ const SyncDialog: FC<SyncDialogProps> = ({
   syncGithub,
 }) => {
   const [repoUrl, setRepoUrl] = useState(config?.repoUrl || "")
-  const [token, setToken] = useState(config?.githubToken || "")
+  // The token is write-only: it is kept in the Rust credential store
+  const [token, setToken] = useState("")
+  const [hasToken, setHasToken] = useState(false)
   const [interval, setIntervalValue] = useState(config?.syncInterval || 60)
+  const [syncHistory, setSyncHistory] = useState(!!config?.syncHistory)
   const [loading, setLoading] = useState(false)
 
   const message = useMessage()
@@ -541,22 +799,40 @@ const SyncDialog: FC<SyncDialogProps> = ({
   useEffect(() => {
     if (open && config) {
       setRepoUrl(config.repoUrl)
-      setToken(config.githubToken)
       setIntervalValue(config.syncInterval)
+      setSyncHistory(!!config.syncHistory)
+    }
+    if (open) {
+      setToken("")
+      sync_status()
+        .then((status) => setHasToken(status.configured || !!config?.githubToken))
+        .catch(() => setHasToken(!!config?.githubToken))
     }
   }, [open, config])
 
+  // A new repository needs the token to be entered again
+  const canSave = !!repoUrl && (!!token || (hasToken && repoUrl === config?.repoUrl))
+  const canSync = !!repoUrl && hasToken
+
   const handleSave = async () => {
-    if (!repoUrl || !token) return
+    if (!canSave) return
     setLoading(true)
     try {
+      if (token) {
+        await set_sync_credentials(token, repoUrl)
+        setHasToken(true)
+      }
       const newConfig = {
         repoUrl,
-        githubToken: token,
         syncInterval: interval,
         lastSyncTime: config?.lastSyncTime,
+        syncHistory,
       }
       onSave(newConfig)
+    } catch (error) {
+      message.error(
+        `Failed to save token: ${get_error_message(error)}`,
+      )
     } finally {
       setLoading(false)
     }
@@ -587,7 +863,7 @@ const SyncDialog: FC<SyncDialogProps> = ({
           onOk: () => {},
         })
       } else {
-        message.error(`Sync failed: ${error instanceof Error ? error.message : String(error)}`)
+        message.error(`Sync failed: ${get_error_message(error)}`)
       }
     }
   }
@@ -610,7 +886,7 @@ const SyncDialog: FC<SyncDialogProps> = ({
         } catch (error) {
           console.error("Force push error:", error)
           message.error(
-            `Force push failed: ${error instanceof Error ? error.message : String(error)}`,
+            `Force push failed: ${get_error_message(error)}`,
           )
         }
       },
@@ -636,7 +912,7 @@ const SyncDialog: FC<SyncDialogProps> = ({
         } catch (error) {
           console.error("Force pull error:", error)
           message.error(
-            `Force pull failed: ${error instanceof Error ? error.message : String(error)}`,
+            `Force pull failed: ${get_error_message(error)}`,
           )
         }
       },
@@ -680,6 +956,7 @@ const SyncDialog: FC<SyncDialogProps> = ({
             onChange={(e) => setToken(e.target.value)}
             fullWidth
             size="small"
+            placeholder={hasToken ? "Saved securely – enter to replace" : undefined}
           />
           <FormControl fullWidth size="small">
             <InputLabel>Sync Interval</InputLabel>
@@ -695,6 +972,15 @@ const SyncDialog: FC<SyncDialogProps> = ({
               <MenuItem value={1440}>1 Day</MenuItem>
             </Select>
           </FormControl>
+          <Stack direction="row" sx={{ alignItems: "center", justifyContent: "space-between" }}>
+            <Box>
+              <Typography>Sync Play History</Typography>
+              <Typography color="text.secondary" variant="body2">
+                Merge listening history across devices
+              </Typography>
+            </Box>
+            <Switch checked={syncHistory} onChange={(e) => setSyncHistory(e.target.checked)} />
+          </Stack>
         </Stack>
       </DialogContent>
       <DialogActions>
@@ -702,7 +988,7 @@ const SyncDialog: FC<SyncDialogProps> = ({
           <IconButton
             color="success"
             onClick={handleSyncNow}
-            disabled={isSyncing || !repoUrl || !token}
+            disabled={isSyncing || !canSync}
             size="small"
             aria-label="Sync"
           >
@@ -711,7 +997,7 @@ const SyncDialog: FC<SyncDialogProps> = ({
           <IconButton
             color="warning"
             onClick={handleForcePush}
-            disabled={isSyncing || !repoUrl || !token}
+            disabled={isSyncing || !canSync}
             size="small"
             aria-label="Force Push"
           >
@@ -720,7 +1006,7 @@ const SyncDialog: FC<SyncDialogProps> = ({
           <IconButton
             color="info"
             onClick={handleForcePull}
-            disabled={isSyncing || !repoUrl || !token}
+            disabled={isSyncing || !canSync}
             size="small"
             aria-label="Force Pull"
           >
@@ -730,7 +1016,7 @@ const SyncDialog: FC<SyncDialogProps> = ({
         <Button onClick={onClose} color="inherit">
           Cancel
         </Button>
-        <Button onClick={handleSave} variant="contained" disabled={!repoUrl || !token || loading}>
+        <Button onClick={handleSave} variant="contained" disabled={!canSave || loading}>
           {loading ? <CircularProgress size={24} /> : "Save"}
         </Button>
       </DialogActions>
@@ -738,4 +1024,437 @@ const SyncDialog: FC<SyncDialogProps> = ({
   )
 }
 
+// Tasks whose progress is counted in bytes rather than items
+const BYTE_TASKS = ["export", "import"]
+
+interface TaskProgressBarProps {
+  progress: TaskProgress | null
+  onCancel: () => void
+}
+
+const TaskProgressBar: FC<TaskProgressBarProps> = ({ progress, onCancel }) => {
+  const percent = progress && progress.total > 0 ? (progress.done / progress.total) * 100 : 0
+  return (
+    <Stack direction="row" spacing={1} sx={{ alignItems: "center", mt: 1 }}>
+      <Box sx={{ flex: 1, minWidth: 0 }}>
+        <LinearProgress
+          variant={progress && progress.total > 0 ? "determinate" : "indeterminate"}
+          value={percent}
+        />
+        {progress && progress.total > 0 && (
+          <Typography variant="caption" color="text.secondary" noWrap component="div">
+            {BYTE_TASKS.includes(progress.task)
+              ? `${prettyBytes(progress.done)} / ${prettyBytes(progress.total)}`
+              : `${progress.done} / ${progress.total}`}
+          </Typography>
+        )}
+      </Box>
+      <Button size="small" color="inherit" onClick={onCancel}>
+        Cancel
+      </Button>
+    </Stack>
+  )
+}
+
+interface ImportDialogProps {
+  open: boolean
+  onClose: () => void
+  importing: boolean
+  onImport: (path: string, mode: ImportMode) => Promise<void>
+  progress: TaskProgress | null
+  onCancel: () => void
+}
+
+const ImportDialog: FC<ImportDialogProps> = ({
+  open,
+  onClose,
+  importing,
+  onImport,
+  progress,
+  onCancel,
+}) => {
+  const [backups, setBackups] = useState<BackupInfo[]>([])
+  const [loading, setLoading] = useState(false)
+  const [path, setPath] = useState("")
+  const [preview, setPreview] = useState<BackupPreview | null>(null)
+  const [previewError, setPreviewError] = useState<string | null>(null)
+  const [mode, setMode] = useState<ImportMode>("merge")
+
+  useEffect(() => {
+    if (!open) return
+    setLoading(true)
+    setMode("merge")
+    list_backups()
+      .then((list) => {
+        setBackups(list)
+        setPath(list[0]?.path ?? "")
+      })
+      .catch((e) => {
+        console.error(e)
+        setBackups([])
+        setPath("")
+      })
+      .finally(() => setLoading(false))
+  }, [open])
+
+  // Preview whichever file is selected or typed, without importing it
+  useEffect(() => {
+    setPreview(null)
+    setPreviewError(null)
+    if (!open || !path) return
+    let cancelled = false
+    inspect_backup(path)
+      .then((p) => !cancelled && setPreview(p))
+      .catch((e) => !cancelled && setPreviewError(get_error_message(e)))
+    return () => {
+      cancelled = true
+    }
+  }, [open, path])
+
+  return (
+    <Dialog open={open} onClose={onClose} fullWidth maxWidth="xs">
+      <DialogTitle>Import Backup</DialogTitle>
+      <DialogContent>
+        <Stack spacing={3} sx={{ mt: 1 }}>
+          {loading ? (
+            <CircularProgress size={24} sx={{ alignSelf: "center" }} />
+          ) : backups.length > 0 ? (
+            <FormControl fullWidth size="small">
+              <InputLabel>Backup</InputLabel>
+              <Select
+                value={backups.some((b) => b.path === path) ? path : ""}
+                label="Backup"
+                onChange={(e) => setPath(e.target.value)}
+              >
+                {backups.map((b) => (
+                  <MenuItem key={b.path} value={b.path}>
+                    {new Date(b.created_at).toLocaleString()} · {prettyBytes(b.size)}
+                    {b.kind === "incremental" ? " · incremental" : ""}
+                  </MenuItem>
+                ))}
+              </Select>
+            </FormControl>
+          ) : (
+            <Typography color="text.secondary" variant="body2">
+              No backup file found in Downloads
+            </Typography>
+          )}
+          <TextField
+            label="File"
+            value={path}
+            onChange={(e) => setPath(e.target.value)}
+            fullWidth
+            size="small"
+            placeholder="Path to a musicfree backup .zip"
+          />
+          {preview && (
+            <Box>
+              <Typography variant="body2">
+                {preview.info.playlist_count} playlists, {preview.info.track_count} tracks
+              </Typography>
+              <Box sx={{ maxHeight: 160, overflowY: "auto", mt: 1 }}>
+                {preview.config.playlists.map((p, i) => (
+                  <Typography key={p.id ?? i} variant="body2" color="text.secondary" noWrap>
+                    {p.title || p.id} ({p.audios.length})
+                  </Typography>
+                ))}
+              </Box>
+            </Box>
+          )}
+          {previewError && (
+            <Typography color="error" variant="body2">
+              {previewError}
+            </Typography>
+          )}
+          <FormControl fullWidth size="small">
+            <InputLabel>Mode</InputLabel>
+            <Select
+              value={mode}
+              label="Mode"
+              onChange={(e) => setMode(e.target.value as ImportMode)}
+            >
+              <MenuItem value="merge">Merge into existing playlists</MenuItem>
+              <MenuItem value="add_missing">Only add missing playlists</MenuItem>
+              <MenuItem value="replace">Replace all playlists</MenuItem>
+            </Select>
+          </FormControl>
+          {importing && <TaskProgressBar progress={progress} onCancel={onCancel} />}
+        </Stack>
+      </DialogContent>
+      <DialogActions>
+        <Button onClick={onClose} color="inherit">
+          Cancel
+        </Button>
+        <Button
+          onClick={() => onImport(path, mode)}
+          variant="contained"
+          color={mode === "replace" ? "warning" : "primary"}
+          disabled={!preview || importing}
+        >
+          {importing ? <CircularProgress size={24} /> : "Import"}
+        </Button>
+      </DialogActions>
+    </Dialog>
+  )
+}
+
+interface PlaylistImportDialogProps {
+  open: boolean
+  onClose: () => void
+  importing: boolean
+  onImport: (path: string) => Promise<void>
+  progress: TaskProgress | null
+  onCancel: () => void
+}
+
+const PlaylistImportDialog: FC<PlaylistImportDialogProps> = ({
+  open,
+  onClose,
+  importing,
+  onImport,
+  progress,
+  onCancel,
+}) => {
+  const [path, setPath] = useState("")
+
+  useEffect(() => {
+    if (open) setPath("")
+  }, [open])
+
+  return (
+    <Dialog open={open} onClose={onClose} fullWidth maxWidth="xs">
+      <DialogTitle>Import Playlist</DialogTitle>
+      <DialogContent>
+        <Stack spacing={3} sx={{ mt: 1 }}>
+          <TextField
+            label="File"
+            value={path}
+            onChange={(e) => setPath(e.target.value)}
+            fullWidth
+            size="small"
+            autoFocus
+            placeholder="Path to .m3u, .m3u8, .xspf, .pls or .json"
+          />
+          {importing && <TaskProgressBar progress={progress} onCancel={onCancel} />}
+        </Stack>
+      </DialogContent>
+      <DialogActions>
+        <Button onClick={onClose} color="inherit">
+          Cancel
+        </Button>
+        <Button
+          onClick={() => onImport(path.trim())}
+          variant="contained"
+          disabled={!path.trim() || importing}
+        >
+          {importing ? <CircularProgress size={24} /> : "Import"}
+        </Button>
+      </DialogActions>
+    </Dialog>
+  )
+}
+
+interface LocalFoldersDialogProps {
+  open: boolean
+  onClose: () => void
+  folders: LocalFolders
+  scanning: boolean
+  onScan: (folders: LocalFolders) => Promise<void>
+  progress: TaskProgress | null
+  onCancel: () => void
+}
+
+const LocalFoldersDialog: FC<LocalFoldersDialogProps> = ({
+  open,
+  onClose,
+  folders,
+  scanning,
+  onScan,
+  progress,
+  onCancel,
+}) => {
+  const [paths, setPaths] = useState("")
+  const [watch, setWatch] = useState(false)
+  const [copy, setCopy] = useState(false)
+
+  useEffect(() => {
+    if (open) {
+      setPaths(folders.paths.join("\n"))
+      setWatch(folders.watch)
+      setCopy(folders.copy)
+    }
+  }, [open, folders])
+
+  const pathList = paths
+    .split("\n")
+    .map((p) => p.trim())
+    .filter(Boolean)
+
+  return (
+    <Dialog open={open} onClose={onClose} fullWidth maxWidth="xs">
+      <DialogTitle>Local Folders</DialogTitle>
+      <DialogContent>
+        <Stack spacing={3} sx={{ mt: 1 }}>
+          <TextField
+            label="Folders"
+            value={paths}
+            onChange={(e) => setPaths(e.target.value)}
+            fullWidth
+            multiline
+            minRows={2}
+            size="small"
+            autoFocus
+            placeholder="One folder per line"
+          />
+          <Stack direction="row" sx={{ justifyContent: "space-between", alignItems: "center" }}>
+            <Typography>Watch for changes</Typography>
+            <Switch checked={watch} onChange={(e) => setWatch(e.target.checked)} />
+          </Stack>
+          {/* Android can only play files inside the app directory, so they are always copied */}
+          {CurrentPlatform !== "android" && (
+            <Stack direction="row" sx={{ justifyContent: "space-between", alignItems: "center" }}>
+              <Box>
+                <Typography>Copy files</Typography>
+                <Typography color="text.secondary" variant="body2">
+                  Otherwise files are played from where they are
+                </Typography>
+              </Box>
+              <Switch checked={copy} onChange={(e) => setCopy(e.target.checked)} />
+            </Stack>
+          )}
+          {scanning && <TaskProgressBar progress={progress} onCancel={onCancel} />}
+        </Stack>
+      </DialogContent>
+      <DialogActions>
+        <Button onClick={onClose} color="inherit">
+          Cancel
+        </Button>
+        <Button
+          onClick={() => onScan({ paths: pathList, watch, copy })}
+          variant="contained"
+          disabled={pathList.length === 0 || scanning}
+        >
+          {scanning ? <CircularProgress size={24} /> : "Scan"}
+        </Button>
+      </DialogActions>
+    </Dialog>
+  )
+}
+
+const DUPLICATE_REASONS: Record<DuplicateReason, string> = {
+  same_id: "Same audio",
+  same_content: "Same file",
+  similar_title: "Similar title",
+  same_recording: "Same recording",
+}
+
+interface DuplicatesDialogProps {
+  open: boolean
+  onClose: () => void
+}
+
+const DuplicatesDialog: FC<DuplicatesDialogProps> = ({ open, onClose }) => {
+  const mergeDuplicates = useAppStore((state) => state.mergeDuplicates)
+  const fingerprintAudios = useAppStore((state) => state.fingerprintAudios)
+  const message = useMessage()
+  const [clusters, setClusters] = useState<DuplicateCluster[] | null>(null)
+  // Index of the entry to keep, per cluster
+  const [keepIndex, setKeepIndex] = useState<number[]>([])
+  const [merging, setMerging] = useState<number | null>(null)
+
+  useEffect(() => {
+    if (!open) return
+    setClusters(null)
+    // Fingerprints only back duplicate detection, so they are taken here
+    fingerprintAudios()
+      .then(() => find_duplicates())
+      .then((found) => {
+        setClusters(found)
+        setKeepIndex(found.map(() => 0))
+      })
+      .catch((e) => {
+        console.error(e)
+        message.error(`Failed to find duplicates: ${get_error_message(e)}`)
+        setClusters([])
+      })
+  }, [open])
+
+  const handleMerge = async (index: number) => {
+    if (!clusters) return
+    const audios = clusters[index].entries.map((e) => e.audio)
+    const keep = audios[keepIndex[index]]
+    setMerging(index)
+    try {
+      const removed = await mergeDuplicates(keep, audios.filter((a) => a !== keep))
+      message.success(`Merged into ${keep.audio.title}, ${removed} files removed`)
+      setClusters(clusters.filter((_, i) => i !== index))
+      setKeepIndex(keepIndex.filter((_, i) => i !== index))
+    } catch (e: unknown) {
+      console.error(e)
+      message.error(`Failed to merge: ${get_error_message(e)}`)
+    } finally {
+      setMerging(null)
+    }
+  }
+
+  return (
+    <Dialog open={open} onClose={onClose} fullWidth maxWidth="sm">
+      <DialogTitle>Duplicates</DialogTitle>
+      <DialogContent>
+        {clusters === null ? (
+          <Box sx={{ display: "flex", justifyContent: "center", py: 2 }}>
+            <CircularProgress />
+          </Box>
+        ) : clusters.length === 0 ? (
+          <Typography color="text.secondary">No duplicates found</Typography>
+        ) : (
+          <Stack spacing={2} divider={<Divider />} sx={{ mt: 1 }}>
+            {clusters.map((cluster, index) => (
+              <Stack key={cluster.entries[0].audio.path} spacing={1}>
+                <Typography color="text.secondary" variant="body2">
+                  {cluster.reasons.map((r) => DUPLICATE_REASONS[r]).join(", ")}
+                </Typography>
+                {cluster.entries.map((entry, i) => (
+                  <Stack
+                    key={entry.audio.path}
+                    direction="row"
+                    spacing={1}
+                    sx={{ alignItems: "center", cursor: "pointer" }}
+                    onClick={() => setKeepIndex(keepIndex.map((k, j) => (j === index ? i : k)))}
+                  >
+                    <Switch size="small" checked={keepIndex[index] === i} />
+                    <Box sx={{ minWidth: 0 }}>
+                      <Typography noWrap>{entry.audio.audio.title}</Typography>
+                      <Typography color="text.secondary" variant="body2" noWrap>
+                        {entry.audio.audio.platform}
+                        {entry.audio.info?.bitrate ? ` · ${entry.audio.info.bitrate} kbps` : ""}
+                        {` · ${entry.playlist_ids.length} playlists`}
+                      </Typography>
+                    </Box>
+                  </Stack>
+                ))}
+                <Box>
+                  <Button
+                    variant="outlined"
+                    size="small"
+                    onClick={() => handleMerge(index)}
+                    disabled={merging !== null}
+                  >
+                    {merging === index ? <CircularProgress size={20} /> : "Keep selected"}
+                  </Button>
+                </Box>
+              </Stack>
+            ))}
+          </Stack>
+        )}
+      </DialogContent>
+      <DialogActions>
+        <Button onClick={onClose} color="inherit">
+          Close
+        </Button>
+      </DialogActions>
+    </Dialog>
+  )
+}
+
 export default SettingsPage
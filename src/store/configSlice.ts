@@ -16,13 +16,33 @@ import {
   storage,
   app_version,
   app_dir,
-  get_web_url,
+  get_audio_url,
   remove_file,
   GistConfig,
   syncConfig,
   SyncError,
   get_device_id,
   TranscodeFormat,
+  set_sync_credentials,
+  ImportMode,
+  is_builtin,
+  LibraryChange,
+  LocalFolders,
+  LoudnessReport,
+  ReplayGainMode,
+  analyze_loudness,
+  SilenceBounds,
+  detect_silence,
+  trim_audio,
+  probe_audio,
+  listen_library_changes,
+  watch_local_folders,
+  unwatch_local_folders,
+  merge_duplicates,
+  fingerprint_audios,
+  sync_play_history,
+  sync_restore,
+  SYNC_FILE_NAME,
 } from "../api"
 import logger from "../utils/logger"
 
@@ -38,6 +58,17 @@ let backgroundSyncTimer: ReturnType<typeof setTimeout> | null = null
 /** Debounce interval (ms) — coalesces rapid background syncs */
 const BACKGROUND_SYNC_DEBOUNCE_MS = 2000
 
+/** Audio analyses run one at a time, after saves settle */
+let analysisTimer: ReturnType<typeof setTimeout> | null = null
+let loudnessInProgress = false
+let silenceInProgress = false
+let fingerprintInProgress = false
+
+/** Paths an analysis could not decode, not retried until restart */
+const loudnessSkipped = new Set<string>()
+const silenceSkipped = new Set<string>()
+const fingerprintSkipped = new Set<string>()
+
 /** Promise chain to serialize sync execution.
  * Reset after each sync completes to prevent unbounded chain growth. */
 let syncMutex: Promise<void> = Promise.resolve()
@@ -111,6 +142,18 @@ export interface ConfigSliceState {
   // Transcoding
   transcodeFormat: TranscodeFormat
 
+  // Drop the video of downloaded MP4/WebM files
+  extractAudio: boolean
+
+  // Local library
+  localFolders: LocalFolders
+
+  // Loudness normalization
+  replayGain: ReplayGainMode
+
+  // Seek past leading and trailing silence
+  skipSilence: boolean
+
   // App info
   app_dir: string | null
   app_version: string | null
@@ -150,10 +193,36 @@ export interface ConfigSliceActions {
   // Gist actions
   setGistConfig: (config: GistConfig | null) => void
   syncGithub: (manual?: boolean, forcePush?: boolean, forcePull?: boolean) => Promise<void>
-  importConfig: (config: Config) => Promise<void>
+  importConfig: (config: Config, mode?: ImportMode) => Promise<void>
 
   // Transcoding
   setTranscodeFormat: (format: TranscodeFormat) => void
+  setExtractAudio: (extract: boolean) => void
+
+  // Local library
+  setLocalFolders: (folders: LocalFolders) => Promise<void>
+  applyLibraryChange: (change: LibraryChange) => Promise<void>
+
+  // Loudness normalization
+  setReplayGain: (mode: ReplayGainMode) => void
+  analyzeLoudness: () => Promise<void>
+  applyLoudnessReport: (report: LoudnessReport) => Promise<void>
+
+  // Silence
+  setSkipSilence: (skip: boolean) => void
+  detectSilence: () => Promise<void>
+  applySilenceBounds: (bounds: Record<string, SilenceBounds>) => Promise<void>
+  trimSilence: (audioId: string) => Promise<void>
+
+  // Lyrics
+  setAudioLyrics: (audio: LocalAudio) => Promise<void>
+
+  // Duplicates
+  mergeDuplicates: (keep: LocalAudio, duplicates: LocalAudio[]) => Promise<number>
+  /** Fingerprint the audios that have none, run when duplicates are looked for */
+  fingerprintAudios: () => Promise<void>
+  applyFingerprints: (fingerprints: Record<string, string>) => Promise<void>
+  restoreSyncRevision: (sha: string) => Promise<void>
 }
 
 export type ConfigSlice = ConfigSliceState & ConfigSliceActions
@@ -184,6 +253,10 @@ export const createConfigSlice: StateCreator<AppState, [], [], ConfigSlice> = (s
   gistConfig: storage.getGistConfig(),
   theme: storage.getTheme(),
   transcodeFormat: storage.getTranscodeFormat(),
+  extractAudio: storage.getExtractAudio(),
+  localFolders: storage.getLocalFolders(),
+  replayGain: storage.getReplayGain(),
+  skipSilence: storage.getSkipSilence(),
   app_dir: null,
   app_version: null,
   viewingPlaylistId: null,
@@ -223,13 +296,20 @@ export const createConfigSlice: StateCreator<AppState, [], [], ConfigSlice> = (s
             restoredAudio = audio
             restoredPlaylistId = storedPlaylistId
             initialDuration = audio.audio.duration || 0
-            audioElement.src = await get_web_url(audio.path)
+            audioElement.src = await get_audio_url(audio)
           }
         }
       }
 
       if (!listenersInitialized) {
         setupAudioListeners()
+        listen_library_changes((change) => get().applyLibraryChange(change))
+        const { localFolders } = get()
+        if (localFolders.watch && localFolders.paths.length > 0) {
+          watch_local_folders(localFolders.paths, localFolders.copy).catch((error) =>
+            log.error("Failed to watch local folders:", error),
+          )
+        }
       }
 
       set({
@@ -243,6 +323,14 @@ export const createConfigSlice: StateCreator<AppState, [], [], ConfigSlice> = (s
         app_dir: dir,
         app_version: version,
       })
+
+      // Measure new downloads in the background
+      if (get().replayGain !== "off") {
+        get().analyzeLoudness()
+      }
+      if (get().skipSilence) {
+        get().detectSilence()
+      }
     } catch (error) {
       console.error("Failed to load config:", error)
       set({ isConfigLoading: false })
@@ -273,6 +361,22 @@ export const createConfigSlice: StateCreator<AppState, [], [], ConfigSlice> = (s
 
       // 3. Trigger debounced background remote sync
       scheduleDebouncedSync(() => get().syncGithub(false))
+
+      // 4. Analyze new audios for the enabled playback features
+      const audios = stampedConfig.playlists.flatMap((p) => p.audios)
+      const needsLoudness =
+        get().replayGain !== "off" &&
+        audios.some((a) => !a.loudness && !loudnessSkipped.has(a.path))
+      const needsSilence =
+        get().skipSilence && audios.some((a) => a.end_ms == null && !silenceSkipped.has(a.path))
+      if (needsLoudness || needsSilence) {
+        if (analysisTimer !== null) clearTimeout(analysisTimer)
+        analysisTimer = setTimeout(() => {
+          analysisTimer = null
+          if (needsLoudness) get().analyzeLoudness()
+          if (needsSilence) get().detectSilence()
+        }, BACKGROUND_SYNC_DEBOUNCE_MS)
+      }
     } catch (error) {
       log.error("Failed to save config:", error)
       throw error
@@ -413,7 +517,7 @@ export const createConfigSlice: StateCreator<AppState, [], [], ConfigSlice> = (s
 
       // 2. Check and delete files from removed playlist
       for (const audio of playlistToRemove.audios) {
-        if (!usedAudioPaths.has(audio.path)) {
+        if (!audio.external_path && !usedAudioPaths.has(audio.path)) {
           await remove_file(audio.path)
         }
         if (audio.cover_path && !usedCoverPaths.has(audio.cover_path)) {
@@ -588,7 +692,7 @@ export const createConfigSlice: StateCreator<AppState, [], [], ConfigSlice> = (s
         }
       }
 
-      if (!usedAudioPaths.has(deletedAudio.path)) {
+      if (!deletedAudio.external_path && !usedAudioPaths.has(deletedAudio.path)) {
         await remove_file(deletedAudio.path)
       }
 
@@ -730,13 +834,23 @@ export const createConfigSlice: StateCreator<AppState, [], [], ConfigSlice> = (s
   },
 
   syncGithub: async (manual = false, forcePush = false, forcePull = false) => {
-    const { config, gistConfig, isSyncing } = get()
+    const { config, isSyncing } = get()
+    let { gistConfig } = get()
 
     if (!gistConfig) {
       log.info("No repository sync config, skipping sync")
       return
     }
 
+    // Move tokens saved by older versions into the Rust credential store
+    if (gistConfig.githubToken) {
+      log.info("Migrating GitHub token to secure storage")
+      await set_sync_credentials(gistConfig.githubToken, gistConfig.repoUrl)
+      const { githubToken: _, ...rest } = gistConfig
+      gistConfig = rest
+      get().setGistConfig(gistConfig)
+    }
+
     if (!manual && isSyncing) {
       log.info("Sync already in progress, skipping")
       return
@@ -767,6 +881,16 @@ export const createConfigSlice: StateCreator<AppState, [], [], ConfigSlice> = (s
       }
 
       get().setGistConfig(newGistConfig)
+
+      if (newGistConfig.syncHistory) {
+        try {
+          const count = await sync_play_history(get().config._deviceId)
+          log.info(`Synced ${count} play history records`)
+        } catch (error) {
+          log.warn("Failed to sync play history:", error)
+        }
+      }
+
       set({ syncStatus: "success" })
       log.info("Sync completed successfully")
 
@@ -799,10 +923,24 @@ export const createConfigSlice: StateCreator<AppState, [], [], ConfigSlice> = (s
     }
   },
 
-  importConfig: async (importedConfig: Config) => {
+  importConfig: async (importedConfig: Config, mode: ImportMode = "merge") => {
     const { config } = get()
     if (!config) return
 
+    if (mode === "replace") {
+      // Keep built-in playlists the backup does not contain
+      const importedIds = new Set(importedConfig.playlists.map((p) => p.id))
+      const keptBuiltins = config.playlists.filter(
+        (p) => is_builtin(p.id) && !importedIds.has(p.id),
+      )
+      await get().saveConfig({
+        ...config,
+        playlists: [...keptBuiltins, ...importedConfig.playlists],
+      })
+      return
+    }
+
+    // "add_missing" configs only contain new playlists, so merging just adds them
     let updatedPlaylists = [...config.playlists]
 
     for (const playlist of importedConfig.playlists) {
@@ -874,4 +1012,251 @@ export const createConfigSlice: StateCreator<AppState, [], [], ConfigSlice> = (s
     storage.setTranscodeFormat(format)
     set({ transcodeFormat: format })
   },
+
+  setExtractAudio: (extract: boolean) => {
+    storage.setExtractAudio(extract)
+    set({ extractAudio: extract })
+  },
+
+  setLocalFolders: async (folders: LocalFolders) => {
+    storage.setLocalFolders(folders)
+    set({ localFolders: folders })
+
+    // Restart watchers so removed folders and a changed copy mode take effect
+    await unwatch_local_folders()
+    if (folders.watch && folders.paths.length > 0) {
+      await watch_local_folders(folders.paths, folders.copy)
+    }
+  },
+
+  setReplayGain: (mode: ReplayGainMode) => {
+    storage.setReplayGain(mode)
+    set({ replayGain: mode })
+    if (mode !== "off") {
+      get().analyzeLoudness()
+    }
+  },
+
+  analyzeLoudness: async () => {
+    if (loudnessInProgress) return
+    loudnessInProgress = true
+    try {
+      const pending = get()
+        .config.playlists.flatMap((p) => p.audios)
+        .filter((a) => !a.loudness)
+        .map((a) => a.path)
+      const report = await analyze_loudness()
+      for (const path of pending) {
+        if (!report.tracks[path]) loudnessSkipped.add(path)
+      }
+      await get().applyLoudnessReport(report)
+    } catch (error) {
+      log.error("Loudness analysis failed:", error)
+    } finally {
+      loudnessInProgress = false
+    }
+  },
+
+  applyLoudnessReport: async (report: LoudnessReport) => {
+    const { config } = get()
+    let changed = false
+
+    const updatedPlaylists = config.playlists.map((p) => {
+      const album = p.id ? report.albums[p.id] : undefined
+      return {
+        ...p,
+        audios: p.audios.map((a) => {
+          const loudness = report.tracks[a.path] ?? a.loudness
+          if (!loudness) return a
+          const updated = album
+            ? { ...loudness, album_gain: album[0], album_peak: album[1] }
+            : loudness
+          // Album gains are recomputed on every run, only save what moved
+          if (
+            a.loudness?.integrated === updated.integrated &&
+            a.loudness?.album_gain === updated.album_gain &&
+            a.loudness?.album_peak === updated.album_peak
+          ) {
+            return a
+          }
+          changed = true
+          return { ...a, loudness: updated }
+        }),
+      }
+    })
+
+    if (changed) {
+      await get().saveConfig({ ...config, playlists: updatedPlaylists })
+    }
+  },
+
+  setSkipSilence: (skip: boolean) => {
+    storage.setSkipSilence(skip)
+    set({ skipSilence: skip })
+    if (skip) {
+      get().detectSilence()
+    }
+  },
+
+  detectSilence: async () => {
+    if (silenceInProgress) return
+    silenceInProgress = true
+    try {
+      const pending = get()
+        .config.playlists.flatMap((p) => p.audios)
+        .filter((a) => a.end_ms == null)
+        .map((a) => a.path)
+      const bounds = await detect_silence()
+      for (const path of pending) {
+        if (!bounds[path]) silenceSkipped.add(path)
+      }
+      await get().applySilenceBounds(bounds)
+    } catch (error) {
+      log.error("Silence detection failed:", error)
+    } finally {
+      silenceInProgress = false
+    }
+  },
+
+  applySilenceBounds: async (bounds: Record<string, SilenceBounds>) => {
+    const { config } = get()
+    if (Object.keys(bounds).length === 0) return
+
+    const updatedPlaylists = config.playlists.map((p) => ({
+      ...p,
+      audios: p.audios.map((a) => (bounds[a.path] ? { ...a, ...bounds[a.path] } : a)),
+    }))
+    await get().saveConfig({ ...config, playlists: updatedPlaylists })
+  },
+
+  fingerprintAudios: async () => {
+    if (fingerprintInProgress) return
+    fingerprintInProgress = true
+    try {
+      const pending = get()
+        .config.playlists.flatMap((p) => p.audios)
+        .filter((a) => !a.fingerprint && !fingerprintSkipped.has(a.path))
+        .map((a) => a.path)
+      if (pending.length === 0) return
+      const fingerprints = await fingerprint_audios()
+      for (const path of pending) {
+        if (!fingerprints[path]) fingerprintSkipped.add(path)
+      }
+      await get().applyFingerprints(fingerprints)
+    } catch (error) {
+      log.error("Fingerprinting failed:", error)
+    } finally {
+      fingerprintInProgress = false
+    }
+  },
+
+  applyFingerprints: async (fingerprints: Record<string, string>) => {
+    const { config } = get()
+    if (Object.keys(fingerprints).length === 0) return
+
+    const updatedPlaylists = config.playlists.map((p) => ({
+      ...p,
+      audios: p.audios.map((a) =>
+        fingerprints[a.path] ? { ...a, fingerprint: fingerprints[a.path] } : a,
+      ),
+    }))
+    await get().saveConfig({ ...config, playlists: updatedPlaylists })
+  },
+
+  trimSilence: async (audioId: string) => {
+    const { config } = get()
+    const audio = config.playlists
+      .flatMap((p) => p.audios)
+      .find((a) => a.audio.id === audioId && a.end_ms != null)
+    if (!audio || audio.end_ms == null) return
+
+    const start = audio.start_ms ?? 0
+    const end = audio.end_ms
+    const path = await trim_audio(audio.path, start, end)
+    const info = await probe_audio(path).catch(() => undefined)
+    log.info(`Trimmed ${audio.audio.title} to ${path}`)
+
+    // Every playlist holding the file now points at the trimmed copy
+    const trimmed = (a: LocalAudio): LocalAudio => ({
+      ...a,
+      path,
+      external_path: undefined,
+      info,
+      start_ms: 0,
+      end_ms: end - start,
+      audio: { ...a.audio, duration: Math.round((end - start) / 1000), format: "Mp3" },
+    })
+    const updatedPlaylists = config.playlists.map((p) => ({
+      ...p,
+      audios: p.audios.map((a) => (a.path === audio.path ? trimmed(a) : a)),
+    }))
+    await get().saveConfig({ ...config, playlists: updatedPlaylists })
+  },
+
+  setAudioLyrics: async (audio: LocalAudio) => {
+    const { config, currentAudio } = get()
+    const lyrics_path = audio.lyrics_path
+    // Lyrics are stored by audio id, so every copy of the audio shares them
+    const updatedPlaylists = config.playlists.map((p) => ({
+      ...p,
+      audios: p.audios.map((a) => (a.audio.id === audio.audio.id ? { ...a, lyrics_path } : a)),
+    }))
+    if (currentAudio?.audio.id === audio.audio.id) {
+      const updated = { ...currentAudio, lyrics_path }
+      storage.setCurrentAudio(updated)
+      set({ currentAudio: updated })
+    }
+    await get().saveConfig({ ...config, playlists: updatedPlaylists })
+  },
+
+  mergeDuplicates: async (keep: LocalAudio, duplicates: LocalAudio[]) => {
+    const { config, currentAudio } = get()
+    const { playlists, removed } = await merge_duplicates(keep, duplicates)
+    // The playing copy may be one of the deleted files
+    if (currentAudio && duplicates.some((d) => d.path === currentAudio.path)) {
+      const kept = playlists.flatMap((p) => p.audios).find((a) => a.path === keep.path) ?? keep
+      storage.setCurrentAudio(kept)
+      set({ currentAudio: kept })
+    }
+    await get().saveConfig({ ...config, playlists })
+    // Only once the playlists no longer point at them
+    for (const path of removed) {
+      await remove_file(path).catch((error) => log.warn(`Failed to remove ${path}:`, error))
+    }
+    log.info(`Merged ${duplicates.length} duplicates into ${keep.audio.title}`)
+    return removed.length
+  },
+
+  restoreSyncRevision: async (sha: string) => {
+    const { config } = get()
+    const { config: restored } = await sync_restore(sha, SYNC_FILE_NAME)
+    // saveConfig renews the stamps, so the restored revision wins the next sync
+    // instead of losing to the newer remote copy
+    await get().saveConfig({ ...config, playlists: restored.playlists })
+    log.info(`Restored sync revision ${sha.slice(0, 7)}`)
+  },
+
+  applyLibraryChange: async (change: LibraryChange) => {
+    const { config } = get()
+    const playlist = config.playlists.find((p) => p.id === change.playlist_id)
+    // Folders are only tracked once they were scanned into a playlist
+    if (!playlist) return
+
+    log.info(
+      `Library change in ${playlist.title}: +${change.added.length} ~${change.updated.length} -${change.removed.length}`,
+    )
+    const removed = new Set(change.removed)
+    const updated = new Map(change.updated.map((a) => [a.audio.id, a]))
+    // Changed files replace their entry in place, new ones go last
+    const audios = playlist.audios
+      .filter((a) => !removed.has(a.audio.id))
+      .map((a) => updated.get(a.audio.id) ?? a)
+    const known = new Set(audios.map((a) => a.audio.id))
+    audios.push(...change.added.filter((a) => !known.has(a.audio.id)))
+
+    await get().saveConfig({
+      ...config,
+      playlists: config.playlists.map((p) => (p.id === playlist.id ? { ...p, audios } : p)),
+    })
+  },
 })
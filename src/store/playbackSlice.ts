@@ -1,6 +1,14 @@
 import { StateCreator } from "zustand"
 import type { AppState } from "./index"
-import { LocalAudio, PlayMode, get_web_url, storage } from "../api"
+import {
+  LocalAudio,
+  PlayMode,
+  get_audio_url,
+  get_web_url,
+  record_play,
+  replay_gain,
+  storage,
+} from "../api"
 import { initBluetoothListener } from "../utils/bluetooth"
 import {
   initMediaSession,
@@ -20,6 +28,23 @@ const getAudio = (): HTMLAudioElement => {
 
 const MAX_HISTORY_SIZE = 64
 
+/** Add how far an audio was played to the play history, ignoring failures */
+const recordPlay = (audio: LocalAudio, playlistId: string, element: HTMLAudioElement) => {
+  const position = element.currentTime
+  if (!(position > 0)) return
+  const duration = Number.isFinite(element.duration) ? element.duration : position
+  record_play(audio.audio.id, audio.audio.platform, playlistId, position, duration).catch((e) =>
+    console.error("Failed to record play:", e),
+  )
+}
+
+/**
+ * Element volume for a ReplayGain gain in dB.
+ * The element cannot amplify, so quiet tracks stay at full volume.
+ */
+const gainToVolume = (gain: number | null): number =>
+  gain === null ? 1 : Math.min(1, Math.pow(10, gain / 20))
+
 // ============================================
 // Playback Slice State Interface
 // ============================================
@@ -114,6 +139,18 @@ export const createPlaybackSlice: StateCreator<AppState, [], [], PlaybackSlice>
     // Time update
     audioElement.addEventListener("timeupdate", () => {
       const { currentTime, duration } = audioElement
+
+      // Treat trailing silence as the end of the track
+      const { skipSilence, currentAudio, isPlaying } = get()
+      const endMs = currentAudio?.end_ms
+      const pastEnd = !!endMs && currentTime * 1000 >= endMs && endMs < duration * 1000
+      if (skipSilence && isPlaying && pastEnd) {
+        // Pausing first keeps later timeupdates from skipping twice
+        audioElement.pause()
+        get().playNext(true)
+        return
+      }
+
       set({
         currentTime,
         duration: duration || 0,
@@ -147,6 +184,10 @@ export const createPlaybackSlice: StateCreator<AppState, [], [], PlaybackSlice>
 
     // Audio ended - auto play next
     audioElement.addEventListener("ended", () => {
+      const { currentAudio, currentPlaylistId } = get()
+      if (currentAudio && currentPlaylistId) {
+        recordPlay(currentAudio, currentPlaylistId, audioElement)
+      }
       get().playNext(true)
     })
 
@@ -217,7 +258,18 @@ export const createPlaybackSlice: StateCreator<AppState, [], [], PlaybackSlice>
     addToHistory: boolean = true,
     autoPlay: boolean = true,
   ) => {
-    const { currentAudio, audioElement, playbackHistory, deviceListenersInitialized } = get()
+    const {
+      currentAudio,
+      currentPlaylistId,
+      audioElement,
+      playbackHistory,
+      deviceListenersInitialized,
+    } = get()
+
+    // An audio that ended was already recorded
+    if (currentAudio && currentPlaylistId && audioElement && !audioElement.ended) {
+      recordPlay(currentAudio, currentPlaylistId, audioElement)
+    }
 
     // Initialize device listeners on first play
     if (!deviceListenersInitialized) {
@@ -235,7 +287,7 @@ export const createPlaybackSlice: StateCreator<AppState, [], [], PlaybackSlice>
     }
 
     try {
-      const url = await get_web_url(audio.path)
+      const url = await get_audio_url(audio)
       set({
         currentAudio: audio,
         currentPlaylistId: playlistId,
@@ -277,6 +329,16 @@ export const createPlaybackSlice: StateCreator<AppState, [], [], PlaybackSlice>
         // Pause current playback to avoid conflicts
         audioElement.pause()
 
+        // Loudness normalization, a failed lookup just plays unchanged
+        const { replayGain } = get()
+        const gain =
+          replayGain === "off"
+            ? null
+            : await replay_gain(playlistId, audio.audio.id, replayGain === "album").catch(
+                () => null,
+              )
+        audioElement.volume = gainToVolume(gain)
+
         // Set new source and load
         audioElement.src = url
         audioElement.load()
@@ -300,6 +362,11 @@ export const createPlaybackSlice: StateCreator<AppState, [], [], PlaybackSlice>
             audioElement.addEventListener("error", onError, { once: true })
           })
 
+          // Start after leading silence
+          if (get().skipSilence && audio.start_ms) {
+            audioElement.currentTime = audio.start_ms / 1000
+          }
+
           // Now play if autoPlay is true
           if (autoPlay) {
             await audioElement.play()
@@ -326,7 +393,7 @@ export const createPlaybackSlice: StateCreator<AppState, [], [], PlaybackSlice>
     if (!audioElement) return
 
     try {
-      const url = await get_web_url(audio.path)
+      const url = await get_audio_url(audio)
       audioElement.src = url
       audioElement.load()
     } catch (error) {
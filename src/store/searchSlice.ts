@@ -5,15 +5,35 @@ import {
   LocalAudio,
   Audio,
   extract_audios,
+  search_platform,
   exists_audio,
   exists_cover,
   download_cover,
   get_web_url,
   download_audio,
   transcode_audio,
+  probe_audio,
   transcode_format_to_audio_format,
+  get_error_message,
 } from "../api"
 
+/**
+ * Extract a URL or ID, falling back to a keyword search on the platforms
+ * for text that is not a URL and could not be extracted.
+ */
+const extractOrSearch = async (
+  text: string,
+): Promise<[playlist: Playlist, default_audio: number | null]> => {
+  try {
+    return await extract_audios(text)
+  } catch (e) {
+    if (/^https?:\/\//i.test(text.trim())) throw e
+    const audios = await search_platform(text.trim())
+    if (audios.length === 0) throw e
+    return [{ title: text.trim(), audios, platform: audios[0].platform }, null]
+  }
+}
+
 // Module-level abort controllers
 const abortControllers = new Map<string, AbortController>()
 
@@ -137,7 +157,7 @@ export const createSearchSlice: StateCreator<AppState, [], [], SearchSlice> = (s
     })
 
     try {
-      const [playlist, defaultAudioIndex] = await extract_audios(url)
+      const [playlist, defaultAudioIndex] = await extractOrSearch(url)
 
       // Check existing audios
       const downloadedIds = new Set<string>()
@@ -240,7 +260,7 @@ export const createSearchSlice: StateCreator<AppState, [], [], SearchSlice> = (s
       if (controller.signal.aborted) throw new Error("ABORTED")
 
       // Perform download
-      const downloadPromise = download_audio(audio)
+      const downloadPromise = download_audio(audio, get().extractAudio)
 
       const result = await new Promise<LocalAudio>((resolve, reject) => {
         const onAbort = () => reject(new Error("ABORTED"))
@@ -263,22 +283,26 @@ export const createSearchSlice: StateCreator<AppState, [], [], SearchSlice> = (s
       let finalResult = result
       const format = get().transcodeFormat
       if (format && format !== "original") {
-        // Skip if already in target format (e.g. source is Mp3 and target is mp3)
+        // Skip if already in target format (e.g. source is Mp3 and target is mp3).
+        // The Rust side also compares the probed codec and returns the same path.
         if (result.audio.format?.toLowerCase() === format) {
           console.info(`Audio already in ${format} format, skipping transcode`)
         } else {
           try {
             const newPath = await transcode_audio(result.path, format)
-            finalResult = {
-              ...result,
-              path: newPath,
-              audio: {
-                ...result.audio,
-                format: transcode_format_to_audio_format(format),
-              },
+            if (newPath !== result.path) {
+              finalResult = {
+                ...result,
+                path: newPath,
+                audio: {
+                  ...result.audio,
+                  format: transcode_format_to_audio_format(format),
+                },
+                info: await probe_audio(newPath).catch(() => undefined),
+              }
             }
           } catch (e) {
-            console.error(`Transcode failed, keeping original: ${e}`)
+            console.error(`Transcode failed, keeping original: ${get_error_message(e)}`)
           }
         }
       }
@@ -306,7 +330,7 @@ export const createSearchSlice: StateCreator<AppState, [], [], SearchSlice> = (s
 
       return finalResult
     } catch (error) {
-      const errorMessage = error instanceof Error ? error.message : String(error)
+      const errorMessage = get_error_message(error)
       const {
         searchDownloadingIds: currDownloading,
         searchFailedIds: currFailed,